@@ -1,7 +1,7 @@
 use super::models;
 use chrono::Datelike;
 use coffeeshop::{
-    prelude::{CoffeeMachineError, Machine, QueryType, ValidationError},
+    prelude::{CoffeeMachineError, Machine, Principal, QueryType, ValidationError},
     reexports::{async_trait, axum},
 };
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -23,6 +23,7 @@ impl Machine<models::HelloQuery, models::HelloPayload, models::HelloResult> for
         &self,
         query: &models::HelloQuery,
         input: Option<&models::HelloPayload>,
+        _principal: Option<&Principal>,
     ) -> Result<models::HelloResult, CoffeeMachineError> {
         let year = chrono::Utc::now()
             .year()
@@ -3,8 +3,9 @@ use coffeeshop::prelude::*;
 use serde::{Deserialize, Serialize};
 
 /// Languages supported by the hello world service.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub enum AcceptedLanguage {
+    #[default]
     #[serde(rename = "en")]
     English,
     #[serde(rename = "es")]
@@ -13,12 +14,6 @@ pub enum AcceptedLanguage {
     Chinese,
 }
 
-impl Default for AcceptedLanguage {
-    fn default() -> Self {
-        Self::English
-    }
-}
-
 impl AcceptedLanguage {
     /// Get the greeting message for the language.
     pub fn greeting(&self) -> &'static str {
@@ -1,6 +1,11 @@
 use clap::Parser;
 
-use crate::CoffeeShopError;
+use crate::{
+    helpers::{self, rate_limit},
+    logger::LogFormat,
+    models::message::Principal,
+    CoffeeShopError,
+};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
 
 /// The default host address for the Waiter, which is to listen on all interfaces.
@@ -18,6 +23,9 @@ const MULTICAST_PORT: u16 = 65355;
 /// The number of Baristas to initiate.
 const DEFAULT_BARISTAS: u16 = 1;
 
+/// The number of tickets each Barista processes at a time.
+const DEFAULT_BARISTA_CONCURRENCY: u16 = 1;
+
 /// The default partition key (Primary Key) to use with the DynamoDB Table.
 ///
 /// This must be set to match the table's partition key.
@@ -30,6 +38,38 @@ const DEFAULT_RESULT_TTL: f32 = 7200.;
 /// requests with a `429 Too Many Requests` status code.
 const MAX_TICKETS: usize = 1024;
 
+/// The default interval in seconds between samples of the AWS SQS queue depth.
+const DEFAULT_QUEUE_DEPTH_INTERVAL: f32 = 5.;
+
+/// The default header to read the API key from for rate limiting.
+const DEFAULT_RATE_LIMIT_HEADER: &str = "x-api-key";
+
+/// The default header to read the API key from for authentication.
+const DEFAULT_API_KEY_HEADER: &str = "x-api-key";
+
+/// The default maximum number of times a ticket is attempted, if the machine keeps
+/// failing with retryable errors.
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The default delay in seconds before the first retry of a ticket; this doubles with
+/// every subsequent attempt.
+const DEFAULT_RETRY_BACKOFF: f32 = 1.;
+
+/// The default number of seconds the tickets in flight are given to complete on
+/// shutdown, before they are returned to the queue.
+const DEFAULT_DRAIN_TIMEOUT: f32 = 30.;
+
+/// Parse a positive, finite number of seconds from the command line.
+fn parse_positive_secs(value: &str) -> Result<f32, String> {
+    let secs = value.parse::<f32>().map_err(|err| err.to_string())?;
+
+    if secs.is_finite() && secs > 0. {
+        Ok(secs)
+    } else {
+        Err(format!("must be positive number, found {secs}."))
+    }
+}
+
 /// Simple program to greet a person
 #[derive(Parser, Debug, PartialEq)]
 #[command(version, about, long_about = None)]
@@ -51,13 +91,139 @@ pub struct Config {
     pub multicast_port: u16,
 
     /// The number of Baristas to initiate.
+    ///
+    /// If [`Config::max_baristas`] is set, this is the minimum number of Baristas kept
+    /// serving even when the AWS SQS queue is idle.
     #[arg(long, default_value_t = DEFAULT_BARISTAS, alias = "workers")]
     pub baristas: u16,
 
+    /// The maximum number of Baristas to scale up to when there is a backlog in the
+    /// AWS SQS queue.
+    ///
+    /// If not set, the number of Baristas is fixed at [`Config::baristas`].
+    #[arg(long, default_value = None)]
+    pub max_baristas: Option<u16>,
+
+    /// The maximum number of tickets each Barista keeps in flight at a time.
+    ///
+    /// Raising this is cheaper than adding more Baristas for I/O-bound machines, as
+    /// each Barista polls the AWS SQS queue on its own.
+    #[arg(long, default_value_t = DEFAULT_BARISTA_CONCURRENCY)]
+    pub barista_concurrency: u16,
+
+    /// The number of threads to run [`BlockingMachine`](crate::models::BlockingMachine)s
+    /// on, across all the Baristas.
+    ///
+    /// If not set, this defaults to the number of logical CPUs.
+    #[arg(long, default_value = None)]
+    pub blocking_threads: Option<usize>,
+
     /// Maximum number of outstanding tickets.
     #[arg(long, default_value_t = MAX_TICKETS)]
     pub max_tickets: usize,
 
+    /// The approximate number of messages in the AWS SQS queue, across all the shops,
+    /// beyond which new synchronous requests are rejected with a
+    /// `503 Service Unavailable` status code.
+    ///
+    /// If not set, the queue depth is not monitored.
+    #[arg(long, default_value = None)]
+    pub max_queue_depth: Option<usize>,
+
+    /// The number of seconds between samples of the AWS SQS queue depth.
+    #[arg(long, default_value_t = DEFAULT_QUEUE_DEPTH_INTERVAL, value_parser = parse_positive_secs)]
+    pub queue_depth_interval: f32,
+
+    /// Whether to keep accepting asynchronous requests when the AWS SQS queue depth
+    /// exceeds [`Config::max_queue_depth`].
+    #[arg(long, default_value_t = false)]
+    pub queue_depth_accepts_async: bool,
+
+    /// The number of requests per second each client is allowed to make on average.
+    ///
+    /// Clients exceeding this are rejected with a `429 Too Many Requests` status code.
+    /// If not set, clients are not rate limited.
+    #[arg(long, default_value = None)]
+    pub rate_limit: Option<f32>,
+
+    /// The number of requests each client is allowed to make in a burst.
+    ///
+    /// Defaults to one second worth of [`Config::rate_limit`], or at least `1`.
+    #[arg(long, default_value = None)]
+    pub rate_limit_burst: Option<u32>,
+
+    /// How to identify a client for rate limiting.
+    #[arg(long, value_enum, default_value_t = rate_limit::RateLimitKey::default())]
+    pub rate_limit_key: rate_limit::RateLimitKey,
+
+    /// The header to read the API key from, if [`Config::rate_limit_key`] is `api-key`.
+    #[arg(long, default_value = DEFAULT_RATE_LIMIT_HEADER)]
+    pub rate_limit_header: String,
+
+    /// Whether to share the rate limits across all the shops through the DynamoDB
+    /// table, instead of keeping them in memory for each shop.
+    #[arg(long, default_value_t = false)]
+    pub rate_limit_shared: bool,
+
+    /// A file containing the accepted API keys, one `name:key` per line.
+    ///
+    /// If any API keys or JSON Web Token keys are configured, all requests except the
+    /// health probes, `/status` (see [`Config::public_status`]) and `/metrics` (see
+    /// [`Config::public_metrics`]) must be authenticated.
+    #[arg(long, default_value = None)]
+    pub api_keys_file: Option<std::path::PathBuf>,
+
+    /// An environment variable containing the accepted API keys, as comma separated
+    /// `name:key` entries.
+    #[arg(long, default_value = None)]
+    pub api_keys_env: Option<String>,
+
+    /// The header to read the API key from.
+    #[arg(long, default_value = DEFAULT_API_KEY_HEADER)]
+    pub api_key_header: String,
+
+    /// An environment variable containing the secret to verify `HS256` JSON Web
+    /// Tokens with.
+    #[arg(long, default_value = None)]
+    pub jwt_secret_env: Option<String>,
+
+    /// A PEM file containing the RSA public key to verify `RS256` JSON Web Tokens with.
+    #[arg(long, default_value = None)]
+    pub jwt_public_key_file: Option<std::path::PathBuf>,
+
+    /// The required `iss` claim of JSON Web Tokens, if any.
+    #[arg(long, default_value = None)]
+    pub jwt_issuer: Option<String>,
+
+    /// The required `aud` claim of JSON Web Tokens, if any.
+    #[arg(long, default_value = None)]
+    pub jwt_audience: Option<String>,
+
+    /// Whether `/status` is accessible without authentication.
+    #[arg(long, default_value_t = false)]
+    pub public_status: bool,
+
+    /// Whether `/metrics` is accessible without authentication, e.g. for a Prometheus
+    /// server that cannot authenticate.
+    #[arg(long, default_value_t = false)]
+    pub public_metrics: bool,
+
+    /// An environment variable containing the secret to sign ticket tokens with.
+    ///
+    /// If set, clients are given signed tokens instead of the raw AWS SQS message IDs
+    /// as tickets.
+    #[arg(long, default_value = None)]
+    pub ticket_token_secret_env: Option<String>,
+
+    /// The time in seconds a ticket token remains valid; defaults to the result TTL.
+    #[arg(long, default_value = None, value_parser = parse_positive_secs)]
+    pub ticket_token_ttl: Option<f32>,
+
+    /// The principals that can access tickets owned by anyone, as comma separated
+    /// `api_key:<name>` or `jwt:<subject>` entries.
+    #[arg(long, value_delimiter = ',')]
+    pub admin_principals: Vec<String>,
+
     /// The AWS DynamoDB table to use.
     #[arg(long, default_value = None)]
     pub dynamodb_table: Option<String>,
@@ -73,9 +239,44 @@ pub struct Config {
 
     /// The maximum time a ticket can be processed before it is killed by the
     /// HTTP server.
+    ///
+    /// This is also enforced by the baristas, which cancel the machine call and
+    /// report a `504 Gateway Timeout` once this is exceeded.
     #[arg(long, default_value = None)]
     pub max_execution_time: Option<f32>,
 
+    /// The maximum number of times a ticket is attempted if the machine fails with a
+    /// [retryable](crate::errors::ErrorSchema::retryable) error, unless overridden by
+    /// [`QueryType::get_max_attempts`](crate::models::message::QueryType::get_max_attempts).
+    #[arg(long, default_value_t = DEFAULT_MAX_ATTEMPTS)]
+    pub max_attempts: u32,
+
+    /// The delay in seconds before a failed ticket is retried for the first time; this
+    /// doubles with every subsequent attempt.
+    #[arg(long, default_value_t = DEFAULT_RETRY_BACKOFF)]
+    pub retry_backoff: f32,
+
+    /// The number of seconds the tickets in flight are given to complete on shutdown.
+    ///
+    /// Any tickets still in flight after this are returned to the queue for another
+    /// shop to pick up.
+    #[arg(long, default_value_t = DEFAULT_DRAIN_TIMEOUT)]
+    pub drain_timeout: f32,
+
+    /// The file to export the tracing spans to, as JSON lines.
+    ///
+    /// If not set, no spans are exported unless the application installs its own
+    /// [`tracing`] subscriber.
+    #[arg(long, default_value = None)]
+    pub trace_file: Option<std::path::PathBuf>,
+
+    /// The format of the log lines, if the `debug` feature is enabled.
+    ///
+    /// This only takes effect if nothing had been logged before the [`Shop`](crate::models::Shop)
+    /// is created.
+    #[arg(long, value_enum, default_value_t = LogFormat::default())]
+    pub log_format: LogFormat,
+
     /// The AWS SQS queue URL to use.
     ///
     /// The AWS user must have the necessary permissions to send and receive messages
@@ -97,11 +298,39 @@ impl Default for Config {
             multicast_host: MULTICAST_HOST,
             multicast_port: MULTICAST_PORT,
             baristas: DEFAULT_BARISTAS,
+            max_baristas: None,
+            barista_concurrency: DEFAULT_BARISTA_CONCURRENCY,
+            blocking_threads: None,
             max_tickets: MAX_TICKETS,
+            max_queue_depth: None,
+            queue_depth_interval: DEFAULT_QUEUE_DEPTH_INTERVAL,
+            queue_depth_accepts_async: false,
+            rate_limit: None,
+            rate_limit_burst: None,
+            rate_limit_key: rate_limit::RateLimitKey::default(),
+            rate_limit_header: DEFAULT_RATE_LIMIT_HEADER.to_owned(),
+            rate_limit_shared: false,
+            api_keys_file: None,
+            api_keys_env: None,
+            api_key_header: DEFAULT_API_KEY_HEADER.to_owned(),
+            jwt_secret_env: None,
+            jwt_public_key_file: None,
+            jwt_issuer: None,
+            jwt_audience: None,
+            public_status: false,
+            public_metrics: false,
+            admin_principals: vec![],
+            ticket_token_secret_env: None,
+            ticket_token_ttl: None,
             dynamodb_table: None,
             dynamodb_partition_key: DEFAULT_DYNAMODB_PARTITION_KEY.to_owned(),
             result_ttl: DEFAULT_RESULT_TTL,
             max_execution_time: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            trace_file: None,
+            log_format: LogFormat::default(),
             sqs_queue: None,
         }
     }
@@ -155,6 +384,56 @@ impl Config {
         }
     }
 
+    /// Builder pattern - change the maximum number of baristas to scale up to.
+    ///
+    /// This must be called after [`Config::with_baristas`], as it cannot be lower than
+    /// the minimum number of baristas.
+    pub fn with_max_baristas(mut self, count: u16) -> Result<Self, CoffeeShopError> {
+        if count < self.baristas {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "max_baristas",
+                message: format!(
+                    "must be at least the number of baristas {baristas}, found {count}.",
+                    baristas = self.baristas,
+                ),
+            })
+        } else {
+            self.max_baristas = Some(count);
+            Ok(self)
+        }
+    }
+
+    /// Get the total number of baristas the shop may have serving at a time.
+    pub fn max_baristas(&self) -> u16 {
+        self.max_baristas.unwrap_or_default().max(self.baristas)
+    }
+
+    /// Builder pattern - change the number of tickets each barista keeps in flight.
+    pub fn with_barista_concurrency(mut self, count: u16) -> Result<Self, CoffeeShopError> {
+        if count == 0 {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "barista_concurrency",
+                message: format!("must be positive number, found {count}."),
+            })
+        } else {
+            self.barista_concurrency = count;
+            Ok(self)
+        }
+    }
+
+    /// Builder pattern - change the number of threads to run blocking machines on.
+    pub fn with_blocking_threads(mut self, count: usize) -> Result<Self, CoffeeShopError> {
+        if count == 0 {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "blocking_threads",
+                message: format!("must be positive number, found {count}."),
+            })
+        } else {
+            self.blocking_threads = Some(count);
+            Ok(self)
+        }
+    }
+
     /// Builder pattern - change the maximum number of tickets.
     pub fn with_max_tickets(mut self, count: usize) -> Result<Self, CoffeeShopError> {
         if count == 0 {
@@ -168,6 +447,229 @@ impl Config {
         }
     }
 
+    /// Builder pattern - change the maximum number of times a ticket is attempted.
+    pub fn with_max_attempts(mut self, count: u32) -> Result<Self, CoffeeShopError> {
+        if count == 0 {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "max_attempts",
+                message: format!("must be positive number, found {count}."),
+            })
+        } else {
+            self.max_attempts = count;
+            Ok(self)
+        }
+    }
+
+    /// Builder pattern - change the delay before a failed ticket is first retried.
+    pub fn with_retry_backoff(mut self, secs: f32) -> Result<Self, CoffeeShopError> {
+        if secs.is_finite() && secs >= 0. {
+            self.retry_backoff = secs;
+            Ok(self)
+        } else {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "retry_backoff",
+                message: format!("must be non-negative number, found {secs}."),
+            })
+        }
+    }
+
+    /// Builder pattern - change the time the tickets in flight are given to complete on
+    /// shutdown.
+    pub fn with_drain_timeout(mut self, secs: f32) -> Result<Self, CoffeeShopError> {
+        if secs.is_finite() && secs >= 0. {
+            self.drain_timeout = secs;
+            Ok(self)
+        } else {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "drain_timeout",
+                message: format!("must be non-negative number, found {secs}."),
+            })
+        }
+    }
+
+    /// Builder pattern - change the maximum AWS SQS queue depth before synchronous
+    /// requests are rejected.
+    pub fn with_max_queue_depth(mut self, count: usize) -> Result<Self, CoffeeShopError> {
+        if count == 0 {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "max_queue_depth",
+                message: format!("must be positive number, found {count}."),
+            })
+        } else {
+            self.max_queue_depth = Some(count);
+            Ok(self)
+        }
+    }
+
+    /// Builder pattern - change the interval between samples of the AWS SQS queue depth.
+    pub fn with_queue_depth_interval(mut self, secs: f32) -> Result<Self, CoffeeShopError> {
+        if secs.is_finite() && secs > 0. {
+            self.queue_depth_interval = secs;
+            Ok(self)
+        } else {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "queue_depth_interval",
+                message: format!("must be positive number, found {secs}."),
+            })
+        }
+    }
+
+    /// Builder pattern - change whether asynchronous requests are still accepted when
+    /// the AWS SQS queue depth is exceeded.
+    pub fn with_queue_depth_accepts_async(mut self, accepts_async: bool) -> Self {
+        self.queue_depth_accepts_async = accepts_async;
+        self
+    }
+
+    /// Builder pattern - change the per-client rate limit, in requests per second,
+    /// and optionally the burst size.
+    pub fn with_rate_limit(
+        mut self,
+        rate: f32,
+        burst: Option<u32>,
+    ) -> Result<Self, CoffeeShopError> {
+        if !(rate.is_finite() && rate > 0.) {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "rate_limit",
+                message: format!("must be positive number, found {rate}."),
+            })
+        } else if burst == Some(0) {
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "rate_limit_burst",
+                message: "must be positive number, found 0.".to_owned(),
+            })
+        } else {
+            self.rate_limit = Some(rate);
+            self.rate_limit_burst = burst;
+            Ok(self)
+        }
+    }
+
+    /// Builder pattern - change how to identify a client for rate limiting.
+    pub fn with_rate_limit_key(mut self, key: rate_limit::RateLimitKey) -> Self {
+        self.rate_limit_key = key;
+        self
+    }
+
+    /// Builder pattern - change the header to read the API key from for rate limiting.
+    pub fn with_rate_limit_header(mut self, header: &str) -> Result<Self, CoffeeShopError> {
+        axum::http::HeaderName::try_from(header)
+            .map_err(|err| CoffeeShopError::InvalidConfiguration {
+                field: "rate_limit_header",
+                message: format!("{header:?} is not a valid header name: {err}"),
+            })
+            .map(|_| {
+                self.rate_limit_header = header.to_owned();
+                self
+            })
+    }
+
+    /// Builder pattern - change whether to share the rate limits across shops.
+    pub fn with_rate_limit_shared(mut self, shared: bool) -> Self {
+        self.rate_limit_shared = shared;
+        self
+    }
+
+    /// Builder pattern - read the accepted API keys from a file.
+    pub fn with_api_keys_file(mut self, path: std::path::PathBuf) -> Self {
+        self.api_keys_file = Some(path);
+        self
+    }
+
+    /// Builder pattern - export the tracing spans to a file, as JSON lines.
+    pub fn with_trace_file(mut self, path: std::path::PathBuf) -> Self {
+        self.trace_file = Some(path);
+        self
+    }
+
+    /// Builder pattern - set the format of the log lines.
+    pub fn with_log_format(mut self, format: LogFormat) -> Self {
+        self.log_format = format;
+        self
+    }
+
+    /// Builder pattern - read the accepted API keys from an environment variable.
+    pub fn with_api_keys_env(mut self, var: &str) -> Self {
+        self.api_keys_env = Some(var.to_owned());
+        self
+    }
+
+    /// Builder pattern - change the header to read the API key from.
+    pub fn with_api_key_header(mut self, header: &str) -> Result<Self, CoffeeShopError> {
+        axum::http::HeaderName::try_from(header)
+            .map_err(|err| CoffeeShopError::InvalidConfiguration {
+                field: "api_key_header",
+                message: format!("{header:?} is not a valid header name: {err}"),
+            })
+            .map(|_| {
+                self.api_key_header = header.to_owned();
+                self
+            })
+    }
+
+    /// Builder pattern - read the `HS256` JSON Web Token secret from an environment
+    /// variable.
+    pub fn with_jwt_secret_env(mut self, var: &str) -> Self {
+        self.jwt_secret_env = Some(var.to_owned());
+        self
+    }
+
+    /// Builder pattern - read the `RS256` JSON Web Token public key from a PEM file.
+    pub fn with_jwt_public_key_file(mut self, path: std::path::PathBuf) -> Self {
+        self.jwt_public_key_file = Some(path);
+        self
+    }
+
+    /// Builder pattern - change the required issuer and audience of JSON Web Tokens.
+    pub fn with_jwt_claims(mut self, issuer: Option<&str>, audience: Option<&str>) -> Self {
+        self.jwt_issuer = issuer.map(str::to_owned);
+        self.jwt_audience = audience.map(str::to_owned);
+        self
+    }
+
+    /// Builder pattern - change whether `/status` is accessible without authentication.
+    pub fn with_public_status(mut self, public: bool) -> Self {
+        self.public_status = public;
+        self
+    }
+
+    /// Builder pattern - change whether `/metrics` is accessible without authentication.
+    pub fn with_public_metrics(mut self, public: bool) -> Self {
+        self.public_metrics = public;
+        self
+    }
+
+    /// Builder pattern - sign the tickets into tokens, using the secret from an
+    /// environment variable, and expiring after `ttl` seconds if specified.
+    pub fn with_ticket_tokens(
+        mut self,
+        secret_env: &str,
+        ttl: Option<f32>,
+    ) -> Result<Self, CoffeeShopError> {
+        match ttl {
+            Some(secs) if !(secs.is_finite() && secs > 0.) => {
+                Err(CoffeeShopError::InvalidConfiguration {
+                    field: "ticket_token_ttl",
+                    message: format!("must be positive number, found {secs}."),
+                })
+            }
+            _ => {
+                self.ticket_token_secret_env = Some(secret_env.to_owned());
+                self.ticket_token_ttl = ttl;
+                Ok(self)
+            }
+        }
+    }
+
+    /// Builder pattern - change the principals that can access tickets owned by anyone.
+    pub fn with_admin_principals<'a>(
+        mut self,
+        principals: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.admin_principals = principals.into_iter().map(str::to_owned).collect();
+        self
+    }
+
     /// Builder pattern - change the DynamoDB configuration.
     pub fn with_dynamodb_table(mut self, table: &str) -> Self {
         self.dynamodb_table = Some(table.to_owned());
@@ -209,17 +711,154 @@ impl Config {
         tokio::time::Duration::from_secs_f32(self.result_ttl)
     }
 
+    /// Get the AWS SQS queue depth sampling interval in [`tokio::time::Duration`] format.
+    pub fn queue_depth_interval(&self) -> tokio::time::Duration {
+        tokio::time::Duration::from_secs_f32(self.queue_depth_interval)
+    }
+
+    /// Get the [`RateLimitSettings`](rate_limit::RateLimitSettings), if rate limiting
+    /// is enabled.
+    pub fn rate_limit_settings(&self) -> Option<rate_limit::RateLimitSettings> {
+        self.rate_limit.map(|rate| rate_limit::RateLimitSettings {
+            rate: f64::from(rate),
+            capacity: f64::from(
+                self.rate_limit_burst
+                    .unwrap_or_else(|| rate.ceil() as u32)
+                    .max(1),
+            ),
+            shared: self.rate_limit_shared,
+        })
+    }
+
+    /// Get the deadline for a barista to process a ticket, which is the shorter of
+    /// [`Config::max_execution_time`] and the timeout of the query, if any.
+    pub fn execution_deadline(
+        &self,
+        query_timeout: Option<tokio::time::Duration>,
+    ) -> Option<tokio::time::Duration> {
+        match (self.max_execution_time(), query_timeout) {
+            (Some(max), Some(timeout)) => Some(max.min(timeout)),
+            (max, timeout) => max.or(timeout),
+        }
+    }
+
+    /// Get the number of threads to run blocking machines on.
+    pub fn blocking_threads(&self) -> usize {
+        self.blocking_threads.unwrap_or_else(num_cpus::get)
+    }
+
+    /// Get the delay before the retry following the given attempt, starting from `1`.
+    ///
+    /// This is [`Config::retry_backoff`] doubled for every attempt after the first.
+    pub fn retry_delay(&self, attempt: u32) -> tokio::time::Duration {
+        helpers::retry::exponential_backoff(
+            tokio::time::Duration::from_secs_f32(self.retry_backoff),
+            attempt.saturating_sub(1),
+        )
+    }
+
+    /// Get the drain timeout in [`tokio::time::Duration`] format.
+    pub fn drain_timeout(&self) -> tokio::time::Duration {
+        tokio::time::Duration::from_secs_f32(self.drain_timeout)
+    }
+
     /// Get the maximum execution time in [`tokio::time::Duration`] format.
     pub fn max_execution_time(&self) -> Option<tokio::time::Duration> {
         self.max_execution_time
             .map(tokio::time::Duration::from_secs_f32)
     }
+
+    /// Check if any authentication scheme is configured.
+    pub fn authentication_enabled(&self) -> bool {
+        self.api_keys_file.is_some()
+            || self.api_keys_env.is_some()
+            || self.jwt_secret_env.is_some()
+            || self.jwt_public_key_file.is_some()
+    }
+
+    /// Check if `/status` should report the raw tickets in flight and the AWS resources
+    /// of the shop to the `principal` of the request.
+    ///
+    /// These are reported to everyone if authentication is disabled, and only to the
+    /// [`Config::admin_principals`] otherwise.
+    pub fn reveals_status_details(&self, principal: Option<&Principal>) -> bool {
+        !self.authentication_enabled() || principal.is_some_and(|principal| principal.admin)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn reveals_status_details() {
+        let alice = Principal::from_api_key("alice".to_owned());
+        let admin = Principal {
+            admin: true,
+            ..Principal::from_api_key("ops".to_owned())
+        };
+
+        let open = Config::default();
+        assert!(open.reveals_status_details(None));
+
+        let authenticated = Config {
+            api_keys_env: Some("COFFEESHOP_API_KEYS".to_owned()),
+            ..Default::default()
+        };
+        assert!(!authenticated.reveals_status_details(None));
+        assert!(!authenticated.reveals_status_details(Some(&alice)));
+        assert!(authenticated.reveals_status_details(Some(&admin)));
+    }
+
+    #[test]
+    fn execution_deadline() {
+        let seconds = tokio::time::Duration::from_secs;
+        let config = Config {
+            max_execution_time: Some(30.),
+            ..Default::default()
+        };
+
+        assert_eq!(config.execution_deadline(None), Some(seconds(30)));
+        assert_eq!(
+            config.execution_deadline(Some(seconds(5))),
+            Some(seconds(5))
+        );
+        assert_eq!(
+            config.execution_deadline(Some(seconds(60))),
+            Some(seconds(30))
+        );
+        assert_eq!(Config::default().execution_deadline(None), None);
+        assert_eq!(
+            Config::default().execution_deadline(Some(seconds(5))),
+            Some(seconds(5))
+        );
+    }
+
+    #[test]
+    fn parse_queue_depth_interval() {
+        let parse = |value: &str| {
+            Config::try_parse_from(["coffeeshop", &format!("--queue-depth-interval={value}")])
+                .map(|config| config.queue_depth_interval)
+        };
+
+        assert_eq!(parse("2.5").ok(), Some(2.5));
+        assert!(parse("0").is_err());
+        assert!(parse("-1").is_err());
+        assert!(parse("NaN").is_err());
+    }
+
+    #[test]
+    fn parse_ticket_token_ttl() {
+        let parse = |value: &str| {
+            Config::try_parse_from(["coffeeshop", &format!("--ticket-token-ttl={value}")])
+                .map(|config| config.ticket_token_ttl)
+        };
+
+        assert_eq!(parse("60").ok(), Some(Some(60.)));
+        assert!(parse("0").is_err());
+        assert!(parse("-1").is_err());
+    }
+
     macro_rules! create_test {
         (
             $name:ident($builder:expr) -> $expected:expr
@@ -293,6 +932,67 @@ mod tests {
             }
         )
     );
+    create_test!(
+        with_good_max_baristas(
+            Config::new().with_baristas(2).and_then(|config| config.with_max_baristas(8))
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                baristas: 2,
+                max_baristas: Some(8),
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_max_baristas(
+            Config::new().with_baristas(4).and_then(|config| config.with_max_baristas(2))
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "max_baristas",
+                message: "must be at least the number of baristas 4, found 2.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_good_blocking_threads(
+            Config::new().with_blocking_threads(4)
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                blocking_threads: Some(4),
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_blocking_threads(
+            Config::new().with_blocking_threads(0)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "blocking_threads",
+                message: "must be positive number, found 0.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_good_barista_concurrency(
+            Config::new().with_barista_concurrency(8)
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                barista_concurrency: 8,
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_barista_concurrency(
+            Config::new().with_barista_concurrency(0)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "barista_concurrency",
+                message: "must be positive number, found 0.".to_owned()
+            }
+        )
+    );
     create_test!(
         with_good_max_tickets(
             Config::new().with_max_tickets(2)
@@ -313,4 +1013,181 @@ mod tests {
             }
         )
     );
+    create_test!(
+        with_good_max_queue_depth(
+            Config::new().with_max_queue_depth(500)
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                max_queue_depth: Some(500),
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_max_queue_depth(
+            Config::new().with_max_queue_depth(0)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "max_queue_depth",
+                message: "must be positive number, found 0.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_bad_queue_depth_interval(
+            Config::new().with_queue_depth_interval(0.)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "queue_depth_interval",
+                message: "must be positive number, found 0.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_good_rate_limit(
+            Config::new().with_rate_limit(2.5, Some(5))
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                rate_limit: Some(2.5),
+                rate_limit_burst: Some(5),
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_rate_limit(
+            Config::new().with_rate_limit(-1., None)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "rate_limit",
+                message: "must be positive number, found -1.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_bad_rate_limit_header(
+            Config::new().with_rate_limit_header("x api key")
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "rate_limit_header",
+                message: "\"x api key\" is not a valid header name: invalid HTTP header name".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_good_api_key_header(
+            Config::new().with_api_key_header("x-coffee-key")
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                api_key_header: "x-coffee-key".to_owned(),
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_api_key_header(
+            Config::new().with_api_key_header("x api key")
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "api_key_header",
+                message: "\"x api key\" is not a valid header name: invalid HTTP header name".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_admin_principals(
+            Ok::<_, CoffeeShopError>(
+                Config::new().with_admin_principals(["api_key:ops", "jwt:root"])
+            )
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                admin_principals: vec!["api_key:ops".to_owned(), "jwt:root".to_owned()],
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_good_ticket_tokens(
+            Config::new().with_ticket_tokens("TICKET_SECRET", Some(600.))
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                ticket_token_secret_env: Some("TICKET_SECRET".to_owned()),
+                ticket_token_ttl: Some(600.),
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_ticket_tokens(
+            Config::new().with_ticket_tokens("TICKET_SECRET", Some(0.))
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "ticket_token_ttl",
+                message: "must be positive number, found 0.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_good_max_attempts(
+            Config::new().with_max_attempts(5)
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                max_attempts: 5,
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_max_attempts(
+            Config::new().with_max_attempts(0)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "max_attempts",
+                message: "must be positive number, found 0.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_bad_retry_backoff(
+            Config::new().with_retry_backoff(-1.)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "retry_backoff",
+                message: "must be non-negative number, found -1.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_drain_timeout(
+            Config::new().with_drain_timeout(0.)
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                drain_timeout: 0.,
+                ..Default::default()
+            }
+        )
+    );
+    create_test!(
+        with_bad_drain_timeout(
+            Config::new().with_drain_timeout(f32::NAN)
+        ) -> Err(
+            CoffeeShopError::InvalidConfiguration{
+                field: "drain_timeout",
+                message: "must be non-negative number, found NaN.".to_owned()
+            }
+        )
+    );
+    create_test!(
+        with_jwt_claims(
+            Ok::<_, CoffeeShopError>(
+                Config::new().with_jwt_claims(Some("https://issuer.example"), None)
+            )
+        ) -> Ok::<_, CoffeeShopError>(
+            Config {
+                jwt_issuer: Some("https://issuer.example".to_owned()),
+                jwt_audience: None,
+                ..Default::default()
+            }
+        )
+    );
 }
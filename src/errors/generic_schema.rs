@@ -28,6 +28,14 @@ pub struct ErrorSchema {
     /// It is encouraged for this field to contain the key "message" with a human-readable
     /// error message.
     pub details: Option<serde_json::Value>,
+
+    /// Whether the error is transient, e.g. a downstream service was temporarily
+    /// unavailable, such that the same request could succeed if retried.
+    ///
+    /// A [`Barista`] will put a ticket that failed with a retryable error back onto
+    /// the queue, up to the maximum number of attempts, before reporting the error.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retryable: bool,
 }
 
 impl ErrorSchema {
@@ -41,8 +49,47 @@ impl ErrorSchema {
             status_code,
             error,
             details,
+            retryable: false,
         }
     }
+
+    /// Create a new instance of [`ErrorSchema`] for a transient error that should be
+    /// retried.
+    pub fn new_retryable(
+        status_code: http::StatusCode,
+        error: String,
+        details: Option<serde_json::Value>,
+    ) -> Self {
+        Self::new(status_code, error, details).with_retryable(true)
+    }
+
+    /// Create a new instance of [`ErrorSchema`] from the payload of a caught panic,
+    /// with a `500 Internal Server Error` status code.
+    ///
+    /// The panic message is included in the details if it is a string, which is the
+    /// case for all panics raised by the [`panic!`] macro.
+    ///
+    /// This is marked as [retryable](Self::retryable), so that a panicking ticket is
+    /// returned to the queue and counts towards its maximum number of receives.
+    pub fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(Panic payload is not a string)".to_owned());
+
+        Self::new_retryable(
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            "MachinePanicked".to_owned(),
+            Some(serde_json::json!({ "message": message })),
+        )
+    }
+
+    /// Builder pattern - change whether the error is retryable.
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
 }
 
 impl IntoResponse for ErrorSchema {
@@ -74,3 +121,31 @@ impl std::fmt::Display for ErrorSchema {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! create_test {
+        ($name:ident($panic:expr) -> $expected:literal) => {
+            #[test]
+            fn $name() {
+                let payload = std::panic::catch_unwind(|| -> () { $panic })
+                    .expect_err("The closure should have panicked.");
+
+                let schema = ErrorSchema::from_panic(payload);
+
+                assert_eq!(schema.status_code, http::StatusCode::INTERNAL_SERVER_ERROR);
+                assert!(schema.retryable);
+                assert_eq!(
+                    schema.details,
+                    Some(serde_json::json!({ "message": $expected }))
+                );
+            }
+        };
+    }
+
+    create_test!(static_message(panic!("out of milk")) -> "out of milk");
+    create_test!(formatted_message(panic!("out of {}", "beans")) -> "out of beans");
+    create_test!(non_string_payload(std::panic::panic_any(42_u8)) -> "(Panic payload is not a string)");
+}
@@ -7,7 +7,10 @@ use std::{
 };
 
 use crate::{
-    helpers::{aws::HasAWSSdkConfig, dynamodb::HasDynamoDBConfiguration, sqs::HasSQSConfiguration},
+    helpers::{
+        self, aws::HasAWSSdkConfig, dynamodb::HasDynamoDBConfiguration,
+        rate_limit::RateLimitDecision, sqs::HasSQSConfiguration,
+    },
     models::Ticket,
 };
 
@@ -67,6 +70,9 @@ pub enum CoffeeShopError {
     #[error("Endpoint {0} is not found on this server. Please consult the API documentation.")]
     InvalidRoute(http::Uri),
 
+    #[error("Task {0:?} is not found on this server. Please consult the API documentation.")]
+    TaskNotFound(String),
+
     // This needs to be reworked to be more specific.
     #[error("Invalid URL query options: {0}")]
     InvalidQueryOptions(String),
@@ -84,6 +90,9 @@ pub enum CoffeeShopError {
     #[error("Failed to bind listener to socket address {1}: {0}")]
     ListenerCreationFailure(String, SocketAddr),
 
+    #[error("Could not encode the metrics: {0}")]
+    MetricsEncodingError(String),
+
     #[error("Could not serialize the payload: {0}")]
     BinaryConversionError(#[from] Box<bincode::ErrorKind>),
 
@@ -123,6 +132,41 @@ pub enum CoffeeShopError {
     #[error("Timed out awaiting results after {0:?} seconds")]
     RetrieveTimeout(tokio::time::Duration),
 
+    #[error("The machine did not finish processing the ticket within {0:?}; it was cancelled.")]
+    ProcessingTimeout(tokio::time::Duration),
+
+    #[error("The machine failed its {hook} hook: {error}")]
+    MachineLifecycleFailed {
+        hook: &'static str,
+        error: CoffeeMachineError,
+    },
+
+    #[error("There are {outstanding} outstanding tickets, which exceeds the limit of {max_tickets}; please retry after {retry_after:?}.")]
+    TooManyTickets {
+        outstanding: usize,
+        max_tickets: usize,
+        retry_after: tokio::time::Duration,
+    },
+
+    #[error("Authentication failed: {0}")]
+    Unauthorized(String),
+
+    #[error("Rate limit of {} requests exceeded; please retry after {:?}.", .0.limit, .0.retry_after.unwrap_or_default())]
+    RateLimited(RateLimitDecision),
+
+    #[error("The request costs {cost} tokens, which exceeds the rate limit of {limit} requests; please split it into smaller requests.")]
+    RateLimitCostExceeded { cost: u32, limit: u32 },
+
+    #[error("The queue has approximately {depth} tickets waiting, which exceeds the limit of {max_queue_depth}; please retry after {retry_after:?}.")]
+    QueueBacklogExceeded {
+        depth: usize,
+        max_queue_depth: usize,
+        retry_after: tokio::time::Duration,
+    },
+
+    #[error("The shop is closing and no longer accepts orders; please retry with another shop.")]
+    ShopClosing,
+
     #[error("An error relating to AWS IAM credentials occurred: {0}")]
     AWSCredentialsError(String),
 
@@ -189,9 +233,21 @@ pub enum CoffeeShopError {
     #[error("The ticket {0} does not have a result. It could have been purged, or the ticket is invalid.")]
     ResultNotFound(Ticket),
 
-    #[error("The ticket {0} was not found.")]
+    #[error("The ticket {0} was not found. It could have expired, or the ticket is invalid.")]
     TicketNotFound(Ticket),
 
+    #[error("The ticket is not a token issued by this shop: {0}")]
+    TicketTokenForged(String),
+
+    #[error("The ticket token had been tampered with; its signature does not match.")]
+    TicketTokenTampered,
+
+    #[error("The ticket token issued at {issued_at} had expired at {expired_at}.")]
+    TicketTokenExpired {
+        issued_at: chrono::DateTime<chrono::Utc>,
+        expired_at: chrono::DateTime<chrono::Utc>,
+    },
+
     #[error("Upstream worker reported an error: {0:?}")]
     ErrorSchema(ErrorSchema),
 
@@ -224,6 +280,7 @@ impl CoffeeShopError {
 
     /// Convenient method to map AWS STS [`sts::Error`] to [`CoffeeShopError`].
     pub fn from_aws_sts_error(error: sts::Error, config: &dyn HasAWSSdkConfig) -> Self {
+        helpers::metrics::metrics().record_aws_error("sts");
         crate::error!(
             "An error occurred during AWS STS validation using config\n:{sdk_config:#?}",
             sdk_config = config.aws_config()
@@ -249,6 +306,7 @@ impl CoffeeShopError {
 
     /// Convenient method to map AWS SQS [`sqs::Error`] to [`CoffeeShopError`].
     pub fn from_aws_sqs_error(error: sqs::Error, config: &dyn HasSQSConfiguration) -> Self {
+        helpers::metrics::metrics().record_aws_error("sqs");
         match error {
             sqs::Error::InvalidAddress(sqs::InvalidAddress {
                 message: msg_opt, ..
@@ -286,6 +344,7 @@ impl CoffeeShopError {
         error: dynamodb::Error,
         config: &dyn HasDynamoDBConfiguration,
     ) -> Self {
+        helpers::metrics::metrics().record_aws_error("dynamodb");
         match error {
             dynamodb::Error::ConditionalCheckFailedException(
                 dynamodb::ConditionalCheckFailedException { message, item, .. },
@@ -384,10 +443,23 @@ impl CoffeeShopError {
             Self::InvalidMulticastAddress(_) => http::StatusCode::BAD_REQUEST,
             Self::InvalidMulticastMessage { .. } => http::StatusCode::BAD_REQUEST,
             Self::InvalidRoute(_) => http::StatusCode::NOT_FOUND,
+            Self::TaskNotFound(_) => http::StatusCode::NOT_FOUND,
             Self::InvalidQueryOptions(_) => http::StatusCode::BAD_REQUEST,
             Self::InvalidPayload { .. } => http::StatusCode::UNPROCESSABLE_ENTITY,
             Self::MalformedJsonPayload(_) => http::StatusCode::BAD_REQUEST,
             Self::RetrieveTimeout(_) => http::StatusCode::REQUEST_TIMEOUT,
+            Self::ProcessingTimeout(_) => http::StatusCode::GATEWAY_TIMEOUT,
+            Self::MachineLifecycleFailed { .. } => http::StatusCode::SERVICE_UNAVAILABLE,
+            Self::TooManyTickets { .. } => http::StatusCode::TOO_MANY_REQUESTS,
+            Self::RateLimited(_) => http::StatusCode::TOO_MANY_REQUESTS,
+            Self::RateLimitCostExceeded { .. } => http::StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unauthorized(_) => http::StatusCode::UNAUTHORIZED,
+            Self::QueueBacklogExceeded { .. } => http::StatusCode::SERVICE_UNAVAILABLE,
+            Self::ShopClosing => http::StatusCode::SERVICE_UNAVAILABLE,
+            Self::TicketNotFound(_) => http::StatusCode::NOT_FOUND,
+            Self::TicketTokenForged(_) => http::StatusCode::BAD_REQUEST,
+            Self::TicketTokenTampered => http::StatusCode::FORBIDDEN,
+            Self::TicketTokenExpired { .. } => http::StatusCode::GONE,
             Self::Base64EncodingOversize(_) => http::StatusCode::PAYLOAD_TOO_LARGE,
             Self::ProcessingError(ErrorSchema { status_code, .. }) => *status_code,
             Self::ErrorSchema(ErrorSchema { status_code, .. }) => *status_code,
@@ -396,6 +468,20 @@ impl CoffeeShopError {
         }
     }
 
+    /// This method returns the duration the client should wait before retrying, if
+    /// applicable.
+    ///
+    /// This is reported in the `Retry-After` header of the response, rounded up to
+    /// the nearest second.
+    pub fn retry_after(&self) -> Option<tokio::time::Duration> {
+        match self {
+            Self::TooManyTickets { retry_after, .. }
+            | Self::QueueBacklogExceeded { retry_after, .. } => Some(*retry_after),
+            Self::RateLimited(decision) => decision.retry_after,
+            _ => None,
+        }
+    }
+
     /// This method returns the kind of error as a string.
     pub fn kind(&self) -> &'static str {
         self.into()
@@ -461,7 +547,7 @@ impl PartialEq for CoffeeShopError {
 
 impl IntoResponse for CoffeeShopError {
     fn into_response(self) -> axum::response::Response<Body> {
-        (
+        let mut response = (
             self.status_code(),
             [
                 (http::header::CONTENT_TYPE, "application/json"),
@@ -469,6 +555,26 @@ impl IntoResponse for CoffeeShopError {
             ],
             Json(self.as_json()),
         )
-            .into_response()
+            .into_response();
+
+        if let Some(retry_after) = self.retry_after() {
+            response.headers_mut().insert(
+                http::header::RETRY_AFTER,
+                http::HeaderValue::from(retry_after.as_secs_f64().ceil() as u64),
+            );
+        }
+
+        if let Self::RateLimited(decision) = &self {
+            decision.apply_headers(&mut response);
+        }
+
+        if let Self::Unauthorized(_) = &self {
+            response.headers_mut().insert(
+                http::header::WWW_AUTHENTICATE,
+                http::HeaderValue::from_static("Bearer"),
+            );
+        }
+
+        response
     }
 }
@@ -0,0 +1,157 @@
+use sha2::Digest;
+
+use crate::{models::message::Principal, CoffeeShopError};
+
+/// A set of static API keys, each with a name to identify the [`Principal`].
+///
+/// Only the hashes of the keys are kept in memory.
+#[derive(Debug, Default, Clone)]
+pub struct ApiKeys {
+    keys: hashbrown::HashMap<String, String>,
+}
+
+/// The number of hexadecimal digits of the hash used to name an unnamed API key.
+const UNNAMED_KEY_HASH_LEN: usize = 16;
+
+/// Hash an API key for storage and lookup.
+fn hash_api_key(key: &str) -> String {
+    format!("{:x}", sha2::Sha256::digest(key.as_bytes()))
+}
+
+impl ApiKeys {
+    /// Parse API keys from text, one per line or separated by commas.
+    ///
+    /// Each entry is either `name:key`, or just `key`, in which case the name
+    /// defaults to `key-<hash>` where `hash` is the start of the hash of the key; this
+    /// keeps the name of a key stable wherever it is listed.
+    /// Empty entries and lines starting with `#` are ignored.
+    pub fn parse(text: &str) -> Self {
+        let keys = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once(':') {
+                Some((name, key)) => (hash_api_key(key.trim()), name.trim().to_owned()),
+                None => {
+                    let hash = hash_api_key(entry);
+                    let name = format!("key-{}", &hash[..UNNAMED_KEY_HASH_LEN]);
+
+                    (hash, name)
+                }
+            })
+            .collect();
+
+        Self { keys }
+    }
+
+    /// Read API keys from a file; see [`ApiKeys::parse`] for the format.
+    pub fn from_file(path: &std::path::Path) -> Result<Self, CoffeeShopError> {
+        std::fs::read_to_string(path)
+            .map(|text| Self::parse(&text))
+            .map_err(|err| CoffeeShopError::InvalidConfiguration {
+                field: "api_keys_file",
+                message: format!("could not read {path:?}: {err}"),
+            })
+    }
+
+    /// Read API keys from an environment variable; see [`ApiKeys::parse`] for the format.
+    pub fn from_env(var: &str) -> Result<Self, CoffeeShopError> {
+        std::env::var(var)
+            .map(|text| Self::parse(&text))
+            .map_err(|err| CoffeeShopError::InvalidConfiguration {
+                field: "api_keys_env",
+                message: format!("could not read ${var}: {err}"),
+            })
+    }
+
+    /// Merge another set of API keys into this one.
+    pub fn extend(&mut self, other: Self) {
+        self.keys.extend(other.keys);
+    }
+
+    /// Get the number of API keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check if there are no API keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Authenticate an API key, returning the [`Principal`] if it is known.
+    pub fn authenticate(&self, key: &str) -> Result<Principal, CoffeeShopError> {
+        self.keys
+            .get(&hash_api_key(key))
+            .map(|name| Principal::from_api_key(name.clone()))
+            .ok_or_else(|| CoffeeShopError::Unauthorized("The API key is not valid.".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &str = "
+        # Comments are ignored.
+        alice:alice-secret
+        bob-secret, carol:carol-secret
+
+    ";
+
+    macro_rules! create_test {
+        ($name:ident($key:literal) -> $expected:expr) => {
+            #[test]
+            fn $name() {
+                let keys = ApiKeys::parse(KEYS);
+                assert_eq!(keys.len(), 3);
+
+                let expected: Option<&str> = $expected;
+                assert_eq!(
+                    keys.authenticate($key)
+                        .ok()
+                        .map(|principal| principal.subject),
+                    expected.map(str::to_owned)
+                );
+            }
+        };
+    }
+
+    create_test!(named_key("alice-secret") -> Some("alice"));
+    create_test!(comma_separated_key("carol-secret") -> Some("carol"));
+    create_test!(unknown_key("mallory-secret") -> None);
+    create_test!(name_is_not_key("alice") -> None);
+
+    #[test]
+    fn unnamed_key() {
+        let subject = |text: &str| {
+            ApiKeys::parse(text)
+                .authenticate("bob-secret")
+                .map(|principal| principal.subject)
+                .unwrap()
+        };
+
+        let name = subject(KEYS);
+        assert_eq!(
+            name,
+            format!(
+                "key-{}",
+                &hash_api_key("bob-secret")[..UNNAMED_KEY_HASH_LEN]
+            )
+        );
+
+        // The name does not depend on the position of the key, or the other keys.
+        assert_eq!(subject("bob-secret"), name);
+        assert_eq!(subject("dave-secret\nbob-secret"), name);
+        assert_ne!(
+            ApiKeys::parse("dave-secret")
+                .authenticate("dave-secret")
+                .unwrap()
+                .subject,
+            name
+        );
+    }
+}
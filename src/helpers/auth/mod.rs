@@ -0,0 +1,146 @@
+//! Helper functions to authenticate requests to the [`Waiter`](crate::models::Waiter).
+//!
+//! Two schemes are supported, either or both of which can be enabled in [`Config`]:
+//!
+//! - static API keys, read from a file or an environment variable, and passed in the
+//!   [`Config::api_key_header`] header; and
+//! - JSON Web Tokens signed with `HS256` or `RS256`, passed in the `Authorization`
+//!   header as a `Bearer` token.
+//!
+
+use axum::http;
+
+use crate::{cli::Config, models::message::Principal, CoffeeShopError};
+
+mod api_key;
+pub use api_key::*;
+
+mod jwt;
+pub use jwt::*;
+
+const LOG_TARGET: &str = "coffeeshop::helpers::auth";
+
+/// Authenticates requests using the schemes enabled in [`Config`].
+#[derive(Debug)]
+pub struct Authenticator {
+    /// The header to read the API key from.
+    pub api_key_header: http::HeaderName,
+
+    /// The accepted API keys; empty if API keys are not enabled.
+    pub api_keys: ApiKeys,
+
+    /// The verifier for JSON Web Tokens, if enabled.
+    pub jwt: Option<JwtVerifier>,
+
+    /// The [owners](Principal::owner) of the principals that are administrators.
+    pub admins: hashbrown::HashSet<String>,
+}
+
+impl Authenticator {
+    /// Build an [`Authenticator`] from the [`Config`], reading any keys from the
+    /// files and environment variables specified.
+    ///
+    /// Returns [`None`] if no authentication scheme is enabled.
+    pub fn from_config(config: &Config) -> Result<Option<Self>, CoffeeShopError> {
+        let mut api_keys = ApiKeys::default();
+        if let Some(path) = &config.api_keys_file {
+            api_keys.extend(ApiKeys::from_file(path)?);
+        }
+        if let Some(var) = &config.api_keys_env {
+            api_keys.extend(ApiKeys::from_env(var)?);
+        }
+
+        let mut jwt_keys = vec![];
+        if let Some(var) = &config.jwt_secret_env {
+            let secret =
+                std::env::var(var).map_err(|err| CoffeeShopError::InvalidConfiguration {
+                    field: "jwt_secret_env",
+                    message: format!("could not read ${var}: {err}"),
+                })?;
+            jwt_keys.push(JwtKey::hs256(secret.as_bytes()));
+        }
+        if let Some(path) = &config.jwt_public_key_file {
+            let key = std::fs::read(path)
+                .map_err(|err| format!("could not read {path:?}: {err}"))
+                .and_then(|pem| JwtKey::rs256_from_pem(&pem))
+                .map_err(|message| CoffeeShopError::InvalidConfiguration {
+                    field: "jwt_public_key_file",
+                    message,
+                })?;
+            jwt_keys.push(key);
+        }
+
+        if api_keys.is_empty() && jwt_keys.is_empty() {
+            if config.api_keys_file.is_some() || config.api_keys_env.is_some() {
+                return Err(CoffeeShopError::InvalidConfiguration {
+                    field: "api_keys_file",
+                    message: "no API keys were found.".to_owned(),
+                });
+            }
+
+            return Ok(None);
+        }
+
+        crate::info!(
+            target: LOG_TARGET,
+            "Authentication enabled with {count} API keys and {schemes:?} JSON Web Tokens.",
+            count = api_keys.len(),
+            schemes = jwt_keys.iter().map(|key| key.algorithm).collect::<Vec<_>>(),
+        );
+
+        Ok(Some(Self {
+            api_key_header: http::HeaderName::try_from(config.api_key_header.as_str()).map_err(
+                |err| CoffeeShopError::InvalidConfiguration {
+                    field: "api_key_header",
+                    message: err.to_string(),
+                },
+            )?,
+            api_keys,
+            jwt: (!jwt_keys.is_empty()).then(|| JwtVerifier {
+                keys: jwt_keys,
+                issuer: config.jwt_issuer.clone(),
+                audience: config.jwt_audience.clone(),
+            }),
+            admins: config.admin_principals.iter().cloned().collect(),
+        }))
+    }
+
+    /// Authenticate a request by its headers, marking the [`Principal`] as an
+    /// administrator if it is one of [`Config::admin_principals`].
+    ///
+    /// An API key takes precedence over a `Bearer` token if both are present.
+    pub fn authenticate(&self, headers: &http::HeaderMap) -> Result<Principal, CoffeeShopError> {
+        self.identify(headers).map(|mut principal| {
+            principal.admin = self.admins.contains(&principal.owner());
+            principal
+        })
+    }
+
+    /// Identify the [`Principal`] of a request by its headers.
+    fn identify(&self, headers: &http::HeaderMap) -> Result<Principal, CoffeeShopError> {
+        let header_value = |name: &http::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+
+        if !self.api_keys.is_empty() {
+            if let Some(key) = header_value(&self.api_key_header) {
+                return self.api_keys.authenticate(key);
+            }
+        }
+
+        if let Some(jwt) = &self.jwt {
+            if let Some(token) = header_value(&http::header::AUTHORIZATION)
+                .and_then(|value| value.strip_prefix("Bearer "))
+            {
+                return jwt.verify(token.trim());
+            }
+        }
+
+        Err(CoffeeShopError::Unauthorized(
+            "No credentials were provided.".to_owned(),
+        ))
+    }
+}
@@ -0,0 +1,132 @@
+//! Admission control of the tickets created by a [`Waiter`], bounded by
+//! [`Config::max_tickets`].
+//!
+//! A slot is [reserved](TicketCapacity::reserve_up_to) before a ticket is put into the
+//! queue, and released when its [`Order`] is fulfilled, or when the reservation is
+//! dropped because the ticket could not be created.
+//!
+//! An [`Order`] that is never fulfilled, e.g. because its ticket was lost, releases its
+//! slot once it is [abandoned](Order::is_abandoned), so that it does not count
+//! against [`Config::max_tickets`] forever.
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+#[cfg(doc)]
+use crate::{cli::Config, models::Order, models::Waiter};
+
+/// The number of tickets created by a [`Waiter`] that are not yet fulfilled.
+#[derive(Debug, Default)]
+pub struct TicketCapacity {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl TicketCapacity {
+    /// Create a new [`TicketCapacity`] without any tickets in flight.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of tickets in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Reserve a slot for each of up to `count` tickets, without exceeding `max_tickets`
+    /// in flight.
+    ///
+    /// Fewer reservations than `count` are returned if there is not enough capacity.
+    pub fn reserve_up_to(&self, count: usize, max_tickets: usize) -> Vec<TicketReservation> {
+        let reserved = self
+            .in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_flight| {
+                let reserved = count.min(max_tickets.saturating_sub(in_flight));
+                (reserved > 0).then_some(in_flight + reserved)
+            })
+            .map_or(0, |in_flight| {
+                count.min(max_tickets.saturating_sub(in_flight))
+            });
+
+        (0..reserved)
+            .map(|_| TicketReservation {
+                in_flight: Arc::clone(&self.in_flight),
+                released: AtomicBool::new(false),
+            })
+            .collect()
+    }
+}
+
+/// A slot reserved for a ticket in a [`TicketCapacity`].
+///
+/// The slot is released by [`TicketReservation::release`], or when this is dropped.
+#[derive(Debug)]
+pub struct TicketReservation {
+    in_flight: Arc<AtomicUsize>,
+    released: AtomicBool,
+}
+
+impl TicketReservation {
+    /// Release the slot; this has no effect if it had already been released.
+    ///
+    /// Returns `true` if the slot was released by this call.
+    pub fn release(&self) -> bool {
+        let released = !self.released.swap(true, Ordering::AcqRel);
+        if released {
+            self.in_flight.fetch_sub(1, Ordering::AcqRel);
+        }
+
+        released
+    }
+}
+
+impl Drop for TicketReservation {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_and_release() {
+        let capacity = TicketCapacity::new();
+
+        let first = capacity.reserve_up_to(3, 4);
+        assert_eq!(first.len(), 3);
+
+        let second = capacity.reserve_up_to(3, 4);
+        assert_eq!(second.len(), 1);
+        assert!(capacity.reserve_up_to(1, 4).is_empty());
+        assert_eq!(capacity.in_flight(), 4);
+
+        assert!(first[0].release());
+        assert!(!first[0].release());
+        assert_eq!(capacity.in_flight(), 3);
+
+        drop(first);
+        assert_eq!(capacity.in_flight(), 1);
+        drop(second);
+        assert_eq!(capacity.in_flight(), 0);
+    }
+
+    #[test]
+    fn concurrent_reservations() {
+        let capacity = Arc::new(TicketCapacity::new());
+
+        let reserved = std::thread::scope(|scope| {
+            (0..8)
+                .map(|_| scope.spawn(|| capacity.reserve_up_to(5, 16)))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(reserved.iter().map(Vec::len).sum::<usize>(), 16);
+        assert_eq!(capacity.in_flight(), 16);
+    }
+}
@@ -15,10 +15,13 @@ use crate::{
 use super::*;
 
 /// Put a processing result into a DynamoDB table.
+///
+/// The `owner` of the ticket is recorded alongside the result, if any.
 pub async fn put_process_result<O>(
     config: &dyn HasDynamoDBConfiguration,
     ticket: &Ticket,
     result: ProcessResult<O>,
+    owner: Option<&str>,
 ) -> Result<(), CoffeeShopError>
 where
     O: serde::Serialize + Send + Sync + 'static,
@@ -26,9 +29,12 @@ where
     let client = dynamodb::Client::new(config.aws_config());
     let table = config.dynamodb_table();
 
-    client.put_item()
-        .table_name(table)
-        .report_ticket_result(config.dynamodb_partition_key(), ticket, result, &config.dynamodb_ttl()).await?
+    add_owner(
+        client.put_item()
+            .table_name(table)
+            .report_ticket_result(config.dynamodb_partition_key(), ticket, result, &config.dynamodb_ttl()).await?,
+        owner,
+    )
         .send()
         .await
         .map_err(|sdk_err| {
@@ -53,6 +59,75 @@ where
         )
 }
 
+/// Put a pending record for a ticket into a DynamoDB table.
+///
+/// This allows any [`Shop`](crate::models::Shop) in the cluster to tell apart a ticket
+/// that is still being processed from one that is unknown or expired, even if the
+/// ticket was not created by itself.
+///
+/// If a processing result for the ticket already exists, this is a no-op.
+///
+/// The `owner` of the ticket is recorded alongside, if any.
+pub async fn put_pending_ticket(
+    config: &dyn HasDynamoDBConfiguration,
+    ticket: &Ticket,
+    owner: Option<&str>,
+) -> Result<(), CoffeeShopError> {
+    let client = dynamodb::Client::new(config.aws_config());
+    let table = config.dynamodb_table();
+
+    let result = add_owner(
+        client
+            .put_item()
+            .table_name(table)
+            .report_ticket_pending(
+                config.dynamodb_partition_key(),
+                ticket,
+                &config.dynamodb_ttl(),
+            )
+            .await?,
+        owner,
+    )
+    .send()
+    .await;
+
+    match result {
+        Ok(_) => {
+            crate::debug!(
+                "Recorded ticket {} as pending in the DynamoDB table {}.",
+                ticket,
+                table,
+            );
+            Ok(())
+        }
+        Err(sdk_err) => {
+            let service_err = sdk_err.into_service_error();
+
+            if service_err.is_conditional_check_failed_exception() {
+                // The barista had beaten us to it; the result is already there.
+                crate::debug!(
+                    "Ticket {} already has a result in the DynamoDB table {}; not recording it as pending.",
+                    ticket,
+                    table,
+                );
+                Ok(())
+            } else {
+                crate::error!(
+                    "Failed to record ticket {} as pending in the DynamoDB table {}. Error: {:?}",
+                    ticket,
+                    table,
+                    service_err
+                );
+
+                Err(CoffeeShopError::from_aws_dynamodb_error(
+                    service_err.into(),
+                    config,
+                ))
+            }
+        }
+    }
+}
+
 /// Get items that matches any given partition keys from a DynamoDB table.
 ///
 /// # Safety
@@ -181,6 +256,9 @@ pub fn tickets_into_chunks<'t>(
 }
 
 /// Get items that matches any given partition keys from a DynamoDB table.
+///
+/// Keys of [rate limit buckets](is_rate_limit_key) are never looked up, so that they
+/// cannot be retrieved as if they were tickets.
 pub async fn get_items_by_tickets<C>(
     config: &C,
     tickets: impl ExactSizeIterator<Item = &Ticket>,
@@ -189,13 +267,17 @@ pub async fn get_items_by_tickets<C>(
 where
     C: HasDynamoDBConfiguration,
 {
-    if tickets.len() == 0 {
+    let tickets = tickets
+        .filter(|ticket| !is_rate_limit_key(ticket))
+        .collect::<Vec<_>>();
+
+    if tickets.is_empty() {
         // If there are no tickets, return an empty vector.
         // This is mandatory because `itertools.chunks` panics on empty iterators.
         return Ok(vec![]);
     }
 
-    let chunks = tickets_into_chunks(tickets);
+    let chunks = tickets_into_chunks(tickets.into_iter());
 
     futures::future::try_join_all(
         // Iterate the chunks and get the items for each chunk.
@@ -222,6 +304,8 @@ where
     let handle = tokio::task::spawn_blocking(move || {
         items
             .into_iter()
+            // Pending tickets do not have a result yet.
+            .filter(|item| !item.is_pending())
             .map(|item| item.to_process_result(&dynamodb_partition_key))
             .collect::<Result<Vec<_>, _>>()
     });
@@ -253,11 +337,63 @@ where
         .and_then(|items| {
             items
                 .into_iter()
+                // Pending tickets do not have a status yet.
+                .filter(|item| !item.is_pending())
                 .map(|item| item.to_process_status(config.dynamodb_partition_key()))
                 .collect::<Result<Vec<_>, _>>()
         })
 }
 
+/// Get the [`TicketRecord`]s that matches any given partition keys from a DynamoDB table.
+///
+/// Tickets without any records are omitted from the results. The
+/// [owner](crate::models::message::Principal::owner) of each ticket is returned
+/// alongside its record, if any.
+pub async fn get_ticket_records_by_tickets<C>(
+    config: &C,
+    tickets: impl ExactSizeIterator<Item = &Ticket>,
+) -> Result<Vec<(Ticket, TicketRecord, Option<String>)>, CoffeeShopError>
+where
+    C: HasDynamoDBConfiguration,
+{
+    let projection_expression = vec![
+        config.dynamodb_partition_key().to_owned(),
+        SUCCESS_KEY.to_owned(),
+        OWNER_KEY.to_owned(),
+    ];
+
+    get_items_by_tickets(config, tickets, Some(&projection_expression))
+        .await
+        .and_then(|items| {
+            items
+                .into_iter()
+                .map(|item| {
+                    item.to_ticket_record(config.dynamodb_partition_key())
+                        .map(|(ticket, record)| (ticket, record, item.owner().map(str::to_owned)))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+}
+
+/// Get the [`TicketRecord`] of a single ticket from a DynamoDB table.
+///
+/// Returns [`None`] if the ticket is unknown, or its record had expired.
+pub async fn get_ticket_record_by_ticket<C>(
+    config: &C,
+    ticket: &Ticket,
+) -> Result<Option<TicketRecord>, CoffeeShopError>
+where
+    C: HasDynamoDBConfiguration,
+{
+    get_ticket_records_by_tickets(config, std::iter::once(ticket))
+        .await
+        .map(|records| {
+            records
+                .into_iter()
+                .find_map(|(found_ticket, record, _)| (found_ticket == *ticket).then_some(record))
+        })
+}
+
 /// Get a single processing result that matches the given partition key from a DynamoDB table.
 /// This function currently is a convenience wrapper around [`get_process_results_by_tickets`];
 /// which could take a bit more computation time than necessary, but reduces the maintenance
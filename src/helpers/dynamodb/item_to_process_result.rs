@@ -7,13 +7,30 @@ use crate::{
 use axum::http;
 use serde::de::DeserializeOwned;
 
-use super::{ERROR_KEY, OUTPUT_KEY, STATUS_KEY, SUCCESS_KEY};
+use super::{is_rate_limit_key, ERROR_KEY, OUTPUT_KEY, OWNER_KEY, STATUS_KEY, SUCCESS_KEY};
 
 use aws_sdk_dynamodb::types::AttributeValue;
 
 #[cfg(doc)]
 use crate::models::message::ProcessResult;
 
+/// The state of a ticket as recorded in the DynamoDB table.
+///
+/// A ticket without any record at all is either unknown, or its record has already
+/// expired; this is represented by the absence of a [`TicketRecord`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TicketRecord {
+    /// The ticket had been accepted by a [`Waiter`](crate::models::Waiter), but no
+    /// result is available yet.
+    Pending,
+
+    /// The ticket had been processed, successfully or otherwise.
+    Fulfilled {
+        /// Whether the processing was successful.
+        success: bool,
+    },
+}
+
 /// Trait for converting an item to a process result.
 pub trait ToProcessResult {
     /// Attempt to check the status of the item.
@@ -25,6 +42,21 @@ pub trait ToProcessResult {
     /// This does not consume the item; a minimal cloning is performed on the ticket.
     fn to_process_status(&self, partition_key: &str) -> Result<(Ticket, bool), CoffeeShopError>;
 
+    /// Attempt to get the [`TicketRecord`] of the item.
+    ///
+    /// Unlike [`to_process_status`](Self::to_process_status), this also accepts
+    /// pending records that do not have a success status yet.
+    fn to_ticket_record(
+        &self,
+        partition_key: &str,
+    ) -> Result<(Ticket, TicketRecord), CoffeeShopError>;
+
+    /// Check if the item is a pending record, i.e. no processing result is available.
+    fn is_pending(&self) -> bool;
+
+    /// Get the [owner](crate::models::message::Principal::owner) of the ticket, if any.
+    fn owner(&self) -> Option<&str>;
+
     /// Attempt to convert the item into a process result.
     ///
     /// The return type of this has a nested [`Result`]:
@@ -56,6 +88,38 @@ impl ToProcessResult for std::collections::HashMap<String, AttributeValue> {
         }
     }
 
+    fn to_ticket_record(
+        &self,
+        partition_key: &str,
+    ) -> Result<(Ticket, TicketRecord), CoffeeShopError> {
+        match (self.get(partition_key), self.get(SUCCESS_KEY)) {
+            (Some(AttributeValue::S(key)), _) if is_rate_limit_key(key) => {
+                Err(CoffeeShopError::AWSDynamoDBMalformedItem(
+                    "A rate limit bucket was retrieved in place of a ticket.".to_string(),
+                ))
+            }
+            (Some(AttributeValue::S(ticket)), Some(AttributeValue::Bool(success))) => Ok((
+                ticket.clone(),
+                TicketRecord::Fulfilled { success: *success },
+            )),
+            (Some(AttributeValue::S(ticket)), None) => Ok((ticket.clone(), TicketRecord::Pending)),
+            _ => Err(CoffeeShopError::AWSDynamoDBMalformedItem(
+                "A map was retrieved, but its structure could not be parsed.".to_string(),
+            )),
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        !self.contains_key(SUCCESS_KEY)
+    }
+
+    fn owner(&self) -> Option<&str> {
+        match self.get(OWNER_KEY) {
+            Some(AttributeValue::S(owner)) => Some(owner),
+            _ => None,
+        }
+    }
+
     fn to_process_result<O>(
         mut self,
         partition_key: &str,
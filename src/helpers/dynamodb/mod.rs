@@ -17,6 +17,28 @@ const ERROR_KEY: &str = "error";
 /// The key for the time-to-live of the processing result.
 const TTL_KEY: &str = "ttl";
 
+/// The key for the [owner](crate::models::message::Principal::owner) of the ticket.
+///
+/// This is absent if the ticket was created without authentication.
+const OWNER_KEY: &str = "owner";
+
+/// The status code recorded against a ticket that is still pending processing.
+///
+/// Pending records do not have a [`SUCCESS_KEY`]; this is what distinguishes them
+/// from a finished processing result.
+const PENDING_STATUS_CODE: u16 = 202;
+
+/// The prefix of the partition key for rate limit buckets, to tell them apart from
+/// tickets.
+const RATE_LIMIT_PREFIX: &str = "rate_limit#";
+
+/// The key for the number of tokens left in a rate limit bucket.
+const RATE_LIMIT_TOKENS_KEY: &str = "tokens";
+
+/// The key for the time a rate limit bucket was last updated, in seconds since the
+/// UNIX epoch.
+const RATE_LIMIT_UPDATED_AT_KEY: &str = "updated_at";
+
 mod config;
 pub use config::*;
 
@@ -29,6 +51,9 @@ pub use item_to_process_result::*;
 mod func;
 pub use func::*;
 
+mod rate_limit;
+pub use rate_limit::*;
+
 /// Alias for a DynamoDB item.
 pub type DynamoDBItem = std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>;
 
@@ -7,8 +7,13 @@
 //! If the result is a [`Err<_, CoffeeShopError>`], then an `error` field is added
 //! to the item with the status code of the error. The error message is customised
 //! by the error type of [`CoffeeShopError::ErrorSchema`].
+//!
+//! A ticket can also be reported as pending, in which case neither of the above
+//! fields are added, and the item will not overwrite any existing result.
 
-use super::{ERROR_KEY, OUTPUT_KEY, STATUS_KEY, SUCCESS_KEY, TTL_KEY};
+use super::{
+    ERROR_KEY, OUTPUT_KEY, OWNER_KEY, PENDING_STATUS_CODE, STATUS_KEY, SUCCESS_KEY, TTL_KEY,
+};
 use crate::{
     helpers,
     models::{message::ProcessResult, Ticket},
@@ -43,6 +48,21 @@ fn add_common_items(
         )
 }
 
+/// Add the [owner](crate::models::message::Principal::owner) of the ticket to the
+/// fluent builder, if any.
+pub fn add_owner(
+    builder: dynamodb::operation::put_item::builders::PutItemFluentBuilder,
+    owner: Option<&str>,
+) -> dynamodb::operation::put_item::builders::PutItemFluentBuilder {
+    match owner {
+        Some(owner) => builder.item(
+            OWNER_KEY,
+            dynamodb::types::AttributeValue::S(owner.to_owned()),
+        ),
+        None => builder,
+    }
+}
+
 /// Convert a processing result into a DynamoDB item.
 #[async_trait::async_trait]
 pub trait ToItem: Sized {
@@ -66,6 +86,17 @@ pub trait ToItem: Sized {
         ttl: &tokio::time::Duration,
     ) -> Self::Output;
 
+    /// Convert a ticket that is still pending processing into a DynamoDB item.
+    ///
+    /// The resultant item must not overwrite any existing item with the same
+    /// ticket, since the processing result could have been reported first.
+    async fn report_ticket_pending(
+        self,
+        partition_key: &str,
+        ticket: &Ticket,
+        ttl: &tokio::time::Duration,
+    ) -> Self::Output;
+
     /// Convert the processing result into a DynamoDB item.
     async fn report_ticket_result<O>(
         self,
@@ -137,4 +168,20 @@ impl ToItem for dynamodb::operation::put_item::builders::PutItemFluentBuilder {
             .item(SUCCESS_KEY, dynamodb::types::AttributeValue::Bool(false))
             .item(ERROR_KEY, dynamodb::types::AttributeValue::S(error_body)))
     }
+
+    async fn report_ticket_pending(
+        self,
+        partition_key: &str,
+        ticket: &Ticket,
+        ttl: &tokio::time::Duration,
+    ) -> Self::Output {
+        Ok(add_common_items(self, partition_key, ticket, ttl)
+            .item(
+                STATUS_KEY,
+                dynamodb::types::AttributeValue::N(PENDING_STATUS_CODE.to_string()),
+            )
+            // Never overwrite a result that had already been reported.
+            .condition_expression("attribute_not_exists(#partition_key)")
+            .expression_attribute_names("#partition_key", partition_key))
+    }
 }
@@ -0,0 +1,162 @@
+//! Helper functions to share [`TokenBucket`]s across [`Shop`](crate::models::Shop)s
+//! using the DynamoDB table as the backend.
+//!
+//! Each bucket is stored as an item keyed by [`RATE_LIMIT_PREFIX`] and the hashed
+//! client key. Updates are guarded by a condition on the previous
+//! [`RATE_LIMIT_UPDATED_AT_KEY`], so that concurrent updates from different shops
+//! do not overwrite each other; the loser of a race simply retries.
+
+use aws_sdk_dynamodb as dynamodb;
+
+use super::{
+    DynamoDBItem, HasDynamoDBConfiguration, RATE_LIMIT_PREFIX, RATE_LIMIT_TOKENS_KEY,
+    RATE_LIMIT_UPDATED_AT_KEY, TTL_KEY,
+};
+use crate::{
+    helpers::rate_limit::{RateLimitDecision, TokenBucket},
+    CoffeeShopError,
+};
+
+/// The maximum number of attempts to update a bucket under contention.
+const MAX_ATTEMPTS: usize = 5;
+
+/// The extra time in seconds a bucket is kept after it would have been refilled.
+const TTL_MARGIN: f64 = 60.;
+
+/// Check if a partition key belongs to a rate limit bucket rather than a ticket.
+///
+/// Buckets share the table with the tickets, so any key supplied by a client as a
+/// ticket must be checked against this before it is looked up.
+pub fn is_rate_limit_key(key: &str) -> bool {
+    key.starts_with(RATE_LIMIT_PREFIX)
+}
+
+/// Parse a number attribute from a DynamoDB item.
+fn get_number(item: &DynamoDBItem, key: &str) -> Result<f64, CoffeeShopError> {
+    item.get(key)
+        .and_then(|value| value.as_n().ok())
+        .and_then(|value| value.parse::<f64>().ok())
+        .ok_or_else(|| {
+            CoffeeShopError::AWSDynamoDBMalformedItem(format!(
+                "The rate limit bucket is missing a valid {key:?} attribute."
+            ))
+        })
+}
+
+/// Convert a DynamoDB item into a [`TokenBucket`].
+pub fn item_to_token_bucket(item: &DynamoDBItem) -> Result<TokenBucket, CoffeeShopError> {
+    Ok(TokenBucket {
+        tokens: get_number(item, RATE_LIMIT_TOKENS_KEY)?,
+        updated_at: get_number(item, RATE_LIMIT_UPDATED_AT_KEY)?,
+    })
+}
+
+/// Attempt to take `cost` tokens from the shared bucket of a client.
+///
+/// The `key` should already be hashed; it is stored as part of the partition key
+/// in plain text.
+///
+/// Rejected requests do not write to the table. If the bucket is still being
+/// contended after a few attempts, a [`CoffeeShopError::UnexpectedAWSResponse`]
+/// is returned.
+pub async fn acquire_rate_limit_tokens(
+    config: &dyn HasDynamoDBConfiguration,
+    key: &str,
+    rate: f64,
+    capacity: f64,
+    cost: u32,
+) -> Result<RateLimitDecision, CoffeeShopError> {
+    let client = dynamodb::Client::new(config.aws_config());
+    let table = config.dynamodb_table();
+    let partition_key = config.dynamodb_partition_key();
+    let item_key = format!("{RATE_LIMIT_PREFIX}{key}");
+
+    for _ in 0..MAX_ATTEMPTS {
+        let now = chrono::Utc::now().timestamp_micros() as f64 / 1e6;
+
+        let existing = client
+            .get_item()
+            .table_name(table)
+            .key(
+                partition_key,
+                dynamodb::types::AttributeValue::S(item_key.clone()),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|sdk_err| {
+                CoffeeShopError::from_aws_dynamodb_error(
+                    sdk_err.into_service_error().into(),
+                    config,
+                )
+            })?
+            .item
+            .map(|item| item_to_token_bucket(&item))
+            .transpose()?;
+
+        let mut bucket = existing.unwrap_or_else(|| TokenBucket::full(capacity, now));
+        let decision = bucket.try_acquire(now, rate, capacity, cost);
+
+        if !decision.allowed {
+            return Ok(decision);
+        }
+
+        let expiry = now + capacity / rate + TTL_MARGIN;
+        let request = client
+            .put_item()
+            .table_name(table)
+            .item(
+                partition_key,
+                dynamodb::types::AttributeValue::S(item_key.clone()),
+            )
+            .item(
+                RATE_LIMIT_TOKENS_KEY,
+                dynamodb::types::AttributeValue::N(bucket.tokens.to_string()),
+            )
+            .item(
+                RATE_LIMIT_UPDATED_AT_KEY,
+                dynamodb::types::AttributeValue::N(bucket.updated_at.to_string()),
+            )
+            .item(
+                TTL_KEY,
+                dynamodb::types::AttributeValue::N((expiry.ceil() as i64).to_string()),
+            );
+
+        let request = match existing {
+            Some(previous) => request
+                .condition_expression("#updated_at = :updated_at")
+                .expression_attribute_names("#updated_at", RATE_LIMIT_UPDATED_AT_KEY)
+                .expression_attribute_values(
+                    ":updated_at",
+                    dynamodb::types::AttributeValue::N(previous.updated_at.to_string()),
+                ),
+            None => request
+                .condition_expression("attribute_not_exists(#partition_key)")
+                .expression_attribute_names("#partition_key", partition_key),
+        };
+
+        match request.send().await {
+            Ok(_) => return Ok(decision),
+            Err(sdk_err) => {
+                let service_err = sdk_err.into_service_error();
+
+                if service_err.is_conditional_check_failed_exception() {
+                    crate::debug!(
+                        "Rate limit bucket {} was updated by another party; retrying.",
+                        item_key,
+                    );
+                    continue;
+                }
+
+                return Err(CoffeeShopError::from_aws_dynamodb_error(
+                    service_err.into(),
+                    config,
+                ));
+            }
+        }
+    }
+
+    Err(CoffeeShopError::UnexpectedAWSResponse(format!(
+        "Rate limit bucket {item_key} is under contention after {MAX_ATTEMPTS} attempts."
+    )))
+}
@@ -46,6 +46,7 @@ mod put_items {
                     &config,
                     &ticket,
                     $expected_result,
+                    None,
                 )
                 .await
                 .expect("Failed to put the processing result into the DynamoDB table.");
@@ -185,3 +186,88 @@ mod process_result_to_and_from_item {
         ))
     )));
 }
+
+mod ticket_records {
+    use super::*;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    macro_rules! create_test {
+        (
+            $name:ident($success:expr) -> $expected:expr
+        ) => {
+            #[test]
+            fn $name() {
+                let ticket = get_random_ticket();
+                let mut item = DynamoDBItem::new();
+                item.insert(PARTITION_KEY.to_owned(), AttributeValue::S(ticket.clone()));
+
+                if let Some(success) = $success {
+                    item.insert(SUCCESS_KEY.to_owned(), AttributeValue::Bool(success));
+                }
+
+                let (actual_ticket, actual_record) = item
+                    .to_ticket_record(PARTITION_KEY)
+                    .expect("Failed to convert the item to a ticket record.");
+
+                assert_eq!(actual_ticket, ticket, "The tickets do not match.");
+                assert_eq!(actual_record, $expected, "The records do not match.");
+                assert_eq!(
+                    item.is_pending(),
+                    $expected == TicketRecord::Pending,
+                    "The pending status does not match."
+                );
+            }
+        };
+    }
+
+    create_test!(pending(None::<bool>) -> TicketRecord::Pending);
+    create_test!(fulfilled_success(Some(true)) -> TicketRecord::Fulfilled { success: true });
+    create_test!(fulfilled_failure(Some(false)) -> TicketRecord::Fulfilled { success: false });
+
+    #[test]
+    fn rate_limit_bucket() {
+        let mut item = DynamoDBItem::new();
+        item.insert(
+            PARTITION_KEY.to_owned(),
+            AttributeValue::S(format!("{RATE_LIMIT_PREFIX}client")),
+        );
+        item.insert(
+            RATE_LIMIT_TOKENS_KEY.to_owned(),
+            AttributeValue::N("10".to_owned()),
+        );
+
+        assert!(matches!(
+            item.to_ticket_record(PARTITION_KEY),
+            Err(CoffeeShopError::AWSDynamoDBMalformedItem(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_bucket_lookup() {
+        let config = DynamoDBConfiguration {
+            table: "unreachable".to_owned(),
+            partition_key: PARTITION_KEY.to_owned(),
+            ttl: TTL,
+            aws_config: aws::SdkConfig::builder().build(),
+        };
+        let tickets = [format!("{RATE_LIMIT_PREFIX}client")];
+
+        // Nothing is sent to DynamoDB, so this does not need AWS access.
+        let records = get_ticket_records_by_tickets(&config, tickets.iter())
+            .await
+            .expect("A rate limit bucket should not be looked up.");
+
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let item = DynamoDBItem::new();
+
+        assert!(matches!(
+            item.to_ticket_record(PARTITION_KEY),
+            Err(CoffeeShopError::AWSDynamoDBMalformedItem(_))
+        ));
+    }
+}
@@ -0,0 +1,297 @@
+//! Prometheus metrics of the shop, served by the [`Waiter`] on `/metrics`.
+//!
+//! The metrics are kept in a process-wide [`Registry`], as some of them, such as the
+//! AWS errors, are recorded where there is no reference to the [`Shop`].
+
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use axum::http;
+
+use crate::CoffeeShopError;
+
+#[cfg(doc)]
+use crate::models::{Barista, Machine, Shop, Waiter};
+
+/// The prefix of all the metric names.
+const NAMESPACE: &str = "coffeeshop";
+
+/// The buckets of the latency histograms in seconds, from 5 milliseconds to 5 minutes.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10., 30., 60., 120., 300.,
+];
+
+/// The metrics of the shop.
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+
+    /// The HTTP requests served by the [`Waiter`], by route, outcome and status code.
+    pub requests: IntCounterVec,
+
+    /// The time the [`Waiter`] took to respond, by route.
+    pub request_duration: HistogramVec,
+
+    /// The tickets completed by the [`Barista`]s, by outcome and status code.
+    pub tickets: IntCounterVec,
+
+    /// The time the tickets spent in the SQS queue before being received, including
+    /// any delays between retries.
+    pub queue_wait: Histogram,
+
+    /// The time the machine took to process a ticket, by outcome.
+    pub machine_duration: HistogramVec,
+
+    /// The time between a [`Barista`] announcing a ticket and this shop hearing it.
+    pub notification_delay: Histogram,
+
+    /// The orders in this shop that are not yet fulfilled.
+    pub orders_in_flight: IntGauge,
+
+    /// The latest sample of the approximate number of tickets in the SQS queue.
+    pub queue_depth: IntGauge,
+
+    /// The multicast messages sent, received, or failed to be sent or received.
+    pub multicast_messages: IntCounterVec,
+
+    /// The errors returned by AWS, by service.
+    pub aws_errors: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+    Metrics::new().expect("The metrics are statically defined, so should always be valid.")
+});
+
+/// Get the process-wide [`Metrics`].
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Register a metric, returning it back.
+fn register<M>(registry: &Registry, metric: M) -> prometheus::Result<M>
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    registry.register(Box::new(metric.clone()))?;
+    Ok(metric)
+}
+
+fn counter(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<IntCounterVec> {
+    register(
+        registry,
+        IntCounterVec::new(Opts::new(name, help).namespace(NAMESPACE), labels)?,
+    )
+}
+
+fn histogram(registry: &Registry, name: &str, help: &str) -> prometheus::Result<Histogram> {
+    register(
+        registry,
+        Histogram::with_opts(
+            HistogramOpts::new(name, help)
+                .namespace(NAMESPACE)
+                .buckets(LATENCY_BUCKETS.to_vec()),
+        )?,
+    )
+}
+
+fn histogram_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<HistogramVec> {
+    register(
+        registry,
+        HistogramVec::new(
+            HistogramOpts::new(name, help)
+                .namespace(NAMESPACE)
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            labels,
+        )?,
+    )
+}
+
+fn gauge(registry: &Registry, name: &str, help: &str) -> prometheus::Result<IntGauge> {
+    register(
+        registry,
+        IntGauge::with_opts(Opts::new(name, help).namespace(NAMESPACE))?,
+    )
+}
+
+impl Metrics {
+    /// Create and register all the metrics in a new [`Registry`].
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+
+        Ok(Self {
+            requests: counter(
+                &registry,
+                "requests_total",
+                "HTTP requests served by the waiter.",
+                &["route", "outcome", "status"],
+            )?,
+            request_duration: histogram_vec(
+                &registry,
+                "request_duration_seconds",
+                "Time taken by the waiter to respond.",
+                &["route"],
+            )?,
+            tickets: counter(
+                &registry,
+                "tickets_total",
+                "Tickets completed by the baristas.",
+                &["outcome", "status"],
+            )?,
+            queue_wait: histogram(
+                &registry,
+                "queue_wait_seconds",
+                "Time the tickets spent in the queue before being received.",
+            )?,
+            machine_duration: histogram_vec(
+                &registry,
+                "machine_duration_seconds",
+                "Time taken by the machine to process a ticket.",
+                &["outcome"],
+            )?,
+            notification_delay: histogram(
+                &registry,
+                "notification_delay_seconds",
+                "Time between a ticket being announced and the announcement being heard.",
+            )?,
+            orders_in_flight: gauge(
+                &registry,
+                "orders_in_flight",
+                "Orders in this shop that are not yet fulfilled.",
+            )?,
+            queue_depth: gauge(
+                &registry,
+                "queue_depth",
+                "Latest sample of the approximate number of tickets in the queue.",
+            )?,
+            multicast_messages: counter(
+                &registry,
+                "multicast_messages_total",
+                "Multicast messages sent, received, or failed.",
+                &["event"],
+            )?,
+            aws_errors: counter(
+                &registry,
+                "aws_errors_total",
+                "Errors returned by AWS.",
+                &["service"],
+            )?,
+            registry,
+        })
+    }
+
+    /// Record an HTTP request served by the [`Waiter`].
+    pub fn record_request(
+        &self,
+        route: &str,
+        status: http::StatusCode,
+        duration: tokio::time::Duration,
+    ) {
+        let outcome = if status.is_server_error() {
+            "server_error"
+        } else if status.is_client_error() {
+            "client_error"
+        } else {
+            "success"
+        };
+
+        self.requests
+            .with_label_values(&[route, outcome, status.as_str()])
+            .inc();
+        self.request_duration
+            .with_label_values(&[route])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Record a ticket completed by a [`Barista`], with the status code of its result
+    /// if any.
+    pub fn record_ticket(&self, outcome: &str, status: Option<http::StatusCode>) {
+        self.tickets
+            .with_label_values(&[
+                outcome,
+                status.as_ref().map_or("", http::StatusCode::as_str),
+            ])
+            .inc();
+    }
+
+    /// Record a call to the [`Machine`] by a [`Barista`], labelled by whether it had
+    /// succeeded, failed, or exceeded its deadline.
+    pub fn record_machine_call<T>(
+        &self,
+        result: &Result<T, CoffeeShopError>,
+        duration: tokio::time::Duration,
+    ) {
+        let outcome = match result {
+            Ok(_) => "success",
+            Err(CoffeeShopError::ProcessingTimeout(_)) => "timeout",
+            Err(_) => "failure",
+        };
+
+        self.machine_duration
+            .with_label_values(&[outcome])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Record a multicast message event, which is one of `sent`, `received` or `failed`.
+    pub fn record_multicast(&self, event: &str) {
+        self.multicast_messages.with_label_values(&[event]).inc();
+    }
+
+    /// Record an error returned by an AWS service.
+    pub fn record_aws_error(&self, service: &str) {
+        self.aws_errors.with_label_values(&[service]).inc();
+    }
+
+    /// Encode all the metrics in the Prometheus text format.
+    pub fn encode(&self) -> Result<String, CoffeeShopError> {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|err| CoffeeShopError::MetricsEncodingError(err.to_string()))?;
+
+        String::from_utf8(buffer)
+            .map_err(|err| CoffeeShopError::MetricsEncodingError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let metrics = Metrics::new().unwrap();
+
+        metrics.record_request(
+            "/request",
+            http::StatusCode::TOO_MANY_REQUESTS,
+            tokio::time::Duration::from_millis(20),
+        );
+        metrics.record_ticket("abandoned", None);
+        metrics.record_aws_error("sqs");
+
+        let text = metrics.encode().unwrap();
+
+        assert!(text.contains(
+            r#"coffeeshop_requests_total{outcome="client_error",route="/request",status="429"} 1"#
+        ));
+        assert!(text.contains(
+            r#"coffeeshop_request_duration_seconds_bucket{route="/request",le="0.025"} 1"#
+        ));
+        assert!(text.contains(r#"coffeeshop_tickets_total{outcome="abandoned",status=""} 1"#));
+        assert!(text.contains(r#"coffeeshop_aws_errors_total{service="sqs"} 1"#));
+    }
+}
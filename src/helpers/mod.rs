@@ -1,11 +1,17 @@
 //! Helper functions for the Coffee Shop crate.
 //!
 
+pub mod auth;
 pub mod aws;
+pub mod capacity;
 pub mod dynamodb;
+pub mod metrics;
 pub mod multicast;
 pub mod order_chain;
+pub mod rate_limit;
 pub mod retry;
 pub mod serde;
 pub mod sqs;
 pub mod sts;
+pub mod telemetry;
+pub mod ticket_token;
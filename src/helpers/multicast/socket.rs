@@ -200,9 +200,7 @@ mod test {
             .await
             .map_err(|timeout| io::Error::new(io::ErrorKind::TimedOut, timeout))
             .and_then(|result| {
-                result.map_err(|join_error| {
-                    io::Error::new(io::ErrorKind::Other, join_error.to_string())
-                })
+                result.map_err(|join_error| io::Error::other(join_error.to_string()))
             })
             .expect("Failed to receive messages!")
         {
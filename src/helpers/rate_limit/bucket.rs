@@ -0,0 +1,189 @@
+use axum::http;
+
+/// The state of a token bucket for a single client.
+///
+/// The timestamps are in seconds since the UNIX epoch, so that the state can be
+/// shared across [`Shop`](crate::models::Shop)s on different hosts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenBucket {
+    /// The number of tokens left in the bucket at [`Self::updated_at`].
+    pub tokens: f64,
+
+    /// The time at which [`Self::tokens`] was last updated.
+    pub updated_at: f64,
+}
+
+impl TokenBucket {
+    /// Create a new full [`TokenBucket`] at the given time.
+    pub fn full(capacity: f64, now: f64) -> Self {
+        Self {
+            tokens: capacity,
+            updated_at: now,
+        }
+    }
+
+    /// Get the number of tokens in the bucket at the given time, after refilling.
+    pub fn tokens_at(&self, now: f64, rate: f64, capacity: f64) -> f64 {
+        // Clocks can go backwards; never drain the bucket because of it.
+        let elapsed = (now - self.updated_at).max(0.);
+
+        (self.tokens + elapsed * rate).min(capacity)
+    }
+
+    /// Check if the bucket would be full at the given time.
+    pub fn is_full_at(&self, now: f64, rate: f64, capacity: f64) -> bool {
+        self.tokens_at(now, rate, capacity) >= capacity
+    }
+
+    /// Attempt to take `cost` tokens from the bucket at the given time.
+    ///
+    /// A `cost` larger than the capacity can never be satisfied, so it is always
+    /// rejected without a [`RateLimitDecision::retry_after`].
+    ///
+    /// The bucket is only updated if the tokens are granted.
+    pub fn try_acquire(
+        &mut self,
+        now: f64,
+        rate: f64,
+        capacity: f64,
+        cost: u32,
+    ) -> RateLimitDecision {
+        let cost = f64::from(cost);
+        let tokens = self.tokens_at(now, rate, capacity);
+        let limit = capacity.floor() as u32;
+
+        if tokens >= cost {
+            *self = Self {
+                tokens: tokens - cost,
+                updated_at: now,
+            };
+
+            RateLimitDecision {
+                allowed: true,
+                limit,
+                remaining: self.tokens.floor() as u32,
+                reset: seconds_to_duration((capacity - self.tokens) / rate),
+                retry_after: None,
+            }
+        } else {
+            RateLimitDecision {
+                allowed: false,
+                limit,
+                remaining: tokens.floor() as u32,
+                reset: seconds_to_duration((capacity - tokens) / rate),
+                retry_after: (cost <= capacity)
+                    .then(|| seconds_to_duration((cost - tokens) / rate)),
+            }
+        }
+    }
+}
+
+/// Convert a number of seconds into a [`tokio::time::Duration`], saturating any
+/// invalid values.
+fn seconds_to_duration(secs: f64) -> tokio::time::Duration {
+    tokio::time::Duration::try_from_secs_f64(secs.max(0.)).unwrap_or(tokio::time::Duration::MAX)
+}
+
+/// The outcome of a rate limit check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+
+    /// The maximum number of requests that can be made in a burst.
+    pub limit: u32,
+
+    /// The number of requests that can still be made right now.
+    pub remaining: u32,
+
+    /// The time until the bucket is full again.
+    pub reset: tokio::time::Duration,
+
+    /// The time until the request can be retried; [`None`] if the request is allowed,
+    /// or if it costs more than the bucket can ever hold.
+    pub retry_after: Option<tokio::time::Duration>,
+}
+
+impl RateLimitDecision {
+    /// Get the `RateLimit-*` headers for this decision, as described by the IETF draft
+    /// [RateLimit header fields for HTTP].
+    ///
+    /// Durations are rounded up to the nearest second.
+    ///
+    /// [RateLimit header fields for HTTP]: https://datatracker.ietf.org/doc/draft-ietf-httpapi-ratelimit-headers/
+    pub fn headers(&self) -> [(http::HeaderName, http::HeaderValue); 3] {
+        [
+            (
+                http::HeaderName::from_static("ratelimit-limit"),
+                http::HeaderValue::from(self.limit),
+            ),
+            (
+                http::HeaderName::from_static("ratelimit-remaining"),
+                http::HeaderValue::from(self.remaining),
+            ),
+            (
+                http::HeaderName::from_static("ratelimit-reset"),
+                http::HeaderValue::from(self.reset.as_secs_f64().ceil() as u64),
+            ),
+        ]
+    }
+
+    /// Add the `RateLimit-*` headers for this decision to a response.
+    pub fn apply_headers(&self, response: &mut axum::response::Response) {
+        response.headers_mut().extend(self.headers());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 2.;
+    const CAPACITY: f64 = 10.;
+
+    macro_rules! create_test {
+        ($name:ident(
+            tokens=$tokens:literal,
+            elapsed=$elapsed:literal,
+            cost=$cost:literal
+        ) -> (
+            allowed=$allowed:literal,
+            remaining=$remaining:literal,
+            retry_after=$retry_after:expr
+        )) => {
+            #[test]
+            fn $name() {
+                let mut bucket = TokenBucket {
+                    tokens: $tokens,
+                    updated_at: 1000.,
+                };
+                let before = bucket;
+
+                let decision = bucket.try_acquire(1000. + $elapsed, RATE, CAPACITY, $cost);
+
+                assert_eq!(decision.allowed, $allowed);
+                assert_eq!(decision.limit, CAPACITY as u32);
+                assert_eq!(decision.remaining, $remaining);
+                assert_eq!(
+                    decision.retry_after,
+                    $retry_after.map(tokio::time::Duration::from_secs_f64)
+                );
+
+                if !$allowed {
+                    assert_eq!(
+                        bucket, before,
+                        "A rejected request should not drain the bucket."
+                    );
+                }
+            }
+        };
+    }
+
+    create_test!(full_bucket(tokens = 10., elapsed = 0., cost = 1) -> (allowed = true, remaining = 9, retry_after = None));
+    create_test!(empty_bucket(tokens = 0., elapsed = 0., cost = 1) -> (allowed = false, remaining = 0, retry_after = Some(0.5)));
+    create_test!(refilled_bucket(tokens = 0., elapsed = 1., cost = 1) -> (allowed = true, remaining = 1, retry_after = None));
+    create_test!(overfilled_bucket(tokens = 5., elapsed = 60., cost = 1) -> (allowed = true, remaining = 9, retry_after = None));
+    create_test!(clock_skew(tokens = 1., elapsed = -5., cost = 1) -> (allowed = true, remaining = 0, retry_after = None));
+    create_test!(batch_cost(tokens = 3., elapsed = 0., cost = 5) -> (allowed = false, remaining = 3, retry_after = Some(1.)));
+    create_test!(cost_above_capacity(tokens = 10., elapsed = 0., cost = 50) -> (allowed = false, remaining = 10, retry_after = None));
+}
@@ -0,0 +1,28 @@
+use sha2::Digest;
+
+/// The source of the key that identifies a client for rate limiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RateLimitKey {
+    /// The IP address of the connecting peer.
+    #[default]
+    ClientIp,
+
+    /// The first address in the `X-Forwarded-For` header, falling back to the IP
+    /// address of the connecting peer.
+    ///
+    /// Only use this behind a trusted load balancer, as clients can set this
+    /// header freely otherwise.
+    ForwardedFor,
+
+    /// The API key in the header specified by
+    /// [`Config::rate_limit_header`](crate::cli::Config::rate_limit_header).
+    ApiKey,
+
+    /// The value returned by [`QueryType::rate_limit_key`](crate::models::message::QueryType::rate_limit_key).
+    Query,
+}
+
+/// Hash a client key, so that secrets such as API keys are not stored in plain text.
+pub fn hash_key(key: &str) -> String {
+    format!("{:x}", sha2::Sha256::digest(key.as_bytes()))
+}
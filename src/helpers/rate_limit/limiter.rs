@@ -0,0 +1,95 @@
+use super::{hash_key, RateLimitDecision, TokenBucket};
+use crate::helpers::dynamodb::{self, HasDynamoDBConfiguration};
+
+const LOG_TARGET: &str = "coffeeshop::helpers::rate_limit";
+
+/// The number of local buckets beyond which full buckets are purged.
+///
+/// A full bucket is indistinguishable from a missing one, so this does not affect
+/// the rate limits; it merely bounds the memory used by idle clients.
+const MAX_LOCAL_BUCKETS: usize = 10_000;
+
+/// The settings of a [`RateLimiter`], as derived from
+/// [`Config`](crate::cli::Config::rate_limit_settings).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitSettings {
+    /// The number of tokens added to each bucket per second.
+    pub rate: f64,
+
+    /// The maximum number of tokens in each bucket.
+    pub capacity: f64,
+
+    /// Whether to share the buckets across shops through DynamoDB.
+    pub shared: bool,
+}
+
+/// A collection of [`TokenBucket`]s keyed by client.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: std::sync::Mutex<hashbrown::HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Create a new [`RateLimiter`] with no buckets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attempt to take `cost` tokens from the local bucket of a client.
+    pub fn acquire_local(
+        &self,
+        settings: &RateLimitSettings,
+        key: &str,
+        cost: u32,
+    ) -> RateLimitDecision {
+        let now = chrono::Utc::now().timestamp_micros() as f64 / 1e6;
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("The rate limit lock is poisoned; this should not be possible.");
+
+        if buckets.len() >= MAX_LOCAL_BUCKETS {
+            buckets.retain(|_, bucket| !bucket.is_full_at(now, settings.rate, settings.capacity));
+        }
+
+        buckets
+            .entry_ref(key)
+            .or_insert_with(|| TokenBucket::full(settings.capacity, now))
+            .try_acquire(now, settings.rate, settings.capacity, cost)
+    }
+
+    /// Attempt to take `cost` tokens from the bucket of a client.
+    ///
+    /// The key is hashed before use. If the buckets are shared but DynamoDB could not
+    /// be reached, the local bucket is used instead, so that an outage of the backend
+    /// does not reject all requests.
+    pub async fn acquire(
+        &self,
+        config: &dyn HasDynamoDBConfiguration,
+        settings: &RateLimitSettings,
+        key: &str,
+        cost: u32,
+    ) -> RateLimitDecision {
+        let key = hash_key(key);
+
+        if settings.shared {
+            match dynamodb::acquire_rate_limit_tokens(
+                config,
+                &key,
+                settings.rate,
+                settings.capacity,
+                cost,
+            )
+            .await
+            {
+                Ok(decision) => return decision,
+                Err(err) => crate::warn!(
+                    target: LOG_TARGET,
+                    "Failed to use the shared rate limit bucket, falling back to the local bucket: {err}",
+                ),
+            }
+        }
+
+        self.acquire_local(settings, &key, cost)
+    }
+}
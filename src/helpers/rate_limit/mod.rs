@@ -0,0 +1,15 @@
+//! Helper functions for per-client rate limiting in the [`Waiter`](crate::models::Waiter).
+//!
+//! Each client is given a token bucket, which refills at a steady rate up to a
+//! burst capacity. The buckets are kept in memory by default, or shared across
+//! [`Shop`](crate::models::Shop)s through the DynamoDB table.
+//!
+
+mod bucket;
+pub use bucket::*;
+
+mod key;
+pub use key::*;
+
+mod limiter;
+pub use limiter::*;
@@ -40,7 +40,7 @@ where
 /// # Parameters
 ///
 /// - `operation_name` - The name of the operation to be performed. This will be used in the logs.
-///                      It will be presented in the format of `Attempt to {operation_name} failed`.
+///   It will be presented in the format of `Attempt to {operation_name} failed`.
 /// - `task_factory` - A function that returns a future that will be executed.
 /// - `max_retries` - The maximum number of retries before giving up.
 pub async fn until_ok<T, E, FutT>(
@@ -58,3 +58,40 @@ where
     })
     .await
 }
+
+/// The maximum delay returned by [`exponential_backoff`], which is the maximum
+/// visibility timeout of an AWS SQS message.
+pub const MAX_BACKOFF: tokio::time::Duration = tokio::time::Duration::from_secs(12 * 60 * 60);
+
+/// Get the delay before a retry, doubling the `base` delay for every `retries` that
+/// had already been made; capped at [`MAX_BACKOFF`].
+pub fn exponential_backoff(base: tokio::time::Duration, retries: u32) -> tokio::time::Duration {
+    2_u32
+        .checked_pow(retries)
+        .and_then(|factor| base.checked_mul(factor))
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! create_test {
+        ($name:ident(base=$base:literal, retries=$retries:literal) -> $expected:expr) => {
+            #[test]
+            fn $name() {
+                assert_eq!(
+                    exponential_backoff(tokio::time::Duration::from_secs($base), $retries),
+                    $expected
+                );
+            }
+        };
+    }
+
+    create_test!(first_retry(base = 2, retries = 0) -> tokio::time::Duration::from_secs(2));
+    create_test!(third_retry(base = 2, retries = 2) -> tokio::time::Duration::from_secs(8));
+    create_test!(capped(base = 3600, retries = 4) -> MAX_BACKOFF);
+    create_test!(overflow(base = 1, retries = 40) -> MAX_BACKOFF);
+    create_test!(no_backoff(base = 0, retries = 5) -> tokio::time::Duration::ZERO);
+}
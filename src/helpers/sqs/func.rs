@@ -9,7 +9,75 @@ use super::{encoding, HasSQSConfiguration, StagedReceipt};
 
 const LOG_TARGET: &str = "coffeeshop::helpers::sqs::func";
 
+/// The maximum number of messages that AWS SQS accepts in a single `SendMessageBatch` call.
+pub const MAX_BATCH_ENTRIES: usize = 10;
+
+/// The message attributes of a ticket that are not part of its body.
+#[derive(Debug, Clone, Default)]
+struct TicketAttributes {
+    owner: Option<String>,
+    traceparent: Option<String>,
+}
+
+impl TicketAttributes {
+    fn from_input<Q, I>(input: &message::CombinedInput<Q, I>) -> Self
+    where
+        Q: message::QueryType,
+        I: serde::de::DeserializeOwned + serde::Serialize,
+    {
+        Self {
+            owner: input.principal.as_ref().map(message::Principal::owner),
+            traceparent: input
+                .trace_context
+                .as_ref()
+                .map(helpers::telemetry::TraceContext::traceparent),
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            (super::OWNER_ATTRIBUTE, self.owner.as_deref()),
+            (helpers::telemetry::TRACEPARENT, self.traceparent.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+    }
+
+    /// Build the message attributes, if any.
+    fn build(
+        &self,
+    ) -> Option<std::collections::HashMap<String, sqs::types::MessageAttributeValue>> {
+        let attributes = self
+            .entries()
+            .map(|(name, value)| {
+                (
+                    name.to_owned(),
+                    sqs::types::MessageAttributeValue::builder()
+                        .data_type("String")
+                        .string_value(value)
+                        .build()
+                        .expect("`data_type` is set; this should not fail."),
+                )
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+
+        (!attributes.is_empty()).then_some(attributes)
+    }
+
+    /// Get the size of the message attributes towards the AWS SQS message size limit.
+    fn size(&self) -> usize {
+        self.entries()
+            .map(|(name, value)| name.len() + "String".len() + value.len())
+            .sum()
+    }
+}
+
 /// Put a ticket into the AWS SQS queue.
+///
+/// The [owner](message::Principal::owner) of the ticket, if any, is recorded as the
+/// [`OWNER_ATTRIBUTE`](super::OWNER_ATTRIBUTE) message attribute, and its
+/// [trace context](message::CombinedInput::trace_context), if any, as the
+/// [`TRACEPARENT`](helpers::telemetry::TRACEPARENT) message attribute.
 pub async fn put_ticket<Q, I>(
     config: &dyn HasSQSConfiguration,
     input: message::CombinedInput<Q, I>,
@@ -20,12 +88,14 @@ where
 {
     let client = sqs::Client::new(config.aws_config());
 
+    let attributes = TicketAttributes::from_input(&input);
     let serialized_input = helpers::serde::serialize(input).await?;
 
     let response = client
         .send_message()
         .queue_url(config.sqs_queue_url())
         .message_body(encoding::encode(&serialized_input).await?)
+        .set_message_attributes(attributes.build())
         .send()
         .await
         .inspect_err(
@@ -48,6 +118,158 @@ where
     })
 }
 
+/// Split the sizes of encoded messages into batches that `SendMessageBatch` will accept.
+///
+/// Each batch contains at most [`MAX_BATCH_ENTRIES`] messages, and the total size of each
+/// batch does not exceed [`encoding::SIZE_LIMIT`]. The returned batches contain the indices
+/// of the messages in their original order.
+pub fn sizes_into_batches(sizes: impl Iterator<Item = (usize, usize)>) -> Vec<Vec<usize>> {
+    let mut batches = vec![];
+    let mut batch: Vec<usize> = vec![];
+    let mut batch_size = 0;
+
+    for (index, size) in sizes {
+        if !batch.is_empty()
+            && (batch.len() >= MAX_BATCH_ENTRIES || batch_size + size > encoding::SIZE_LIMIT)
+        {
+            batches.push(std::mem::take(&mut batch));
+            batch_size = 0;
+        }
+
+        batch.push(index);
+        batch_size += size;
+    }
+
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    batches
+}
+
+/// Put multiple tickets into the AWS SQS queue using `SendMessageBatch`.
+///
+/// The inputs are sent in batches of up to [`MAX_BATCH_ENTRIES`] messages. The results
+/// are returned in the same order as the inputs; each input can fail independently
+/// without affecting the rest.
+pub async fn put_tickets<Q, I>(
+    config: &dyn HasSQSConfiguration,
+    inputs: Vec<message::CombinedInput<Q, I>>,
+) -> Vec<Result<Ticket, CoffeeShopError>>
+where
+    Q: message::QueryType + 'static,
+    I: serde::de::DeserializeOwned + serde::Serialize + Send + Sync + 'static,
+{
+    let client = sqs::Client::new(config.aws_config());
+
+    // Encode all the inputs first; any failures here are final for that input.
+    let mut results: Vec<Result<Ticket, CoffeeShopError>> = Vec::with_capacity(inputs.len());
+    let mut bodies: Vec<Option<(String, TicketAttributes)>> = Vec::with_capacity(inputs.len());
+
+    for input in inputs {
+        let attributes = TicketAttributes::from_input(&input);
+        let body = async { encoding::encode(&helpers::serde::serialize(input).await?).await }.await;
+
+        match body {
+            Ok(body) => {
+                // Placeholder until the batch is sent.
+                results.push(Err(CoffeeShopError::UnexpectedAWSResponse(
+                    "No response received for this message in the batch.".to_string(),
+                )));
+                bodies.push(Some((body, attributes)));
+            }
+            Err(err) => {
+                results.push(Err(err));
+                bodies.push(None);
+            }
+        }
+    }
+
+    let batches = sizes_into_batches(bodies.iter().enumerate().filter_map(|(index, body)| {
+        body.as_ref()
+            .map(|(body, attributes)| (index, body.len() + attributes.size()))
+    }));
+
+    let responses = futures::future::join_all(batches.into_iter().map(|batch| {
+        let client = &client;
+        let bodies = &bodies;
+
+        async move {
+            let entries = batch
+                .iter()
+                .map(|index| {
+                    let (body, attributes) = bodies[*index].clone().unwrap_or_default();
+
+                    sqs::types::SendMessageBatchRequestEntry::builder()
+                        .id(index.to_string())
+                        .message_body(body)
+                        .set_message_attributes(attributes.build())
+                        .build()
+                        .expect("Both `id` and `message_body` are set; this should not fail.")
+                })
+                .collect::<Vec<_>>();
+
+            let response = client
+                .send_message_batch()
+                .queue_url(config.sqs_queue_url())
+                .set_entries(Some(entries))
+                .send()
+                .await
+                .inspect_err(|err| {
+                    crate::error!(target: LOG_TARGET, "Failed to send message batch: {err}", err = err)
+                })
+                .map_err(|sdk_err| {
+                    CoffeeShopError::from_aws_sqs_error(sdk_err.into_service_error().into(), config)
+                });
+
+            (batch, response)
+        }
+    }))
+    .await;
+
+    for (batch, response) in responses {
+        match response {
+            Ok(output) => {
+                for entry in output.successful() {
+                    if let Ok(index) = entry.id().parse::<usize>() {
+                        crate::info!(
+                            target: LOG_TARGET,
+                            "Sent message ID {message_id} in batch.",
+                            message_id = entry.message_id(),
+                        );
+                        results[index] = Ok(Ticket::from(entry.message_id()));
+                    }
+                }
+
+                for entry in output.failed() {
+                    if let Ok(index) = entry.id().parse::<usize>() {
+                        crate::warn!(
+                            target: LOG_TARGET,
+                            "Message #{index} in batch was rejected with {code}.",
+                            code = entry.code(),
+                        );
+                        results[index] = Err(CoffeeShopError::AWSSQSInvalidMessage(format!(
+                            "{code}: {message}",
+                            code = entry.code(),
+                            message = entry.message().unwrap_or("(No details provided)"),
+                        )));
+                    }
+                }
+            }
+            Err(err) => {
+                // The whole batch failed; report the same error for every message in it.
+                let schema = err.as_error_schema();
+
+                for index in batch {
+                    results[index] = Err(CoffeeShopError::ErrorSchema(schema.clone()));
+                }
+            }
+        }
+    }
+
+    results
+}
+
 /// Retrieve a ticket from the AWS SQS queue.
 pub async fn retrieve_ticket<Q, I, C>(
     config: &C,
@@ -125,3 +347,41 @@ pub async fn get_ticket_count(config: &dyn HasSQSConfiguration) -> Result<usize,
                 })
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! create_test {
+        (
+            $name:ident(
+                $sizes:expr,
+                $expected:expr
+            )
+        ) => {
+            #[test]
+            fn $name() {
+                let sizes: Vec<usize> = $sizes;
+
+                let batches = sizes_into_batches(sizes.into_iter().enumerate());
+                let batch_lengths = batches.iter().map(|batch| batch.len()).collect::<Vec<_>>();
+
+                assert_eq!(&batch_lengths, $expected);
+            }
+        };
+    }
+
+    create_test!(test_no_messages(vec![], &[] as &[usize]));
+    create_test!(test_1_message(vec![1], &[1]));
+    create_test!(test_10_messages(vec![1; 10], &[10]));
+    create_test!(test_11_messages(vec![1; 11], &[10, 1]));
+    create_test!(test_25_messages(vec![1; 25], &[10, 10, 5]));
+    create_test!(test_oversized_messages(
+        vec![encoding::SIZE_LIMIT / 2; 5],
+        &[2, 2, 1]
+    ));
+    create_test!(test_full_size_messages(
+        vec![encoding::SIZE_LIMIT; 3],
+        &[1, 1, 1]
+    ));
+}
@@ -5,6 +5,13 @@
 #[cfg(doc)]
 use crate::models::{Barista, Ticket, Waiter};
 
+/// The name of the message attribute containing the
+/// [owner](crate::models::message::Principal::owner) of the ticket.
+///
+/// The owner is also part of the message body; this attribute makes it visible to
+/// tools inspecting the queue without decoding the body.
+pub const OWNER_ATTRIBUTE: &str = "owner";
+
 mod config;
 pub use config::*;
 
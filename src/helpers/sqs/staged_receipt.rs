@@ -3,7 +3,7 @@ use aws_sdk_sqs as sqs;
 use std::sync::OnceLock;
 
 use crate::{
-    helpers::{retry, serde::deserialize, sqs::HasSQSConfiguration},
+    helpers::{metrics, retry, serde::deserialize, sqs::HasSQSConfiguration, telemetry},
     models::{message, Ticket},
     CoffeeShopError,
 };
@@ -39,6 +39,9 @@ where
     pub receipt_handle: String,
     pub queue_url: String,
 
+    /// The number of times this message had been received, including this time.
+    pub receive_count: u32,
+
     /// Completed
     completed: OnceLock<bool>,
 
@@ -78,6 +81,11 @@ where
             .queue_url(config.sqs_queue_url())
             .max_number_of_messages(1)
             .wait_time_seconds(timeout.as_secs() as i32)
+            .message_system_attribute_names(
+                sqs::types::MessageSystemAttributeName::ApproximateReceiveCount,
+            )
+            .message_system_attribute_names(sqs::types::MessageSystemAttributeName::SentTimestamp)
+            .message_attribute_names(telemetry::TRACEPARENT)
             // Visibility timeout is NOT set here; we will leave it for the queue to handle.
             // .visibility_timeout(30)
             .send()
@@ -102,8 +110,44 @@ where
             let ticket = message.message_id.ok_or_else(|| {
                 CoffeeShopError::UnexpectedAWSResponse("Missing SQS message ID".to_string())
             })?;
+            // This is approximate; if absent, assume this is the first attempt.
+            let receive_count = message
+                .attributes
+                .as_ref()
+                .and_then(|attributes| {
+                    attributes.get(&sqs::types::MessageSystemAttributeName::ApproximateReceiveCount)
+                })
+                .and_then(|count| count.parse::<u32>().ok())
+                .unwrap_or(1)
+                .max(1);
+
+            // The time the message was first sent to the queue, in epoch milliseconds.
+            if let Some(sent_at) = message
+                .attributes
+                .as_ref()
+                .and_then(|attributes| {
+                    attributes.get(&sqs::types::MessageSystemAttributeName::SentTimestamp)
+                })
+                .and_then(|timestamp| timestamp.parse::<i64>().ok())
+                .and_then(chrono::DateTime::from_timestamp_millis)
+            {
+                metrics::metrics().queue_wait.observe(
+                    (chrono::Utc::now() - sent_at)
+                        .to_std()
+                        .unwrap_or_default()
+                        .as_secs_f64(),
+                );
+            }
+
+            // The trace the ticket was put into the queue as part of, if any.
+            let trace_context = message
+                .message_attributes
+                .as_ref()
+                .and_then(|attributes| attributes.get(telemetry::TRACEPARENT))
+                .and_then(|attribute| attribute.string_value())
+                .and_then(telemetry::TraceContext::from_traceparent);
 
-            let message =
+            let message: message::CombinedInput<Q, I> =
                 deserialize(encoding::decode(&body).await?)
                 .inspect_err(
                     |err| {
@@ -126,6 +170,7 @@ where
                         }
                     }
                 )?;
+            let message = message.with_trace_context(trace_context);
 
             Ok(Self {
                 client,
@@ -133,6 +178,7 @@ where
                 message,
                 receipt_handle,
                 queue_url: config.sqs_queue_url().to_owned(),
+                receive_count,
                 completed: OnceLock::new(),
                 config,
             })
@@ -141,6 +187,28 @@ where
         }
     }
 
+    /// Stage a message that was never received from SQS, so that the processing of a
+    /// ticket can be tested without a queue.
+    ///
+    /// The receipt is already completed, as there is nothing to reply to.
+    #[cfg(test)]
+    pub(crate) fn without_queue(
+        config: &'c C,
+        ticket: Ticket,
+        message: message::CombinedInput<Q, I>,
+    ) -> Self {
+        Self {
+            client: sqs::Client::new(config.aws_config()),
+            ticket,
+            message,
+            receipt_handle: String::new(),
+            queue_url: config.sqs_queue_url().to_owned(),
+            receive_count: 1,
+            completed: OnceLock::from(true),
+            config,
+        }
+    }
+
     /// Get the query from the message.
     pub fn query(&self) -> &Q {
         &self.message.query
@@ -151,6 +219,35 @@ where
         self.message.input.as_ref()
     }
 
+    /// Get the authenticated principal from the message, if any.
+    pub fn principal(&self) -> Option<&message::Principal> {
+        self.message.principal.as_ref()
+    }
+
+    /// Get the trace context the ticket was put into the queue with, if any.
+    pub fn trace_context(&self) -> Option<&telemetry::TraceContext> {
+        self.message.trace_context.as_ref()
+    }
+
+    /// Change the visibility timeout of the message, such that it can be received
+    /// again after the given number of seconds.
+    async fn change_visibility(&self, visibility_timeout: i32) -> Result<(), CoffeeShopError> {
+        self.client
+            .change_message_visibility()
+            .queue_url(&self.queue_url)
+            .receipt_handle(&self.receipt_handle)
+            .visibility_timeout(visibility_timeout)
+            .send()
+            .await
+            .map_err(|sdk_err| {
+                CoffeeShopError::from_aws_sqs_error(
+                    sdk_err.into_service_error().into(),
+                    self.config,
+                )
+            })
+            .map(|_output| ())
+    }
+
     /// Mark the message as completed.
     pub async fn complete(self, result: bool) -> Result<(), CoffeeShopError> {
         // Check if the message has already been completed; if so, return an error.
@@ -195,20 +292,7 @@ where
                 );
 
                 // Change the visibility of the message back to visible.
-                self.client
-                    .change_message_visibility()
-                    .queue_url(&self.queue_url)
-                    .receipt_handle(&self.receipt_handle)
-                    .visibility_timeout(0)
-                    .send()
-                    .await
-                    .map_err(|sdk_err| {
-                        CoffeeShopError::from_aws_sqs_error(
-                            sdk_err.into_service_error().into(),
-                            self.config,
-                        )
-                    })
-                    .map(|_output| ())
+                self.change_visibility(0).await
             }
         };
 
@@ -224,6 +308,33 @@ where
     pub async fn delete(self) -> Result<(), CoffeeShopError> {
         self.complete(true).await
     }
+
+    /// Return the message to the queue, such that it will only be visible again after
+    /// the given delay, to be retried.
+    ///
+    /// The delay is capped at the maximum visibility timeout of [`retry::MAX_BACKOFF`].
+    pub async fn retry_after(self, delay: tokio::time::Duration) -> Result<(), CoffeeShopError> {
+        self.completed
+            .set(false)
+            .map_err(|_| CoffeeShopError::AWSSQSStagedReceiptAlreadyCompleted("retried"))?;
+
+        crate::warn!(
+            target: LOG_TARGET,
+            "Returning ticket {} to the queue, to be retried after {:?}.",
+            self.ticket,
+            delay,
+        );
+
+        let visibility_timeout = i32::try_from(delay.min(retry::MAX_BACKOFF).as_secs())
+            .expect("The maximum backoff should fit into an i32.");
+
+        retry::until_ok(
+            "return SQS message for retry",
+            || self.change_visibility(visibility_timeout),
+            MAX_COMPLETION_RETRIES,
+        )
+        .await
+    }
 }
 
 impl<Q, I, C> Drop for StagedReceipt<'_, Q, I, C>
@@ -0,0 +1,221 @@
+use axum::http::HeaderMap;
+
+/// The name of the HTTP header and the AWS SQS message attribute carrying the
+/// [`TraceContext`], as per the [W3C Trace Context](https://www.w3.org/TR/trace-context/).
+pub const TRACEPARENT: &str = "traceparent";
+
+/// The identity of a span within a distributed trace, as per the
+/// [W3C Trace Context](https://www.w3.org/TR/trace-context/).
+///
+/// This is carried across hosts in the `traceparent` HTTP header, AWS SQS message
+/// attribute and multicast message, so that the spans of the same request can be
+/// correlated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    /// The ID of the whole trace.
+    pub trace_id: u128,
+
+    /// The ID of this span.
+    pub span_id: u64,
+
+    /// The ID of the parent span, if any.
+    pub parent_span_id: Option<u64>,
+
+    /// Whether the trace is sampled by the caller.
+    pub sampled: bool,
+}
+
+/// Generate a random, non-zero ID.
+fn random_id() -> u128 {
+    loop {
+        let id = uuid::Uuid::new_v4().as_u128();
+        if id != 0 {
+            break id;
+        }
+    }
+}
+
+impl TraceContext {
+    /// Start a new trace.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: random_id(),
+            span_id: random_id() as u64 | 1,
+            parent_span_id: None,
+            sampled: true,
+        }
+    }
+
+    /// Create the context of a child span of this one.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_id() as u64 | 1,
+            parent_span_id: Some(self.span_id),
+            ..*self
+        }
+    }
+
+    /// Create the context of a child span of the given one, or start a new trace.
+    pub fn child_of(parent: Option<&Self>) -> Self {
+        parent.map_or_else(Self::new_root, Self::child)
+    }
+
+    /// Parse a `traceparent` value.
+    ///
+    /// Returns [`None`] if the value is invalid, in which case a new trace should be
+    /// started instead.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+
+        // Later versions may append more fields, but version `00` may not.
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+
+        let parse = |hex: &str, len: usize| {
+            (hex.len() == len
+                && hex
+                    .bytes()
+                    .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f')))
+            .then(|| u128::from_str_radix(hex, 16).ok())
+            .flatten()
+            .filter(|id| *id != 0)
+        };
+
+        Some(Self {
+            trace_id: parse(trace_id, 32)?,
+            span_id: parse(span_id, 16)? as u64,
+            parent_span_id: None,
+            sampled: u8::from_str_radix(flags, 16).ok()? & 1 == 1,
+        })
+    }
+
+    /// Get the context of the `traceparent` header, if any is valid.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(TRACEPARENT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_traceparent)
+    }
+
+    /// Format the context as a `traceparent` value.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{trace_id}-{span_id}-{flags:02x}",
+            trace_id = self.trace_id_hex(),
+            span_id = self.span_id_hex(),
+            flags = u8::from(self.sampled),
+        )
+    }
+
+    /// Get the trace ID in hexadecimal.
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// Get the span ID in hexadecimal.
+    pub fn span_id_hex(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// Get the parent span ID in hexadecimal, or an empty string if there is none.
+    pub fn parent_span_id_hex(&self) -> String {
+        self.parent_span_id
+            .map(|id| format!("{id:016x}"))
+            .unwrap_or_default()
+    }
+}
+
+/// Create an `INFO` [`tracing::Span`] identified by a [`TraceContext`], with any
+/// additional fields.
+macro_rules! context_span {
+    ($name:literal, $context:expr $(, $($fields:tt)+)?) => {{
+        let context: &$crate::helpers::telemetry::TraceContext = &$context;
+
+        ::tracing::info_span!(
+            $name,
+            trace_id = %context.trace_id_hex(),
+            span_id = %context.span_id_hex(),
+            parent_span_id = %context.parent_span_id_hex(),
+            $($($fields)+)?
+        )
+    }};
+}
+pub(crate) use context_span;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! create_test {
+        ($name:ident($value:expr) -> $expected:expr) => {
+            #[test]
+            fn $name() {
+                assert_eq!(TraceContext::from_traceparent($value), $expected);
+            }
+        };
+    }
+
+    create_test!(
+        valid("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01") -> Some(TraceContext {
+            trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+            span_id: 0x00f067aa0ba902b7,
+            parent_span_id: None,
+            sampled: true,
+        })
+    );
+    create_test!(
+        not_sampled("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00")
+            -> Some(TraceContext {
+                trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+                span_id: 0x00f067aa0ba902b7,
+                parent_span_id: None,
+                sampled: false,
+            })
+    );
+    create_test!(
+        future_version("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra")
+            -> Some(TraceContext {
+                trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+                span_id: 0x00f067aa0ba902b7,
+                parent_span_id: None,
+                sampled: true,
+            })
+    );
+    create_test!(
+        extra_fields("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra") -> None
+    );
+    create_test!(
+        invalid_version("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01") -> None
+    );
+    create_test!(
+        zero_trace_id("00-00000000000000000000000000000000-00f067aa0ba902b7-01") -> None
+    );
+    create_test!(
+        uppercase("00-4BF92F3577B34DA6A3CE929D0E0E4736-00F067AA0BA902B7-01") -> None
+    );
+    create_test!(
+        truncated("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7") -> None
+    );
+
+    #[test]
+    fn round_trip() {
+        let root = TraceContext::new_root();
+        let child = root.child();
+
+        assert_eq!(child.trace_id, root.trace_id);
+        assert_eq!(child.parent_span_id, Some(root.span_id));
+        assert_ne!(child.span_id, root.span_id);
+
+        let parsed = TraceContext::from_traceparent(&child.traceparent()).unwrap();
+        assert_eq!(
+            parsed,
+            TraceContext {
+                parent_span_id: None,
+                ..child
+            }
+        );
+    }
+}
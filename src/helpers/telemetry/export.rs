@@ -0,0 +1,224 @@
+use std::{io::Write, sync::Mutex};
+
+use serde_json::{Map, Value};
+use tracing::{field::Field, span, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+#[cfg(doc)]
+use super::TraceContext;
+
+/// The state of a span kept until it closes.
+struct SpanRecord {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    start: chrono::DateTime<chrono::Utc>,
+    started: std::time::Instant,
+    fields: Map<String, Value>,
+}
+
+/// Collects the fields of a span into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl tracing::field::Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{value:?}").into());
+    }
+}
+
+/// A [`Layer`] that writes each closed span of a trace as a line of JSON.
+///
+/// The trace, span and parent span IDs are taken from the fields of a span created
+/// from a [`TraceContext`]; other spans inherit the trace of their closest traced
+/// ancestor, and spans outside of any trace are not written. Only spans at `INFO` or
+/// above are written.
+///
+/// Each line is an object of `name`, `target`, `trace_id`, `span_id`,
+/// `parent_span_id`, `start`, `duration` in seconds and the remaining `fields`.
+#[derive(Debug)]
+pub struct JsonSpanLayer<W> {
+    writer: Mutex<W>,
+}
+
+impl<W> JsonSpanLayer<W>
+where
+    W: Write + Send + 'static,
+{
+    /// Create a new [`JsonSpanLayer`] writing to the given writer.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Take a non-empty ID out of the fields.
+    fn take_id(fields: &mut Map<String, Value>, name: &str) -> Option<String> {
+        match fields.remove(name) {
+            Some(Value::String(id)) if !id.is_empty() => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl<S, W> Layer<S> for JsonSpanLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: Write + Send + 'static,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        if *span.metadata().level() > Level::INFO {
+            return;
+        }
+
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+
+        // The closest ancestor that is part of a trace, if any.
+        let parent = span.scope().skip(1).find_map(|ancestor| {
+            ancestor
+                .extensions()
+                .get::<SpanRecord>()
+                .map(|record| (record.trace_id.clone(), record.span_id.clone()))
+        });
+
+        let Some(trace_id) = Self::take_id(&mut fields, "trace_id")
+            .or_else(|| parent.as_ref().map(|(trace_id, _)| trace_id.clone()))
+        else {
+            return;
+        };
+
+        let record = SpanRecord {
+            trace_id,
+            span_id: Self::take_id(&mut fields, "span_id")
+                .unwrap_or_else(|| format!("{:016x}", uuid::Uuid::new_v4().as_u128() as u64)),
+            parent_span_id: Self::take_id(&mut fields, "parent_span_id")
+                .or_else(|| parent.map(|(_, span_id)| span_id)),
+            start: chrono::Utc::now(),
+            started: std::time::Instant::now(),
+            fields,
+        };
+
+        span.extensions_mut().insert(record);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(record) = span.extensions_mut().get_mut::<SpanRecord>() {
+                values.record(&mut JsonVisitor(&mut record.fields));
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(record) = span.extensions_mut().remove::<SpanRecord>() else {
+            return;
+        };
+
+        let line = serde_json::json!({
+            "name": span.name(),
+            "target": span.metadata().target(),
+            "trace_id": record.trace_id,
+            "span_id": record.span_id,
+            "parent_span_id": record.parent_span_id,
+            "start": record.start,
+            "duration": record.started.elapsed().as_secs_f64(),
+            "fields": record.fields,
+        });
+
+        let mut writer = self
+            .writer
+            .lock()
+            .expect("The span writer lock is poisoned; this should not be possible.");
+
+        // Exporting is best effort; a failure to write must not affect the shop.
+        let _ = writeln!(writer, "{line}").and_then(|_| writer.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::telemetry::{context_span, TraceContext};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// A writer to a shared buffer.
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn exports_traced_spans() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(JsonSpanLayer::new(buffer.clone()));
+        let context = TraceContext::new_root().child();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = context_span!("process_ticket", context, ticket = "abc").entered();
+            let _child = tracing::info_span!("machine_call").entered();
+            let _hidden = tracing::debug_span!("verbose").entered();
+        });
+        tracing::subscriber::with_default(
+            tracing_subscriber::registry().with(JsonSpanLayer::new(buffer.clone())),
+            || {
+                let _untraced = tracing::info_span!("untraced").entered();
+            },
+        );
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = output
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        // Spans are written as they close, so the innermost comes first.
+        assert_eq!(lines.len(), 2, "Unexpected output: {output}");
+        let (child, parent) = (&lines[0], &lines[1]);
+
+        assert_eq!(parent["name"], "process_ticket");
+        assert_eq!(parent["trace_id"], context.trace_id_hex());
+        assert_eq!(parent["span_id"], context.span_id_hex());
+        assert_eq!(parent["parent_span_id"], context.parent_span_id_hex());
+        assert_eq!(parent["fields"]["ticket"], "abc");
+
+        assert_eq!(child["name"], "machine_call");
+        assert_eq!(child["trace_id"], context.trace_id_hex());
+        assert_eq!(child["parent_span_id"], context.span_id_hex());
+    }
+}
@@ -0,0 +1,53 @@
+//! Distributed tracing of the tickets across the [`Waiter`]s and [`Barista`]s of a
+//! cluster.
+//!
+//! Each request starts or continues a trace from its `traceparent` header; the
+//! [`TraceContext`] is then passed along in the AWS SQS message attributes and the
+//! multicast messages, so that the [`tracing`] spans of the same ticket on different
+//! hosts share the same trace ID.
+//!
+//! The spans can be exported as JSON lines with [`init_json_export`], or by any
+//! other [`tracing`] subscriber installed by the application.
+
+use std::path::Path;
+
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::CoffeeShopError;
+
+#[cfg(doc)]
+use crate::models::{Barista, Waiter};
+
+const LOG_TARGET: &str = "coffeeshop::helpers::telemetry";
+
+mod context;
+pub use context::*;
+
+mod export;
+pub use export::*;
+
+/// Export the spans of all traces as JSON lines appended to the given file.
+///
+/// This installs a global [`tracing`] subscriber; if the application has already
+/// installed one, that is kept and nothing is exported.
+pub fn init_json_export(path: &Path) -> Result<(), CoffeeShopError> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| CoffeeShopError::InvalidConfiguration {
+            field: "trace_file",
+            message: format!("could not open {path:?}: {err}"),
+        })?;
+
+    let subscriber = tracing_subscriber::registry().with(JsonSpanLayer::new(file));
+
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        crate::warn!(
+            target: LOG_TARGET,
+            "A global tracing subscriber is already installed; not exporting spans to {path:?}.",
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,229 @@
+//! Helper functions to hand out opaque, signed ticket tokens instead of the raw AWS SQS
+//! message IDs.
+//!
+//! A token is made up of two base64url encoded parts separated by a `.`:
+//!
+//! - the payload `<issued_at>:<expires_at>:<ticket>`, with the timestamps in seconds
+//!   since the UNIX epoch; and
+//! - the `HMAC-SHA256` signature of the payload.
+//!
+
+use base64::Engine;
+
+use crate::{cli::Config, models::Ticket, CoffeeShopError};
+
+/// The base64 engine used for both parts of a token.
+const TOKEN_ENCODER: base64::engine::GeneralPurpose =
+    base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// Signs [`Ticket`]s into opaque tokens, and verifies them back into [`Ticket`]s.
+#[derive(Debug)]
+pub struct TicketSigner {
+    key: ring::hmac::Key,
+
+    /// The time a token remains valid after it is issued.
+    pub ttl: tokio::time::Duration,
+}
+
+impl TicketSigner {
+    /// Create a new [`TicketSigner`] with the given secret.
+    pub fn new(secret: &[u8], ttl: tokio::time::Duration) -> Self {
+        Self {
+            key: ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret),
+            ttl,
+        }
+    }
+
+    /// Build a [`TicketSigner`] from the [`Config`], reading the secret from the
+    /// environment variable specified.
+    ///
+    /// Returns [`None`] if ticket tokens are not enabled. Tokens expire after
+    /// [`Config::ticket_token_ttl`], or the result TTL if not set.
+    pub fn from_config(config: &Config) -> Result<Option<Self>, CoffeeShopError> {
+        let Some(var) = &config.ticket_token_secret_env else {
+            return Ok(None);
+        };
+
+        let secret = std::env::var(var)
+            .map_err(|err| CoffeeShopError::InvalidConfiguration {
+                field: "ticket_token_secret_env",
+                message: format!("could not read ${var}: {err}"),
+            })
+            .and_then(|secret| {
+                if secret.is_empty() {
+                    Err(CoffeeShopError::InvalidConfiguration {
+                        field: "ticket_token_secret_env",
+                        message: format!("${var} is empty."),
+                    })
+                } else {
+                    Ok(secret)
+                }
+            })?;
+
+        let ttl = match config.ticket_token_ttl {
+            Some(secs) if secs.is_finite() && secs > 0. => {
+                tokio::time::Duration::from_secs_f32(secs)
+            }
+            Some(secs) => {
+                return Err(CoffeeShopError::InvalidConfiguration {
+                    field: "ticket_token_ttl",
+                    message: format!("must be positive number, found {secs}."),
+                })
+            }
+            None => config.dynamodb_ttl(),
+        };
+
+        Ok(Some(Self::new(secret.as_bytes(), ttl)))
+    }
+
+    /// Sign a [`Ticket`] into a token issued at the given time.
+    pub fn sign_at(&self, ticket: &Ticket, now: i64) -> String {
+        let expires_at = now.saturating_add(i64::try_from(self.ttl.as_secs()).unwrap_or(i64::MAX));
+        let payload = format!("{now}:{expires_at}:{ticket}");
+        let signature = ring::hmac::sign(&self.key, payload.as_bytes());
+
+        format!(
+            "{payload}.{signature}",
+            payload = TOKEN_ENCODER.encode(payload),
+            signature = TOKEN_ENCODER.encode(signature.as_ref()),
+        )
+    }
+
+    /// Sign a [`Ticket`] into a token issued now.
+    pub fn sign(&self, ticket: &Ticket) -> String {
+        self.sign_at(ticket, chrono::Utc::now().timestamp())
+    }
+
+    /// Verify a token at the given time, and decode the [`Ticket`] from it.
+    ///
+    /// - A token that is not in the format issued by any [`TicketSigner`] is
+    ///   [forged](CoffeeShopError::TicketTokenForged).
+    /// - A token in the right format, but whose signature does not match its payload, is
+    ///   [tampered](CoffeeShopError::TicketTokenTampered).
+    /// - A token with a valid signature past its expiry is
+    ///   [expired](CoffeeShopError::TicketTokenExpired).
+    pub fn verify_at(&self, token: &str, now: i64) -> Result<Ticket, CoffeeShopError> {
+        let forged = |message: &str| CoffeeShopError::TicketTokenForged(message.to_owned());
+
+        let (payload, signature) = token
+            .split_once('.')
+            .ok_or_else(|| forged("The ticket is not a signed token."))?;
+
+        let payload = TOKEN_ENCODER
+            .decode(payload)
+            .map_err(|_| forged("The ticket payload is not valid base64url."))?;
+        let signature = TOKEN_ENCODER
+            .decode(signature)
+            .map_err(|_| forged("The ticket signature is not valid base64url."))?;
+
+        // Check the structure first; only the signature can tell if the content is genuine.
+        let (issued_at, expires_at, ticket) = std::str::from_utf8(&payload)
+            .ok()
+            .and_then(|payload| {
+                let mut parts = payload.splitn(3, ':');
+
+                Some((
+                    parts.next()?.parse::<i64>().ok()?,
+                    parts.next()?.parse::<i64>().ok()?,
+                    parts.next().filter(|ticket| !ticket.is_empty())?,
+                ))
+            })
+            .ok_or_else(|| forged("The ticket payload is malformed."))?;
+
+        ring::hmac::verify(&self.key, &payload, &signature)
+            .map_err(|_| CoffeeShopError::TicketTokenTampered)?;
+
+        if now >= expires_at {
+            return Err(CoffeeShopError::TicketTokenExpired {
+                issued_at: chrono::DateTime::from_timestamp(issued_at, 0).unwrap_or_default(),
+                expired_at: chrono::DateTime::from_timestamp(expires_at, 0).unwrap_or_default(),
+            });
+        }
+
+        Ok(ticket.to_owned())
+    }
+
+    /// Verify a token now, and decode the [`Ticket`] from it.
+    pub fn verify(&self, token: &str) -> Result<Ticket, CoffeeShopError> {
+        self.verify_at(token, chrono::Utc::now().timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICKET: &str = "2d8ba4e2-3c4b-4bd4-9a0c-5f7f2f6c4c1a";
+    const ISSUED_AT: i64 = 1_700_000_000;
+    const TTL: u64 = 3600;
+
+    fn signer() -> TicketSigner {
+        TicketSigner::new(b"ticket-secret", tokio::time::Duration::from_secs(TTL))
+    }
+
+    /// Replace the payload of a token, keeping its signature.
+    fn replace_payload(token: &str, payload: &str) -> String {
+        let (_, signature) = token.split_once('.').unwrap();
+
+        format!("{}.{signature}", TOKEN_ENCODER.encode(payload))
+    }
+
+    macro_rules! create_test {
+        ($name:ident($token:expr, elapsed=$elapsed:expr) -> $expected:pat $(if $guard:expr)?) => {
+            #[test]
+            fn $name() {
+                let token: String = $token;
+
+                let result = signer().verify_at(&token, ISSUED_AT + $elapsed);
+                assert!(
+                    matches!(&result, $expected $(if $guard)?),
+                    "Unexpected result: {result:?}"
+                );
+            }
+        };
+    }
+
+    create_test!(
+        valid(signer().sign_at(&TICKET.to_owned(), ISSUED_AT), elapsed = 10)
+            -> Ok(ticket) if ticket == TICKET
+    );
+    create_test!(
+        expired(signer().sign_at(&TICKET.to_owned(), ISSUED_AT), elapsed = TTL as i64)
+            -> Err(CoffeeShopError::TicketTokenExpired { .. })
+    );
+    create_test!(
+        raw_ticket(TICKET.to_owned(), elapsed = 0)
+            -> Err(CoffeeShopError::TicketTokenForged(_))
+    );
+    create_test!(
+        malformed_payload(
+            replace_payload(&signer().sign_at(&TICKET.to_owned(), ISSUED_AT), TICKET),
+            elapsed = 0
+        ) -> Err(CoffeeShopError::TicketTokenForged(_))
+    );
+    create_test!(
+        tampered_ticket(
+            replace_payload(
+                &signer().sign_at(&TICKET.to_owned(), ISSUED_AT),
+                &format!("{ISSUED_AT}:{}:someone-elses-ticket", ISSUED_AT + TTL as i64),
+            ),
+            elapsed = 0
+        ) -> Err(CoffeeShopError::TicketTokenTampered)
+    );
+    create_test!(
+        tampered_expiry(
+            replace_payload(
+                &signer().sign_at(&TICKET.to_owned(), ISSUED_AT),
+                &format!("{ISSUED_AT}:{}:{TICKET}", i64::MAX),
+            ),
+            elapsed = TTL as i64
+        ) -> Err(CoffeeShopError::TicketTokenTampered)
+    );
+    create_test!(
+        different_secret(
+            TicketSigner::new(b"another-secret", tokio::time::Duration::from_secs(TTL))
+                .sign_at(&TICKET.to_owned(), ISSUED_AT),
+            elapsed = 0
+        ) -> Err(CoffeeShopError::TicketTokenTampered)
+    );
+}
@@ -53,7 +53,7 @@ pub mod models;
 pub mod cli;
 pub use cli::DEFAULT_PORT;
 
-mod logger;
+pub mod logger;
 
 /// Exports all the necessary types for the user to implement the coffee machine.
 pub mod prelude {
@@ -65,8 +65,12 @@ pub mod prelude {
     }
     pub use super::cli::Config;
     pub use super::helpers::aws;
+    #[cfg(feature = "wasm")]
+    pub use super::models::WasmMachine;
     pub use super::models::{
-        message::QueryType, Announcer, Barista, CollectionPoint, Machine, Shop, Waiter,
+        message::{Principal, QueryType},
+        Announcer, Barista, BlockingAdapter, BlockingMachine, CollectionPoint, CommandMachine,
+        Machine, Shop, Tasks, Waiter,
     };
     pub use super::{CoffeeMachineError, CoffeeShopError, ErrorSchema, ValidationError};
 }
@@ -1,17 +1,141 @@
 //! Centralised logging for the coffee shop.
 //!
+//! Logs are written by [`env_logger`] if the `debug` feature is enabled, either as
+//! plain text or as [JSON lines](LogFormat::Json). The [`LogContext`] of the task
+//! emitting a log line, such as the ticket being processed, is attached to every
+//! JSON line.
 
 #[cfg(feature = "debug")]
 use std::sync::OnceLock;
 
-/// Lock for initialising the logger.
+/// The format of the log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human readable text, as formatted by [`env_logger`].
+    #[default]
+    Text,
+
+    /// One JSON object per line, with the `timestamp`, `level`, `target`, `message`
+    /// and `hostname`, as well as the `shop`, `barista` and `ticket` of the
+    /// [`LogContext`] if set.
+    Json,
+}
+
+/// The context of a task, attached to every log line it emits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogContext {
+    /// The name of the [`Shop`](crate::models::Shop).
+    pub shop: Option<String>,
+
+    /// The index of the [`Barista`](crate::models::Barista) in the shop.
+    pub barista: Option<usize>,
+
+    /// The ticket being processed.
+    pub ticket: Option<String>,
+}
+
+tokio::task_local! {
+    static LOG_CONTEXT: LogContext;
+}
+
+impl LogContext {
+    /// Get the context of the current task, or an empty one outside of any
+    /// [scope](LogContext::scope).
+    pub fn current() -> Self {
+        LOG_CONTEXT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// Builder pattern - set the name of the shop.
+    pub fn with_shop(mut self, shop: &str) -> Self {
+        self.shop = Some(shop.to_owned());
+        self
+    }
+
+    /// Builder pattern - set the index of the barista.
+    pub fn with_barista(mut self, barista: usize) -> Self {
+        self.barista = Some(barista);
+        self
+    }
+
+    /// Builder pattern - set the ticket being processed.
+    pub fn with_ticket(mut self, ticket: &str) -> Self {
+        self.ticket = Some(ticket.to_owned());
+        self
+    }
+
+    /// Run the future with this context; nested scopes replace the outer one, so
+    /// they should be built from [`LogContext::current`].
+    pub fn scope<F>(self, future: F) -> tokio::task::futures::TaskLocalFuture<Self, F>
+    where
+        F: std::future::Future,
+    {
+        LOG_CONTEXT.scope(self, future)
+    }
+
+    /// Format a log record as a JSON line with this context.
+    #[cfg(feature = "debug")]
+    fn to_json(&self, record: &log::Record, hostname: &str) -> serde_json::Value {
+        let mut line = serde_json::json!({
+            "timestamp": chrono::Utc::now(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+            "hostname": hostname,
+        });
+
+        if let Some(fields) = line.as_object_mut() {
+            if let Some(shop) = &self.shop {
+                fields.insert("shop".to_owned(), shop.as_str().into());
+            }
+            if let Some(barista) = self.barista {
+                fields.insert("barista".to_owned(), barista.into());
+            }
+            if let Some(ticket) = &self.ticket {
+                fields.insert("ticket".to_owned(), ticket.as_str().into());
+            }
+        }
+
+        line
+    }
+}
+
+/// The format the logger was initialised with.
 #[cfg(feature = "debug")]
-static INIT: OnceLock<()> = OnceLock::new();
+static INIT: OnceLock<LogFormat> = OnceLock::new();
 
-/// Initialises the logger.
+/// Initialises the logger, as plain text unless already initialised.
 pub fn init() {
+    init_with_format(LogFormat::default());
+}
+
+/// Initialises the logger with the given format.
+///
+/// The logger can only be initialised once, which happens on the first log line if
+/// not before; returns the format actually in use.
+pub fn init_with_format(format: LogFormat) -> LogFormat {
     #[cfg(feature = "debug")]
-    INIT.get_or_init(env_logger::init);
+    return *INIT.get_or_init(|| {
+        let mut builder = env_logger::Builder::from_default_env();
+
+        if format == LogFormat::Json {
+            let hostname = gethostname::gethostname()
+                .to_str()
+                .unwrap_or("(unknown host)")
+                .to_owned();
+
+            builder.format(move |buf, record| {
+                use std::io::Write;
+
+                writeln!(buf, "{}", LogContext::current().to_json(record, &hostname))
+            });
+        }
+
+        builder.init();
+        format
+    });
+
+    #[cfg(not(feature = "debug"))]
+    format
 }
 
 #[macro_export]
@@ -108,3 +232,50 @@ macro_rules! error {
         }
     )
 }
+
+#[cfg(all(test, feature = "debug"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn context_is_scoped() {
+        assert_eq!(LogContext::current(), LogContext::default());
+
+        let context = LogContext::default().with_shop("shop").with_barista(1);
+        let ticket = context
+            .clone()
+            .scope(async {
+                LogContext::current()
+                    .with_ticket("ticket")
+                    .scope(async { LogContext::current() })
+                    .await
+            })
+            .await;
+
+        assert_eq!(ticket, context.with_ticket("ticket"));
+        assert_eq!(LogContext::current(), LogContext::default());
+    }
+
+    #[test]
+    fn json_line() {
+        let context = LogContext::default()
+            .with_shop("shop")
+            .with_ticket("ticket");
+        let line = context.to_json(
+            &log::Record::builder()
+                .args(format_args!("Processing."))
+                .level(log::Level::Warn)
+                .target("coffeeshop::test")
+                .build(),
+            "host",
+        );
+
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["target"], "coffeeshop::test");
+        assert_eq!(line["message"], "Processing.");
+        assert_eq!(line["hostname"], "host");
+        assert_eq!(line["shop"], "shop");
+        assert_eq!(line["ticket"], "ticket");
+        assert!(line.get("barista").is_none());
+    }
+}
@@ -2,10 +2,19 @@ use super::{message, Machine, Shop};
 use prost::Message;
 use serde::{de::DeserializeOwned, Serialize};
 use socket2::SockAddr;
-use std::sync::{Arc, OnceLock, Weak};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, OnceLock, Weak,
+};
 use tokio::sync::Notify;
 
-use crate::{helpers::multicast, CoffeeShopError};
+use crate::{
+    helpers::{
+        metrics, multicast,
+        telemetry::{context_span, TraceContext},
+    },
+    CoffeeShopError,
+};
 
 /// The default buffer size for receiving multicast messages.
 const DEFAULT_BUFFER_SIZE: usize = 1024;
@@ -26,6 +35,10 @@ where
     shop: Weak<Shop<Q, I, O, F>>,
     sender: OnceLock<multicast::AsyncSocket>,
     receiver: OnceLock<multicast::AsyncSocket>,
+    listening: AtomicBool,
+    sent: AtomicUsize,
+    received: AtomicUsize,
+    failed: AtomicUsize,
 }
 
 impl<Q, I, O, F> std::fmt::Debug for Announcer<Q, I, O, F>
@@ -64,6 +77,32 @@ where
             shop,
             sender: OnceLock::new(),
             receiver: OnceLock::new(),
+            listening: AtomicBool::new(false),
+            sent: AtomicUsize::new(0),
+            received: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count a multicast message event, which is one of `sent`, `received` or `failed`.
+    fn record_message(&self, event: &str) {
+        let counter = match event {
+            "sent" => &self.sent,
+            "received" => &self.received,
+            _ => &self.failed,
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+        metrics::metrics().record_multicast(event);
+    }
+
+    /// Report the status of this announcer.
+    pub fn status(&self) -> message::AnnouncerStatus {
+        message::AnnouncerStatus {
+            listening: self.is_listening(),
+            sent: self.sent.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
         }
     }
 
@@ -196,7 +235,9 @@ where
 
         multicast::socket::send_multicast(self.sender(), &self.multicast_addr(), &encoded)
             .await
+            .inspect(|_| self.record_message("sent"))
             .inspect_err(|err| {
+                self.record_message("failed");
                 crate::error!(
                     target: LOG_TARGET,
                     "Failed to send multicast message: {err}",
@@ -229,7 +270,9 @@ where
         addr: SockAddr,
     ) -> Result<message::MulticastMessage, CoffeeShopError> {
         message::MulticastMessage::decode(&data[..])
+            .inspect(|_| self.record_message("received"))
             .inspect_err(|err| {
+                self.record_message("failed");
                 crate::error!(
                     target: LOG_TARGET,
                     "Failed to decode multicast message from {addr:?}: {err}",
@@ -279,17 +322,34 @@ where
                 let shop = self.shop();
 
                 if let Some(order) = shop.get_order(&message.ticket).await {
-                    order
-                        .value()
-                        .complete(status == message::MulticastMessageStatus::Success)
-                        .inspect_err(|err| {
-                            crate::error!(
-                                target: LOG_TARGET,
-                                "Failed to set order {ticket:?} to complete, ignoring: {err}",
-                                ticket = &message.ticket,
-                                err = err
-                            )
-                        })?;
+                    if let Some(delay) = message
+                        .timestamp
+                        .and_then(|timestamp| std::time::SystemTime::try_from(timestamp).ok())
+                        .and_then(|timestamp| timestamp.elapsed().ok())
+                    {
+                        metrics::metrics()
+                            .notification_delay
+                            .observe(delay.as_secs_f64());
+                    }
+
+                    // The order is collected as part of the trace of the barista.
+                    let context = TraceContext::child_of(message.trace_context().as_ref());
+
+                    context_span!("collect_order", context, ticket = %message.ticket).in_scope(
+                        || {
+                            order
+                                .value()
+                                .complete(status == message::MulticastMessageStatus::Success)
+                                .inspect_err(|err| {
+                                    crate::error!(
+                                        target: LOG_TARGET,
+                                        "Failed to set order {ticket:?} to complete, ignoring: {err}",
+                                        ticket = &message.ticket,
+                                        err = err
+                                    )
+                                })
+                        },
+                    )?;
                 } else {
                     crate::info!(
                         target: LOG_TARGET,
@@ -338,10 +398,28 @@ where
         Ok(())
     }
 
+    /// Check if the announcer is listening for announcements on its multicast socket.
+    pub fn is_listening(&self) -> bool {
+        self.receiver.get().is_some() && self.listening.load(Ordering::Relaxed)
+    }
+
     /// Listen for announcements from other [`Announcer`]s as well as itself.
     pub async fn listen_for_announcements(
         &self,
         shutdown_signal: Arc<Notify>,
+    ) -> Result<(), CoffeeShopError> {
+        self.listening.store(true, Ordering::Relaxed);
+        let result = self.listen_until_terminated(shutdown_signal).await;
+        self.listening.store(false, Ordering::Relaxed);
+
+        result
+    }
+
+    /// The body of [`Announcer::listen_for_announcements`], without tracking whether it
+    /// is listening.
+    async fn listen_until_terminated(
+        &self,
+        shutdown_signal: Arc<Notify>,
     ) -> Result<(), CoffeeShopError> {
         let mut message_count: u64 = 0;
 
@@ -352,6 +430,7 @@ where
                     multicast::socket::receive_multicast(self.receiver(), DEFAULT_BUFFER_SIZE)
                         .await
                         .inspect_err(|err| {
+                            self.record_message("failed");
                             crate::error!(
                                 target: LOG_TARGET,
                                 "Failed to receive multicast message, skipping: {err}",
@@ -1,17 +1,24 @@
+use futures::{FutureExt, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 use std::sync::{
     atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc, Weak,
 };
 use tokio::sync::Notify;
+use tracing::Instrument;
 
 use super::{
     message::{self, MulticastMessage, ProcessResult},
-    Machine, Shop,
+    Dependency, Machine, Shop,
 };
 
 use crate::{
-    helpers::{self, sqs::HasSQSConfiguration},
+    helpers::{
+        self,
+        sqs::HasSQSConfiguration,
+        telemetry::{context_span, TraceContext},
+    },
+    logger::LogContext,
     models::message::MulticastMessageStatus,
     CoffeeShopError,
 };
@@ -25,9 +32,70 @@ const LOG_TARGET: &str = "coffeeshop::models::barista";
 /// sent to the queue is not immediately visible to the barista.
 const BARISTA_REPORT_IDLE: tokio::time::Duration = tokio::time::Duration::from_secs(3);
 
+/// The interval at which the pool of baristas is resized.
+///
+/// Each resize looks up the approximate number of tickets in the SQS queue, so this
+/// should not be too short to keep the cost of polling down.
+const BARISTA_POOL_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(10);
+
+/// The number of consecutive empty polls after which an additional barista is retired.
+///
+/// With [`BARISTA_REPORT_IDLE`] this is about a minute of idling.
+const BARISTA_RETIRE_AFTER_IDLE_POLLS: usize = 20;
+
 #[cfg(doc)]
 use crate::models::Ticket;
 
+/// The queue that the [`Barista`]s receive their [`Ticket`]s from.
+///
+/// This is always the SQS queue of the [`Shop`] in normal use; see [`ShopQueue`].
+#[async_trait::async_trait]
+trait TicketQueue<Q, I>: Sync
+where
+    Q: message::QueryType + 'static,
+    I: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// The configuration that the received tickets are completed with.
+    type Config: HasSQSConfiguration;
+
+    /// Receive the next ticket, waiting up to [`BARISTA_REPORT_IDLE`] for one.
+    async fn receive(
+        &self,
+    ) -> Result<helpers::sqs::StagedReceipt<'_, Q, I, Self::Config>, CoffeeShopError>;
+
+    /// Get the approximate number of tickets waiting in the queue.
+    async fn depth(&self) -> Result<usize, CoffeeShopError>;
+}
+
+/// The SQS queue of a [`Shop`].
+struct ShopQueue<'s, Q, I, O, F>(&'s Shop<Q, I, O, F>)
+where
+    Q: message::QueryType,
+    I: Serialize + DeserializeOwned + Send + Sync,
+    O: Serialize + DeserializeOwned + Send + Sync,
+    F: Machine<Q, I, O>;
+
+#[async_trait::async_trait]
+impl<Q, I, O, F> TicketQueue<Q, I> for ShopQueue<'_, Q, I, O, F>
+where
+    Q: message::QueryType + 'static,
+    I: Serialize + DeserializeOwned + Send + Sync + 'static,
+    O: Serialize + DeserializeOwned + Send + Sync + 'static,
+    F: Machine<Q, I, O>,
+{
+    type Config = Shop<Q, I, O, F>;
+
+    async fn receive(
+        &self,
+    ) -> Result<helpers::sqs::StagedReceipt<'_, Q, I, Self::Config>, CoffeeShopError> {
+        helpers::sqs::retrieve_ticket(self.0, Some(BARISTA_REPORT_IDLE)).await
+    }
+
+    async fn depth(&self) -> Result<usize, CoffeeShopError> {
+        helpers::sqs::get_ticket_count(self.0).await
+    }
+}
+
 /// A [`Barista`] instance that acts as a worker for the shop.
 ///
 /// A shop can have any positive number of [`Barista`] instances; they are responsible
@@ -50,6 +118,29 @@ where
 
     /// The total amount of historical requests processed.
     pub process_count: AtomicUsize,
+
+    /// The number of tickets currently being processed.
+    pub in_flight: AtomicUsize,
+
+    /// The tickets currently being processed.
+    current_tickets: std::sync::Mutex<Vec<message::Ticket>>,
+
+    /// The number of consecutive polls that found the SQS queue empty.
+    pub idle_polls: AtomicUsize,
+
+    /// Whether this barista is currently serving.
+    pub serving: AtomicBool,
+
+    /// Whether this barista has been asked to stop fetching tickets, and terminate after
+    /// the tickets in flight.
+    pub retiring: AtomicBool,
+
+    /// Whether the drain deadline has passed, in which case the tickets in flight are
+    /// abandoned and returned to the queue.
+    pub abandoning: AtomicBool,
+
+    /// The signal to abandon the tickets in flight; see [`Barista::abandon`].
+    abandon_signal: Notify,
 }
 
 impl<Q, I, O, F> Barista<Q, I, O, F>
@@ -64,6 +155,13 @@ where
         Self {
             shop,
             process_count: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            current_tickets: std::sync::Mutex::new(vec![]),
+            idle_polls: AtomicUsize::new(0),
+            serving: AtomicBool::new(false),
+            retiring: AtomicBool::new(false),
+            abandoning: AtomicBool::new(false),
+            abandon_signal: Notify::new(),
         }
     }
 
@@ -78,110 +176,404 @@ where
             .load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Get the number of tickets currently being processed.
+    pub fn get_in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Get the tickets currently being processed.
+    pub fn get_current_tickets(&self) -> Vec<message::Ticket> {
+        self.current_tickets
+            .lock()
+            .expect("The current tickets lock is poisoned; this should not be possible.")
+            .clone()
+    }
+
+    /// Report the status of this barista.
+    pub fn status(&self) -> message::BaristaStatus {
+        message::BaristaStatus {
+            serving: self.is_serving(),
+            process_count: self.get_process_count(),
+            in_flight: self.get_in_flight(),
+            tickets: Some(self.get_current_tickets()),
+        }
+    }
+
+    /// Check if this barista is currently serving.
+    pub fn is_serving(&self) -> bool {
+        self.serving.load(Ordering::Relaxed)
+    }
+
+    /// Abandon the tickets in flight, returning them to the queue without waiting for
+    /// the machine to complete them.
+    ///
+    /// This is used once the [drain timeout](crate::cli::Config::drain_timeout) has
+    /// passed on shutdown; any ticket received afterwards is returned immediately.
+    pub fn abandon(&self) {
+        self.abandoning.store(true, Ordering::Relaxed);
+        self.abandon_signal.notify_waiters();
+    }
+
+    /// Wait until the tickets in flight are [abandoned](Barista::abandon).
+    async fn abandoned(&self) {
+        // Registering before checking the flag, so that a signal in between is not missed.
+        let notified = self.abandon_signal.notified();
+
+        if !self.abandoning.load(Ordering::Relaxed) {
+            notified.await;
+        }
+    }
+
     /// Ask the [`Barista`] to start serving.
     ///
-    /// This function never returns, and will loop indefinitely until the
-    /// program is terminated.
+    /// Up to [`Config::barista_concurrency`](crate::cli::Config::barista_concurrency)
+    /// tickets are processed at the same time; the next ticket is only fetched from the
+    /// queue while the barista is below that limit.
+    ///
+    /// This function loops indefinitely until a shutdown is requested or this barista
+    /// is [retired](Barista::retiring); the tickets in flight are always completed
+    /// before returning.
     pub async fn serve(&self, is_shutdown_requested: &AtomicBool) -> Result<(), CoffeeShopError> {
+        let shop = self.shop();
+
+        self.serve_from(&ShopQueue(&shop), is_shutdown_requested)
+            .await
+    }
+
+    /// [`Barista::serve`] the tickets from the given `queue`.
+    async fn serve_from(
+        &self,
+        queue: &impl TicketQueue<Q, I>,
+        is_shutdown_requested: &AtomicBool,
+    ) -> Result<(), CoffeeShopError> {
+        self.serving.store(true, Ordering::Relaxed);
+        let result = self
+            .serve_until_terminated(queue, is_shutdown_requested)
+            .await;
+        self.serving.store(false, Ordering::Relaxed);
+
+        result
+    }
+
+    /// The body of [`Barista::serve_from`], without tracking whether it is serving.
+    async fn serve_until_terminated(
+        &self,
+        queue: &impl TicketQueue<Q, I>,
+        is_shutdown_requested: &AtomicBool,
+    ) -> Result<(), CoffeeShopError> {
+        let shop = self.shop();
+        let concurrency = usize::from(shop.config.barista_concurrency).max(1);
+
+        // Receiving a ticket is not cancel safe, so the pending receive is kept across
+        // iterations instead of being dropped when a ticket in flight completes first.
+        let mut receiving = None;
+        let mut in_flight = futures::stream::FuturesUnordered::new();
+        let mut terminal_error = None;
+
         loop {
-            if is_shutdown_requested.load(Ordering::Relaxed) {
-                crate::warn!(
+            let is_terminating = terminal_error.is_some()
+                || is_shutdown_requested.load(Ordering::Relaxed)
+                || self.retiring.load(Ordering::Relaxed);
+
+            if receiving.is_none() && !is_terminating && in_flight.len() < concurrency {
+                crate::trace!(
                     target: LOG_TARGET,
-                    "Received shutdown signal, terminating barista."
+                    "A Barista is waiting for the next ticket...",
                 );
+                receiving = Some(queue.receive());
+            }
+
+            if receiving.is_none() && in_flight.is_empty() {
+                break match terminal_error {
+                    Some(err) => Err(err),
+                    None if is_shutdown_requested.load(Ordering::Relaxed) => {
+                        crate::warn!(
+                            target: LOG_TARGET,
+                            "Received shutdown signal, terminating barista."
+                        );
 
-                break Ok(());
+                        Ok(())
+                    }
+                    None => {
+                        crate::info!(target: LOG_TARGET, "Retiring idle barista.");
+
+                        Ok(())
+                    }
+                };
             }
 
-            crate::trace!(
-                target: LOG_TARGET,
-                "A Barista is waiting for the next ticket...",
-            );
-            let result = self.process_next_ticket(Some(BARISTA_REPORT_IDLE)).await;
+            let result = tokio::select! {
+                received = async { receiving.as_mut().expect("receiving is guarded").await }, if receiving.is_some() => {
+                    receiving = None;
+
+                    if matches!(received, Ok(_) | Err(CoffeeShopError::AWSSQSQueueEmpty(_))) {
+                        shop.health.record(Dependency::Sqs);
+                    }
+
+                    match received {
+                        Ok(receipt) => {
+                            self.idle_polls.store(0, Ordering::Relaxed);
+                            in_flight.push(self.process_receipt(receipt));
+                            continue;
+                        }
+                        Err(err) => {
+                            if matches!(err, CoffeeShopError::AWSSQSQueueEmpty(_)) {
+                                self.idle_polls.fetch_add(1, Ordering::Relaxed);
+                            }
+
+                            Err(err)
+                        }
+                    }
+                }
+                Some(result) = in_flight.next(), if !in_flight.is_empty() => result,
+            };
+
+            if let Err(err) = Self::inspect_error(result) {
+                if terminal_error.is_none() {
+                    terminal_error = Some(err);
+                }
+            }
+        }
+    }
 
-            // Inspect the result and decide what to do.
-            match &result {
-                Ok(_) => (),
-                // Expected errors.
-                Err(crate::CoffeeShopError::AWSSQSQueueEmpty(duration)) => crate::trace!(
+    /// Inspect the result of a ticket, and decide what to do.
+    ///
+    /// Returns the error back if it is irrecoverable, in which case the barista should
+    /// stop fetching tickets and terminate.
+    fn inspect_error(result: Result<(), CoffeeShopError>) -> Result<(), CoffeeShopError> {
+        match result {
+            Ok(_) => Ok(()),
+            // Expected errors.
+            Err(crate::CoffeeShopError::AWSSQSQueueEmpty(duration)) => {
+                crate::trace!(
                     target: LOG_TARGET,
                     "No tickets in the queue after {duration:?}; trying again.",
                     duration = duration,
-                ),
-                // Irrecoverable errors.
-                Err(crate::CoffeeShopError::AWSQueueDoesNotExist(queue_url)) => {
-                    crate::error!(
-                        target: LOG_TARGET,
-                        "The SQS queue {queue:?} does not exist; terminating barista.",
-                        queue = queue_url,
-                    );
-
-                    break result;
-                }
-                Err(crate::CoffeeShopError::AWSDynamoDBTableDoesNotExist(table_name)) => {
-                    crate::error!(
-                        target: LOG_TARGET,
-                        "The DynamoDB table {table:?} does not exist; terminating barista.",
-                        table = table_name,
-                    );
-
-                    break result;
-                }
-                Err(crate::CoffeeShopError::InvalidConfiguration { field, message }) => {
-                    crate::error!(
-                        target: LOG_TARGET,
-                        "Invalid configuration for the barista: {field}: {message}",
-                        field = field,
-                        message = message,
-                    );
-
-                    break result;
-                }
-                Err(crate::CoffeeShopError::AWSCredentialsError(err)) => {
-                    crate::error!(
-                        target: LOG_TARGET,
-                        "AWS credentials rejected: {error}",
-                        error = err,
-                    );
-
-                    break result;
-                }
-                // Catch all.
-                Err(err) => crate::error!(
+                );
+
+                Ok(())
+            }
+            // Irrecoverable errors.
+            Err(crate::CoffeeShopError::AWSQueueDoesNotExist(ref queue_url)) => {
+                crate::error!(
+                    target: LOG_TARGET,
+                    "The SQS queue {queue:?} does not exist; terminating barista.",
+                    queue = queue_url,
+                );
+
+                result
+            }
+            Err(crate::CoffeeShopError::AWSDynamoDBTableDoesNotExist(ref table_name)) => {
+                crate::error!(
+                    target: LOG_TARGET,
+                    "The DynamoDB table {table:?} does not exist; terminating barista.",
+                    table = table_name,
+                );
+
+                result
+            }
+            Err(crate::CoffeeShopError::InvalidConfiguration { field, ref message }) => {
+                crate::error!(
+                    target: LOG_TARGET,
+                    "Invalid configuration for the barista: {field}: {message}",
+                    field = field,
+                    message = message,
+                );
+
+                result
+            }
+            Err(crate::CoffeeShopError::AWSCredentialsError(ref err)) => {
+                crate::error!(
+                    target: LOG_TARGET,
+                    "AWS credentials rejected: {error}",
+                    error = err,
+                );
+
+                result
+            }
+            // Catch all.
+            Err(err) => {
+                crate::error!(
                     target: LOG_TARGET,
                     "Error processing ticket: {error}",
                     error = err,
-                ),
+                );
+
+                Ok(())
             }
         }
     }
 
     /// Serve all the baristas in the list.
+    ///
+    /// The first [`Config::baristas`](crate::cli::Config::baristas) are always serving.
+    /// The rest are put to work one at a time when the SQS queue has more tickets than
+    /// the serving baristas can take in flight, and retired again one at a time after
+    /// the SQS queue has been empty for a while.
+    ///
+    /// Once the `shutdown_signal` is triggered, the baristas stop fetching tickets and
+    /// complete the ones in flight; any tickets still in flight after the
+    /// [drain timeout](crate::cli::Config::drain_timeout) are
+    /// [abandoned](Barista::abandon) back to the queue.
     pub async fn serve_all(
         baristas: &[Self],
         shutdown_signal: Arc<Notify>,
     ) -> Result<(), CoffeeShopError> {
+        let Some(shop) = baristas.first().map(Self::shop) else {
+            return Ok(());
+        };
+
+        Self::serve_all_from(
+            baristas,
+            &ShopQueue(&shop),
+            BARISTA_POOL_INTERVAL,
+            shutdown_signal,
+        )
+        .await
+    }
+
+    /// [`Barista::serve_all`] the tickets from the given `queue`, resizing the pool of
+    /// baristas every `interval`.
+    async fn serve_all_from(
+        baristas: &[Self],
+        queue: &impl TicketQueue<Q, I>,
+        interval: tokio::time::Duration,
+        shutdown_signal: Arc<Notify>,
+    ) -> Result<(), CoffeeShopError> {
+        let Some(shop) = baristas.first().map(Self::shop) else {
+            return Ok(());
+        };
+
         let is_shutdown_requested = AtomicBool::new(false);
+        // Registered before anything is served, so that the shutdown is never missed.
+        let drain_deadline = shutdown_signal.notified();
+        let (permanent, additional) =
+            baristas.split_at(usize::from(shop.config.baristas).min(baristas.len()));
 
-        let shutdown_signal_task = async {
-            shutdown_signal.notified().await;
-            crate::warn!(
-                target: LOG_TARGET,
-                "Received shutdown signal, flagging all baristas to terminate after current workload."
-            );
-            is_shutdown_requested.store(true, Ordering::Relaxed);
+        let supervisor_task = async {
+            let mut shutdown = std::pin::pin!(shutdown_signal.notified());
+            let mut interval = tokio::time::interval(interval);
+            let mut serving = futures::stream::FuturesUnordered::new();
+            // The indices of the additional baristas serving, in the order they started.
+            let mut started: Vec<usize> = vec![];
 
-            Ok(())
+            loop {
+                let is_shutdown = is_shutdown_requested.load(Ordering::Relaxed);
+                if is_shutdown && serving.is_empty() {
+                    break Ok(());
+                }
+
+                tokio::select! {
+                    _ = &mut shutdown, if !is_shutdown => {
+                        crate::warn!(
+                            target: LOG_TARGET,
+                            "Received shutdown signal, flagging all baristas to terminate after current workload."
+                        );
+                        is_shutdown_requested.store(true, Ordering::Relaxed);
+                    }
+                    Some((index, result)) = serving.next(), if !serving.is_empty() => {
+                        let index: usize = index;
+                        started.retain(|started| *started != index);
+                        additional[index].retiring.store(false, Ordering::Relaxed);
+
+                        result?;
+                    }
+                    _ = interval.tick(), if !is_shutdown && !additional.is_empty() => {
+                        if let Some(&index) = started.last() {
+                            let barista = &additional[index];
+
+                            if !barista.retiring.load(Ordering::Relaxed)
+                                && barista.idle_polls.load(Ordering::Relaxed)
+                                    >= BARISTA_RETIRE_AFTER_IDLE_POLLS
+                            {
+                                crate::info!(
+                                    target: LOG_TARGET,
+                                    "The queue has been idle; retiring a barista.",
+                                );
+                                barista.retiring.store(true, Ordering::Relaxed);
+
+                                continue;
+                            }
+                        }
+
+                        // Baristas still draining after retiring are not started again.
+                        let Some(index) = (0..additional.len())
+                            .find(|index| !started.contains(index))
+                        else {
+                            continue;
+                        };
+
+                        let slots = (permanent.len() + started.len())
+                            * usize::from(shop.config.barista_concurrency);
+                        match queue.depth().await {
+                            Ok(count) if count > slots => {
+                                shop.health.record(Dependency::Sqs);
+
+                                crate::info!(
+                                    target: LOG_TARGET,
+                                    "{count} tickets in the queue exceed the {slots} slots of the serving baristas; starting another barista.",
+                                );
+
+                                let barista = &additional[index];
+                                barista.idle_polls.store(0, Ordering::Relaxed);
+                                started.push(index);
+                                serving.push(
+                                    LogContext::current()
+                                        .with_barista(permanent.len() + index)
+                                        .scope(barista.serve_from(queue, &is_shutdown_requested))
+                                        .map(move |result| (index, result)),
+                                );
+                            }
+                            Ok(_) => shop.health.record(Dependency::Sqs),
+                            Err(err) => crate::warn!(
+                                target: LOG_TARGET,
+                                "Failed to count the tickets in the queue, not resizing the baristas: {error}",
+                                error = err,
+                            ),
+                        }
+                    }
+                }
+            }
         };
 
-        let tasks = baristas
-            .iter()
-            .map(|barista| barista.serve(&is_shutdown_requested));
+        let tasks = permanent.iter().enumerate().map(|(index, barista)| {
+            LogContext::current()
+                .with_barista(index)
+                .scope(barista.serve_from(queue, &is_shutdown_requested))
+        });
+
+        let serving =
+            async { tokio::try_join!(supervisor_task, futures::future::try_join_all(tasks)) };
+        let mut serving = std::pin::pin!(serving);
 
-        tokio::try_join!(shutdown_signal_task, futures::future::try_join_all(tasks),).map(|_| ())
+        tokio::select! {
+            result = &mut serving => result,
+            _ = async {
+                drain_deadline.await;
+                tokio::time::sleep(shop.config.drain_timeout()).await;
+            } => {
+                crate::warn!(
+                    target: LOG_TARGET,
+                    "The baristas did not drain within {timeout:?}; returning the tickets in flight to the queue.",
+                    timeout = shop.config.drain_timeout(),
+                );
+                baristas.iter().for_each(Self::abandon);
+
+                serving.await
+            }
+        }
+        .map(|_| ())
     }
 
     /// Process a ticket from the SQS queue.
+    ///
+    /// If the [execution deadline](crate::cli::Config::execution_deadline) is exceeded,
+    /// the machine call is cancelled and a [`CoffeeShopError::ProcessingTimeout`] is
+    /// returned instead.
+    ///
+    /// If the machine panics, the panic is caught and returned as an
+    /// [`ErrorSchema::from_panic`](crate::errors::ErrorSchema::from_panic), so that the
+    /// barista can carry on serving.
     pub async fn process_ticket<C>(
         &self,
         receipt: &helpers::sqs::StagedReceipt<'_, Q, I, C>,
@@ -193,15 +585,51 @@ where
         self.process_count
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
-        self.shop()
-            .coffee_machine
-            .call(receipt.query(), receipt.input())
-            .await
-            .map_err(CoffeeShopError::ProcessingError)
+        let shop = self.shop();
+        let started = tokio::time::Instant::now();
+        // The machine is not used again by this call after a panic, and the receipt is
+        // always completed regardless, so it is safe to assume unwind safety here.
+        let call = std::panic::AssertUnwindSafe(shop.coffee_machine.call(
+            receipt.query(),
+            receipt.input(),
+            receipt.principal(),
+        ))
+        .catch_unwind()
+        .instrument(tracing::info_span!("machine_call"))
+        .map(|result| {
+            result.unwrap_or_else(|payload| {
+                let schema = crate::errors::ErrorSchema::from_panic(payload);
+
+                crate::error!(
+                    target: LOG_TARGET,
+                    "The machine panicked while processing ticket {ticket}: {details:?}",
+                    ticket = &receipt.ticket,
+                    details = schema.details,
+                );
+
+                Err(schema)
+            })
+        });
+
+        let deadline = shop
+            .config
+            .execution_deadline(receipt.query().get_timeout());
+        let result = call_with_deadline(call, deadline).await;
+
+        if let Err(CoffeeShopError::ProcessingTimeout(deadline)) = &result {
+            crate::error!(
+                target: LOG_TARGET,
+                "Ticket {ticket} exceeded the deadline of {deadline:?}; cancelling.",
+                ticket = &receipt.ticket,
+            );
+        }
+
+        helpers::metrics::metrics().record_machine_call(&result, started.elapsed());
+
+        result
     }
 
     /// Fetch the next ticket from the SQS queue, process it, and send the result to DynamoDB.
-    #[allow(unused_variables)]
     pub async fn process_next_ticket(
         &self,
         timeout: Option<tokio::time::Duration>,
@@ -212,20 +640,125 @@ where
         let receipt: helpers::sqs::StagedReceipt<'_, Q, I, _> =
             helpers::sqs::retrieve_ticket(&shop, timeout).await?;
 
+        self.process_receipt(receipt).await
+    }
+
+    /// Process a received ticket, send the result to DynamoDB, and complete the receipt.
+    pub async fn process_receipt<C>(
+        &self,
+        receipt: helpers::sqs::StagedReceipt<'_, Q, I, C>,
+    ) -> Result<(), crate::CoffeeShopError>
+    where
+        C: HasSQSConfiguration,
+    {
+        // The ticket is part of the trace of the order, or starts a new one.
+        let context = TraceContext::child_of(receipt.trace_context());
+        let span = context_span!(
+            "process_receipt",
+            context,
+            ticket = %receipt.ticket,
+            attempt = receipt.receive_count,
+        );
+
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let ticket = receipt.ticket.clone();
+        self.current_tickets
+            .lock()
+            .expect("The current tickets lock is poisoned; this should not be possible.")
+            .push(ticket.clone());
+
+        let log_context = LogContext::current().with_ticket(&ticket);
+        let result = log_context
+            .scope(self.complete_receipt(receipt, &context).instrument(span))
+            .await;
+
+        self.current_tickets
+            .lock()
+            .expect("The current tickets lock is poisoned; this should not be possible.")
+            .retain(|current| *current != ticket);
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        result
+    }
+
+    /// The body of [`Barista::process_receipt`], without the in-flight accounting.
+    async fn complete_receipt<C>(
+        &self,
+        receipt: helpers::sqs::StagedReceipt<'_, Q, I, C>,
+        context: &TraceContext,
+    ) -> Result<(), crate::CoffeeShopError>
+    where
+        C: HasSQSConfiguration,
+    {
+        let shop = self.shop();
+
+        // Process the ticket, unless it is abandoned during shutdown.
+        let process_result = tokio::select! {
+            result = self.process_ticket(&receipt) => result,
+            _ = self.abandoned() => {
+                crate::warn!(
+                    target: LOG_TARGET,
+                    "Abandoning ticket {ticket} as the shop is closing.",
+                    ticket = &receipt.ticket,
+                );
+                helpers::metrics::metrics().record_ticket("abandoned", None);
+
+                return receipt.abort().await;
+            }
+        };
+
+        // Transient failures are returned to the queue with exponential backoff, without
+        // reporting anything, until the attempts are exhausted.
+        if let Err(CoffeeShopError::ProcessingError(schema)) = &process_result {
+            let max_attempts = receipt
+                .query()
+                .get_max_attempts()
+                .unwrap_or(shop.config.max_attempts);
+
+            if schema.retryable && receipt.receive_count < max_attempts {
+                let delay = shop.config.retry_delay(receipt.receive_count);
+
+                crate::warn!(
+                    target: LOG_TARGET,
+                    "Ticket {ticket} failed with a retryable error on attempt {attempt}/{max_attempts}, retrying after {delay:?}: {schema}",
+                    ticket = &receipt.ticket,
+                    attempt = receipt.receive_count,
+                );
+
+                helpers::metrics::metrics().record_ticket("retry", Some(schema.status_code));
+
+                return receipt.retry_after(delay).await;
+            }
+        }
+
+        let process_status = match &process_result {
+            Ok(_) => axum::http::StatusCode::OK,
+            Err(err) => err.status_code(),
+        };
+
         let result = async {
-            // Process the ticket.
-            let process_result = self.process_ticket(&receipt).await;
             let status = if process_result.is_ok() {
                 // If the processing is successful, mark the ticket as complete.
                 MulticastMessageStatus::Success
             } else {
-                // If the machine failed to process it, there is not point retrying,
-                // so mark it as rejected; which is different from failure.
+                // If the machine failed to process it, and it is not retryable or had
+                // run out of attempts, mark it as rejected; which is different from failure.
                 MulticastMessageStatus::Aborted
             };
 
             // Send the result to DynamoDB.
-            helpers::dynamodb::put_process_result(&shop, &receipt.ticket, process_result).await?;
+            helpers::dynamodb::put_process_result(
+                &shop,
+                &receipt.ticket,
+                process_result,
+                receipt
+                    .principal()
+                    .map(message::Principal::owner)
+                    .as_deref(),
+            )
+            .instrument(tracing::info_span!("put_process_result"))
+            .await?;
+            shop.health.record(Dependency::DynamoDB);
 
             crate::info!(
                 target: LOG_TARGET,
@@ -244,6 +777,14 @@ where
             MulticastMessageStatus::Error
         };
 
+        match &result {
+            Ok(MulticastMessageStatus::Success) => {
+                helpers::metrics::metrics().record_ticket("success", Some(process_status))
+            }
+            Ok(_) => helpers::metrics::metrics().record_ticket("failure", Some(process_status)),
+            Err(err) => helpers::metrics::metrics().record_ticket("error", Some(err.status_code())),
+        }
+
         self.shop().announcer.send_message(
             MulticastMessage::new(
                 &self.shop().name,
@@ -251,7 +792,8 @@ where
                 message::MulticastMessageKind::Ticket,
                 status,
             )
-        ).await.unwrap_or_else(
+            .with_trace_context(context)
+        ).instrument(tracing::info_span!("announce")).await.unwrap_or_else(
             |err| {
                 crate::error!(
                     target: LOG_TARGET,
@@ -287,3 +829,366 @@ where
         result.map(|_| ())
     }
 }
+
+/// Await a machine `call`, cancelling it if it does not finish within the `deadline`.
+///
+/// The error of the machine is returned as a [`CoffeeShopError::ProcessingError`], and
+/// a cancelled call as a [`CoffeeShopError::ProcessingTimeout`].
+async fn call_with_deadline<T>(
+    call: impl std::future::Future<Output = Result<T, crate::errors::ErrorSchema>>,
+    deadline: Option<tokio::time::Duration>,
+) -> Result<T, CoffeeShopError> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout(deadline, call)
+            .await
+            .map_err(|_| CoffeeShopError::ProcessingTimeout(deadline))
+            .and_then(|result| result.map_err(CoffeeShopError::ProcessingError)),
+        None => call.await.map_err(CoffeeShopError::ProcessingError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Config,
+        models::test::{get_random_ticket, TestPayload, TestQuery, TestResult, TestStatus},
+        ValidationError,
+    };
+
+    type SlowShop = Shop<TestQuery, TestPayload, TestResult, SlowMachine>;
+
+    /// The time an empty [`TestQueue`] waits before reporting that it is empty.
+    const IDLE_POLL: tokio::time::Duration = tokio::time::Duration::from_millis(5);
+
+    /// The longest a test waits for the baristas to reach an expected state.
+    const TEST_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(10);
+
+    /// A machine that works for the duration of the payload, and records the most calls
+    /// running at once.
+    #[derive(Default)]
+    struct SlowMachine {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Machine<TestQuery, TestPayload, TestResult> for SlowMachine {
+        async fn call(
+            &self,
+            query: &TestQuery,
+            input: Option<&TestPayload>,
+            _principal: Option<&message::Principal>,
+        ) -> message::MachineResult<TestResult> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+
+            let payload = input.expect("The test always has a payload.");
+            tokio::time::sleep(tokio::time::Duration::from_secs_f64(payload.duration)).await;
+
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(TestResult {
+                greetings: format!("Hello, {name}!", name = query.name),
+                narration: format!("You worked for {:?} seconds.", payload.duration),
+            })
+        }
+
+        async fn validator(
+            &self,
+            _query: &TestQuery,
+            _input: Option<&TestPayload>,
+        ) -> Result<(), ValidationError> {
+            Ok(())
+        }
+    }
+
+    /// An in-memory [`TicketQueue`], which tickets are never returned to.
+    struct TestQueue<'s> {
+        shop: &'s SlowShop,
+        tickets: std::sync::Mutex<std::collections::VecDeque<message::Ticket>>,
+        duration: f64,
+    }
+
+    impl<'s> TestQueue<'s> {
+        /// Queue `count` tickets, each working for `duration` seconds.
+        fn new(shop: &'s SlowShop, count: usize, duration: f64) -> Self {
+            Self {
+                shop,
+                tickets: std::sync::Mutex::new((0..count).map(|_| get_random_ticket()).collect()),
+                duration,
+            }
+        }
+
+        fn len(&self) -> usize {
+            self.tickets.lock().unwrap().len()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TicketQueue<TestQuery, TestPayload> for TestQueue<'_> {
+        type Config = SlowShop;
+
+        async fn receive(
+            &self,
+        ) -> Result<
+            helpers::sqs::StagedReceipt<'_, TestQuery, TestPayload, SlowShop>,
+            CoffeeShopError,
+        > {
+            let ticket = self.tickets.lock().unwrap().pop_front();
+
+            if let Some(ticket) = ticket {
+                Ok(helpers::sqs::StagedReceipt::without_queue(
+                    self.shop,
+                    ticket,
+                    work(self.duration),
+                ))
+            } else {
+                tokio::time::sleep(IDLE_POLL).await;
+                Err(CoffeeShopError::AWSSQSQueueEmpty(IDLE_POLL))
+            }
+        }
+
+        async fn depth(&self) -> Result<usize, CoffeeShopError> {
+            Ok(self.len())
+        }
+    }
+
+    /// Create a shop that is not connected to any AWS resources.
+    async fn new_slow_shop(config: Config) -> Arc<SlowShop> {
+        Shop::new(
+            "slow_shop".to_owned(),
+            SlowMachine::default(),
+            config,
+            Some(
+                helpers::aws::SdkConfig::builder()
+                    .behavior_version(aws_config::BehaviorVersion::latest())
+                    .build(),
+            ),
+        )
+        .await
+        .expect("Failed to create the shop.")
+    }
+
+    /// The input of a ticket that works for `duration` seconds.
+    fn work(duration: f64) -> message::CombinedInput<TestQuery, TestPayload> {
+        message::CombinedInput::new(
+            TestQuery {
+                name: "Jane".to_owned(),
+                timeout: None,
+                is_async: false,
+            },
+            Some(TestPayload {
+                action: TestStatus::Work,
+                duration,
+            }),
+        )
+    }
+
+    /// Wait until the `condition` holds, checking it every millisecond.
+    async fn wait_until(condition: impl Fn() -> bool) {
+        tokio::time::timeout(TEST_TIMEOUT, async {
+            while !condition() {
+                tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("The baristas did not reach the expected state in time.");
+    }
+
+    /// Count the machine calls that exceeded their deadline so far.
+    fn timeouts() -> u64 {
+        helpers::metrics::metrics()
+            .machine_duration
+            .with_label_values(&["timeout"])
+            .get_sample_count()
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(machine_metrics)]
+    async fn call_exceeding_deadline() {
+        let before = timeouts();
+
+        let deadline = tokio::time::Duration::from_millis(10);
+        let result = call_with_deadline(
+            futures::future::pending::<Result<(), crate::errors::ErrorSchema>>(),
+            Some(deadline),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(CoffeeShopError::ProcessingTimeout(found)) if found == deadline
+        ));
+
+        helpers::metrics::metrics().record_machine_call(&result, deadline);
+        assert_eq!(timeouts(), before + 1);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(machine_metrics)]
+    async fn process_ticket_exceeding_deadline() {
+        let shop = new_slow_shop(Config::default()).await;
+        let barista = shop.baristas.first().expect("No baristas available.");
+
+        let deadline = tokio::time::Duration::from_millis(10);
+        let mut input = work(60.);
+        input.query.timeout = Some(deadline);
+        let receipt =
+            helpers::sqs::StagedReceipt::without_queue(&*shop, get_random_ticket(), input);
+
+        let before = timeouts();
+        let result = barista.process_ticket(&receipt).await;
+
+        assert!(matches!(
+            result,
+            Err(CoffeeShopError::ProcessingTimeout(found)) if found == deadline
+        ));
+        assert_eq!(timeouts(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn serve_tickets_concurrently() {
+        const TICKETS: usize = 4;
+
+        let shop = new_slow_shop(
+            Config::default()
+                .with_barista_concurrency(TICKETS as u16)
+                .unwrap(),
+        )
+        .await;
+        let barista = shop.baristas.first().expect("No baristas available.");
+        let queue = TestQueue::new(&shop, TICKETS, 0.2);
+        let is_shutdown_requested = AtomicBool::new(false);
+
+        let (result, _) = tokio::join!(barista.serve_from(&queue, &is_shutdown_requested), async {
+            // All the tickets are in flight at once, before any of them completes.
+            wait_until(|| barista.get_in_flight() == TICKETS).await;
+            assert_eq!(barista.get_current_tickets().len(), TICKETS);
+
+            wait_until(|| barista.get_process_count() == TICKETS && barista.get_in_flight() == 0)
+                .await;
+            is_shutdown_requested.store(true, Ordering::Relaxed);
+        });
+
+        result.expect("The barista should have stopped gracefully.");
+        assert_eq!(
+            shop.coffee_machine.max_running.load(Ordering::SeqCst),
+            TICKETS
+        );
+        assert!(!barista.is_serving());
+    }
+
+    #[tokio::test]
+    async fn resize_pool_with_queue_depth() {
+        const TICKETS: usize = 6;
+
+        let shop = new_slow_shop(
+            Config::default()
+                .with_baristas(1)
+                .and_then(|config| config.with_max_baristas(3))
+                .unwrap(),
+        )
+        .await;
+        let queue = TestQueue::new(&shop, TICKETS, 0.2);
+        let shutdown_signal = Arc::new(Notify::new());
+        let serving = || {
+            shop.baristas
+                .iter()
+                .filter(|barista| barista.is_serving())
+                .count()
+        };
+
+        let (result, _) = tokio::join!(
+            Barista::serve_all_from(
+                &shop.baristas,
+                &queue,
+                tokio::time::Duration::from_millis(20),
+                shutdown_signal.clone(),
+            ),
+            async {
+                // The pool grows while the queue is deeper than the baristas can take...
+                wait_until(|| serving() == 3).await;
+                assert!(
+                    queue.len() > 0,
+                    "The pool should grow before the queue is drained."
+                );
+
+                // ...and shrinks back to the permanent barista once it has been idle.
+                wait_until(|| queue.len() == 0 && serving() == 1).await;
+                assert!(shop.baristas[0].is_serving());
+                assert_eq!(
+                    shop.baristas
+                        .iter()
+                        .map(Barista::get_process_count)
+                        .sum::<usize>(),
+                    TICKETS
+                );
+
+                shutdown_signal.notify_waiters();
+            }
+        );
+
+        result.expect("The baristas should have stopped gracefully.");
+        assert_eq!(serving(), 0);
+    }
+
+    #[tokio::test]
+    async fn abandon_after_drain_timeout() {
+        let abandoned = || {
+            helpers::metrics::metrics()
+                .tickets
+                .with_label_values(&["abandoned", ""])
+                .get()
+        };
+        let drain_timeout = tokio::time::Duration::from_millis(100);
+
+        let shop = new_slow_shop(
+            Config::default()
+                .with_drain_timeout(drain_timeout.as_secs_f32())
+                .unwrap(),
+        )
+        .await;
+        let barista = shop.baristas.first().expect("No baristas available.");
+        let queue = TestQueue::new(&shop, 2, 60.);
+        let shutdown_signal = Arc::new(Notify::new());
+        let before = abandoned();
+
+        let (result, elapsed) = tokio::join!(
+            Barista::serve_all_from(
+                &shop.baristas,
+                &queue,
+                BARISTA_POOL_INTERVAL,
+                shutdown_signal.clone(),
+            ),
+            async {
+                wait_until(|| barista.get_in_flight() == 1).await;
+                shutdown_signal.notify_waiters();
+                let signalled_at = tokio::time::Instant::now();
+
+                // The ticket in flight is given until the drain timeout to complete.
+                tokio::time::sleep(drain_timeout / 2).await;
+                assert_eq!(barista.get_in_flight(), 1);
+                assert_eq!(abandoned(), before);
+
+                wait_until(|| !barista.is_serving()).await;
+                signalled_at.elapsed()
+            }
+        );
+
+        result.expect("The baristas should have stopped gracefully.");
+        assert!(
+            elapsed >= drain_timeout
+                && elapsed < drain_timeout + tokio::time::Duration::from_secs(1),
+            "The baristas stopped {elapsed:?} after the termination signal."
+        );
+
+        // The slow ticket was abandoned rather than completed, and no further ticket was
+        // fetched after the termination signal.
+        assert_eq!(abandoned(), before + 1);
+        assert_eq!(barista.get_in_flight(), 0);
+        assert!(barista.get_current_tickets().is_empty());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(shop.coffee_machine.running.load(Ordering::SeqCst), 1);
+    }
+}
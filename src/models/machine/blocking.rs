@@ -0,0 +1,270 @@
+use std::sync::{mpsc, Arc, Mutex};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::Machine;
+use crate::{cli::Config, models::message, ValidationError};
+
+#[cfg(doc)]
+use crate::models::{Barista, Shop};
+
+/// The name prefix of the threads that [`BlockingMachine`]s run on.
+const THREAD_NAME: &str = "coffeeshop-blocking";
+
+/// A call to run on one of the threads of a [`BlockingAdapter`].
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A synchronous counterpart of [`Machine`], for CPU-bound or otherwise blocking work.
+///
+/// Running blocking code inside an async [`Machine::call`] starves the [`tokio`] runtime
+/// that also serves the [`Waiter`](crate::models::Waiter) and the [`Barista`]s. Wrap a
+/// [`BlockingMachine`] in a [`BlockingAdapter`] instead, which runs each call on a
+/// dedicated, fixed-size pool of threads.
+pub trait BlockingMachine<Q, I, O>: Send + Sync + 'static
+where
+    Q: message::QueryType,
+    I: DeserializeOwned + Serialize + Send + Sync,
+    O: DeserializeOwned + Serialize + Send + Sync,
+{
+    /// Required method for the [`BlockingMachine`] trait; see [`Machine::call`].
+    fn call(
+        &self,
+        query: &Q,
+        input: Option<&I>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<O>;
+
+    /// Validate the input before processing; see [`Machine::validator`].
+    ///
+    /// This is run on the async runtime directly, so it is expected to be cheap.
+    fn validator(&self, query: &Q, input: Option<&I>) -> Result<(), ValidationError>;
+}
+
+/// A [`Machine`] that runs a [`BlockingMachine`] on its own fixed-size pool of threads,
+/// fed by a channel.
+///
+/// The threads are separate from those of [`tokio`], so blocking calls never compete
+/// with the blocking work of the runtime itself, such as file I/O. Calls beyond the
+/// number of threads wait in the channel in order. The threads exit once the adapter is
+/// dropped and the calls already queued have returned.
+///
+/// As the threads cannot borrow from the [`Shop`], the query, input and principal are
+/// cloned for each call.
+///
+/// # Note
+///
+/// A blocking call cannot be cancelled; if the
+/// [execution deadline](Config::execution_deadline) is exceeded, the ticket is failed but
+/// the call keeps its thread until it returns.
+#[derive(Debug)]
+pub struct BlockingAdapter<M> {
+    /// The blocking machine to run.
+    pub machine: Arc<M>,
+
+    /// The channel feeding the calls to the threads.
+    jobs: mpsc::Sender<Job>,
+}
+
+impl<M> BlockingAdapter<M> {
+    /// Wrap a blocking machine to run on a pool of `threads` threads.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system fails to spawn a thread.
+    pub fn new(machine: M, threads: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for id in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+
+            std::thread::Builder::new()
+                .name(format!("{THREAD_NAME}-{id}"))
+                .spawn(move || loop {
+                    // The lock is released as soon as a call is received, so that the
+                    // other threads can receive the next one while this one runs.
+                    let job = receiver
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .recv();
+
+                    match job {
+                        Ok(job) => job(),
+                        // The adapter had been dropped.
+                        Err(mpsc::RecvError) => break,
+                    }
+                })
+                .expect("Failed to spawn a thread for the blocking machine.");
+        }
+
+        Self {
+            machine: Arc::new(machine),
+            jobs,
+        }
+    }
+
+    /// Wrap a blocking machine to run on [`Config::blocking_threads`] threads.
+    pub fn from_config(machine: M, config: &Config) -> Self {
+        Self::new(machine, config.blocking_threads())
+    }
+}
+
+#[async_trait::async_trait]
+impl<Q, I, O, M> Machine<Q, I, O> for BlockingAdapter<M>
+where
+    Q: message::QueryType + Clone + 'static,
+    I: DeserializeOwned + Serialize + Send + Sync + Clone + 'static,
+    O: DeserializeOwned + Serialize + Send + Sync + 'static,
+    M: BlockingMachine<Q, I, O>,
+{
+    async fn call(
+        &self,
+        query: &Q,
+        input: Option<&I>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<O> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        let machine = Arc::clone(&self.machine);
+        let query = query.clone();
+        let input = input.cloned();
+        let principal = principal.cloned();
+
+        self.jobs
+            .send(Box::new(move || {
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    machine.call(&query, input.as_ref(), principal.as_ref())
+                }));
+
+                // The receiver is gone if the call had been abandoned.
+                let _ = sender.send(result);
+            }))
+            .expect("The threads of the blocking machine outlive the adapter.");
+
+        receiver
+            .await
+            .expect("The threads of the blocking machine always reply to a call.")
+            // Resume the panic on this task, so that the barista reports it like any
+            // other machine panic.
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+
+    async fn validator(&self, query: &Q, input: Option<&I>) -> Result<(), ValidationError> {
+        self.machine.validator(query, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test::{TestPayload, TestQuery, TestResult, TestStatus};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A blocking machine that sleeps, and records the most calls running at once and
+    /// the threads they ran on.
+    #[derive(Default)]
+    struct SleepingMachine {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        threads: Mutex<hashbrown::HashSet<String>>,
+    }
+
+    impl BlockingMachine<TestQuery, TestPayload, TestResult> for SleepingMachine {
+        fn call(
+            &self,
+            query: &TestQuery,
+            input: Option<&TestPayload>,
+            _principal: Option<&message::Principal>,
+        ) -> message::MachineResult<TestResult> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            self.threads
+                .lock()
+                .unwrap()
+                .insert(std::thread::current().name().unwrap_or_default().to_owned());
+
+            let payload = input.expect("The test always has a payload.");
+            if payload.action == TestStatus::Sleep {
+                std::thread::sleep(std::time::Duration::from_secs_f64(payload.duration));
+            } else {
+                panic!(
+                    "{name} refuses to {action:?}.",
+                    name = query.name,
+                    action = payload.action
+                );
+            }
+
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(TestResult {
+                greetings: format!("Hello, {name}!", name = query.name),
+                narration: "You slept.".to_owned(),
+            })
+        }
+
+        fn validator(
+            &self,
+            _query: &TestQuery,
+            _input: Option<&TestPayload>,
+        ) -> Result<(), ValidationError> {
+            Ok(())
+        }
+    }
+
+    fn query() -> TestQuery {
+        TestQuery {
+            name: "Jane".to_owned(),
+            timeout: None,
+            is_async: false,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn limits_threads() {
+        let adapter = BlockingAdapter::new(SleepingMachine::default(), 2);
+        let payload = TestPayload {
+            action: TestStatus::Sleep,
+            duration: 0.1,
+        };
+
+        let query = query();
+
+        let results =
+            futures::future::join_all((0..6).map(|_| adapter.call(&query, Some(&payload), None)))
+                .await;
+
+        assert!(
+            results.iter().all(Result::is_ok),
+            "Unexpected results: {results:?}"
+        );
+        assert_eq!(adapter.machine.max_running.load(Ordering::SeqCst), 2);
+
+        let threads = adapter.machine.threads.lock().unwrap();
+        assert!(
+            threads.len() <= 2 && threads.iter().all(|name| name.starts_with(THREAD_NAME)),
+            "Unexpected threads: {threads:?}"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resumes_panic() {
+        let adapter = BlockingAdapter::new(SleepingMachine::default(), 1);
+        let payload = TestPayload {
+            action: TestStatus::Work,
+            duration: 0.0,
+        };
+
+        let panic = futures::FutureExt::catch_unwind(std::panic::AssertUnwindSafe(adapter.call(
+            &query(),
+            Some(&payload),
+            None,
+        )))
+        .await
+        .expect_err("The machine should have panicked.");
+
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("Jane refuses to Work.")
+        );
+    }
+}
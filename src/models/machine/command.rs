@@ -0,0 +1,466 @@
+use std::{ffi::OsString, path::PathBuf, process::Stdio};
+
+use axum::http;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::{Mutex, Semaphore},
+};
+
+use super::{Machine, MachineInput};
+use crate::{models::message, CoffeeMachineError, ValidationError};
+
+#[cfg(doc)]
+use crate::models::message::CombinedInput;
+
+const LOG_TARGET: &str = "coffeeshop::models::machine::command";
+
+/// A [`Machine`] that processes each ticket by running an external program.
+///
+/// By default, the command is spawned once per ticket:
+///
+/// - the JSON serialized [`CombinedInput`] is written to its `stdin`, which is then
+///   closed;
+/// - if it exits successfully, its `stdout` is parsed as the JSON output `O`;
+/// - otherwise, its `stderr` is parsed as a JSON [`CoffeeMachineError`] to report,
+///   falling back to a `500 Internal Server Error` with `stderr` as the message.
+///
+/// With [`CommandMachine::with_workers`], a pool of long-lived worker processes is
+/// kept instead, speaking newline-delimited JSON: each ticket is written as a single
+/// line of [`CombinedInput`] to `stdin` of an idle worker, which must reply with a single
+/// line on `stdout` of either `{"Ok": <output>}` or `{"Err": <CoffeeMachineError>}`.
+/// The `stderr` of the workers is inherited from the shop.
+///
+/// The command does not validate the input; the [`Machine::validator`] always passes.
+#[derive(Debug)]
+pub struct CommandMachine {
+    /// The program to run.
+    pub program: OsString,
+
+    /// The arguments to pass to the program.
+    pub args: Vec<OsString>,
+
+    /// The environment variables to set for the program, in addition to those inherited.
+    pub envs: Vec<(OsString, OsString)>,
+
+    /// The working directory of the program; inherited if [`None`].
+    pub current_dir: Option<PathBuf>,
+
+    /// The time a ticket may take before the command is killed.
+    pub timeout: Option<tokio::time::Duration>,
+
+    /// The pool of long-lived worker processes, if enabled.
+    workers: Option<WorkerPool>,
+}
+
+/// A pool of long-lived worker processes.
+#[derive(Debug)]
+struct WorkerPool {
+    /// The permits for the workers, limiting the pool to its size.
+    permits: Semaphore,
+
+    /// The idle workers; workers are spawned on demand up to the size of the pool.
+    idle: Mutex<Vec<Worker>>,
+}
+
+/// A long-lived worker process.
+#[derive(Debug)]
+struct Worker {
+    /// The child process; killed when the worker is dropped.
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl CommandMachine {
+    /// Create a new [`CommandMachine`] that runs the given program for each ticket.
+    pub fn new(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            envs: vec![],
+            current_dir: None,
+            timeout: None,
+            workers: None,
+        }
+    }
+
+    /// Builder pattern - add arguments to pass to the program.
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Builder pattern - set an environment variable for the program.
+    pub fn with_env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Builder pattern - set the working directory of the program.
+    pub fn with_current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Builder pattern - set the time a ticket may take before the command is killed.
+    pub fn with_timeout(mut self, timeout: tokio::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Builder pattern - keep a pool of up to `count` long-lived worker processes
+    /// instead of spawning the command for each ticket.
+    pub fn with_workers(mut self, count: usize) -> Self {
+        self.workers = Some(WorkerPool {
+            permits: Semaphore::new(count.max(1)),
+            idle: Mutex::new(vec![]),
+        });
+        self
+    }
+
+    /// Build the [`Command`] to spawn, with all of its pipes set up.
+    fn command(&self, stderr: Stdio) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .kill_on_drop(true);
+
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+
+        command
+    }
+
+    /// Run the future within the [`CommandMachine::timeout`], if any.
+    async fn within_timeout<T>(
+        &self,
+        future: impl std::future::Future<Output = Result<T, CoffeeMachineError>>,
+    ) -> Result<T, CoffeeMachineError> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future).await.map_err(|_| {
+                CoffeeMachineError::new(
+                    http::StatusCode::GATEWAY_TIMEOUT,
+                    "CommandTimeout".to_owned(),
+                    Some(serde_json::json!({
+                        "message": format!(
+                            "The command did not complete within {timeout:?}.",
+                        ),
+                    })),
+                )
+            })?,
+            None => future.await,
+        }
+    }
+
+    /// Spawn the command for a single ticket, and wait for its output.
+    async fn call_once<O: DeserializeOwned>(&self, payload: Vec<u8>) -> message::MachineResult<O> {
+        let mut child = self.command(Stdio::piped()).spawn().map_err(spawn_error)?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("The stdin of the command is always piped.");
+
+        // Write concurrently with reading the output; a command may start writing
+        // before it has read all of its input.
+        let write = async move {
+            // A command may exit without reading its input; this is judged by its exit
+            // status instead.
+            let _ = stdin.write_all(&payload).await;
+        };
+        let (_, output) = tokio::join!(write, child.wait_with_output());
+        let output = output.map_err(io_error)?;
+
+        if output.status.success() {
+            serde_json::from_slice(&output.stdout).map_err(output_error)
+        } else {
+            Err(
+                serde_json::from_slice::<CoffeeMachineError>(&output.stderr).unwrap_or_else(|_| {
+                    CoffeeMachineError::new(
+                        http::StatusCode::INTERNAL_SERVER_ERROR,
+                        "CommandFailed".to_owned(),
+                        Some(serde_json::json!({
+                            "message": String::from_utf8_lossy(&output.stderr).trim(),
+                            "status": output.status.code(),
+                        })),
+                    )
+                }),
+            )
+        }
+    }
+
+    /// Send a single ticket to an idle worker from the pool, and wait for its reply.
+    async fn call_worker<O: DeserializeOwned>(
+        &self,
+        pool: &WorkerPool,
+        mut payload: Vec<u8>,
+    ) -> message::MachineResult<O> {
+        let _permit = pool
+            .permits
+            .acquire()
+            .await
+            .expect("The semaphore of the workers is never closed.");
+
+        let idle = pool.idle.lock().await.pop();
+        let mut worker = match idle {
+            Some(worker) => worker,
+            None => {
+                crate::info!(target: LOG_TARGET, "Spawning a new command worker.");
+                Worker::spawn(self.command(Stdio::inherit()))?
+            }
+        };
+
+        payload.push(b'\n');
+        let line = worker.request(&payload).await?;
+
+        // Only return the worker to the pool once it has replied; a worker that failed
+        // or timed out is dropped and killed, to be replaced by a fresh one.
+        pool.idle.lock().await.push(worker);
+
+        serde_json::from_str::<Result<O, CoffeeMachineError>>(&line).map_err(output_error)?
+    }
+}
+
+impl Worker {
+    /// Spawn a new worker process from the command.
+    fn spawn(mut command: Command) -> Result<Self, CoffeeMachineError> {
+        let mut child = command.spawn().map_err(spawn_error)?;
+
+        Ok(Self {
+            stdin: child
+                .stdin
+                .take()
+                .expect("The stdin of the command is always piped."),
+            stdout: BufReader::new(
+                child
+                    .stdout
+                    .take()
+                    .expect("The stdout of the command is always piped."),
+            ),
+            _child: child,
+        })
+    }
+
+    /// Write a line to the worker, and read its reply.
+    async fn request(&mut self, line: &[u8]) -> Result<String, CoffeeMachineError> {
+        self.stdin.write_all(line).await.map_err(worker_error)?;
+        self.stdin.flush().await.map_err(worker_error)?;
+
+        let mut reply = String::new();
+        match self.stdout.read_line(&mut reply).await {
+            Ok(0) => Err(worker_error(std::io::ErrorKind::UnexpectedEof.into())),
+            Ok(_) => Ok(reply),
+            Err(err) => Err(worker_error(err)),
+        }
+    }
+}
+
+/// The error for a command that could not be spawned.
+fn spawn_error(err: std::io::Error) -> CoffeeMachineError {
+    CoffeeMachineError::new(
+        http::StatusCode::INTERNAL_SERVER_ERROR,
+        "CommandNotSpawned".to_owned(),
+        Some(serde_json::json!({
+            "message": format!("The command could not be spawned: {err}"),
+        })),
+    )
+}
+
+/// The error for a command that could not be communicated with.
+fn io_error(err: std::io::Error) -> CoffeeMachineError {
+    CoffeeMachineError::new(
+        http::StatusCode::INTERNAL_SERVER_ERROR,
+        "CommandFailed".to_owned(),
+        Some(serde_json::json!({
+            "message": format!("The command could not be communicated with: {err}"),
+        })),
+    )
+}
+
+/// The error for a worker that exited or stopped responding; the ticket may succeed
+/// on a fresh worker.
+fn worker_error(err: std::io::Error) -> CoffeeMachineError {
+    CoffeeMachineError::new_retryable(
+        http::StatusCode::BAD_GATEWAY,
+        "CommandWorkerFailed".to_owned(),
+        Some(serde_json::json!({
+            "message": format!("The command worker did not reply: {err}"),
+        })),
+    )
+}
+
+/// The error for a command that replied with output that could not be parsed.
+fn output_error(err: serde_json::Error) -> CoffeeMachineError {
+    CoffeeMachineError::new(
+        http::StatusCode::BAD_GATEWAY,
+        "CommandOutputInvalid".to_owned(),
+        Some(serde_json::json!({
+            "message": format!("The output of the command is not valid: {err}"),
+        })),
+    )
+}
+
+#[async_trait::async_trait]
+impl<Q, I, O> Machine<Q, I, O> for CommandMachine
+where
+    Q: message::QueryType,
+    I: DeserializeOwned + Serialize + Send + Sync,
+    O: DeserializeOwned + Serialize + Send + Sync,
+{
+    async fn call(
+        &self,
+        query: &Q,
+        input: Option<&I>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<O> {
+        let payload = serde_json::to_vec(&MachineInput {
+            query,
+            input,
+            principal,
+        })
+        .map_err(|err| {
+            CoffeeMachineError::new(
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                "CommandInputInvalid".to_owned(),
+                Some(serde_json::json!({
+                    "message": format!("The input could not be serialized: {err}"),
+                })),
+            )
+        })?;
+
+        match &self.workers {
+            Some(pool) => self.within_timeout(self.call_worker(pool, payload)).await,
+            None => self.within_timeout(self.call_once(payload)).await,
+        }
+    }
+
+    async fn validator(&self, _query: &Q, _input: Option<&I>) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test::{TestPayload, TestQuery, TestResult, TestStatus};
+
+    const RESULT: &str = r#"{"greetings":"Hello, Jane!","narration":"You ran a command."}"#;
+
+    fn shell(script: &str) -> CommandMachine {
+        CommandMachine::new("sh").with_args(["-c", script])
+    }
+
+    async fn call(machine: &CommandMachine) -> message::MachineResult<TestResult> {
+        Machine::<TestQuery, TestPayload, TestResult>::call(
+            machine,
+            &TestQuery {
+                name: "Jane".to_owned(),
+                timeout: None,
+                is_async: false,
+            },
+            Some(&TestPayload {
+                action: TestStatus::Work,
+                duration: 1.0,
+            }),
+            None,
+        )
+        .await
+    }
+
+    macro_rules! create_test {
+        ($name:ident($machine:expr) -> $expected:pat $(if $guard:expr)?) => {
+            #[tokio::test]
+            async fn $name() {
+                let machine: CommandMachine = $machine;
+
+                let result = call(&machine).await;
+                assert!(
+                    matches!(&result, $expected $(if $guard)?),
+                    "Unexpected result: {result:?}"
+                );
+            }
+        };
+    }
+
+    create_test!(
+        success(shell(&format!("cat > /dev/null; echo '{RESULT}'")))
+            -> Ok(result) if result.greetings == "Hello, Jane!"
+    );
+    create_test!(
+        reads_input(shell(
+            r#"grep -q '"query":{"name":"Jane"' && echo "$RESULT""#
+        ).with_env("RESULT", RESULT))
+            -> Ok(result) if result.greetings == "Hello, Jane!"
+    );
+    create_test!(
+        current_dir(
+            shell(r#"cat > /dev/null; [ "$(pwd)" = / ] && echo "$RESULT""#)
+                .with_env("RESULT", RESULT)
+                .with_current_dir("/")
+        ) -> Ok(result) if result.greetings == "Hello, Jane!"
+    );
+    create_test!(
+        failure(shell("echo 'No coffee today.' >&2; exit 3"))
+            -> Err(err) if err.error == "CommandFailed"
+                && err.details == Some(serde_json::json!({
+                    "message": "No coffee today.",
+                    "status": 3,
+                }))
+    );
+    create_test!(
+        failure_schema(shell(
+            r#"echo '{"status_code":418,"error":"Teapot","details":null}' >&2; exit 1"#
+        )) -> Err(err) if err.status_code == http::StatusCode::IM_A_TEAPOT
+    );
+    create_test!(
+        invalid_output(shell("echo 'Not JSON.'"))
+            -> Err(err) if err.error == "CommandOutputInvalid"
+    );
+    create_test!(
+        not_spawned(CommandMachine::new("/nonexistent/coffee-machine"))
+            -> Err(err) if err.error == "CommandNotSpawned"
+    );
+    create_test!(
+        timeout(shell("sleep 5").with_timeout(tokio::time::Duration::from_millis(100)))
+            -> Err(err) if err.status_code == http::StatusCode::GATEWAY_TIMEOUT
+    );
+    create_test!(
+        worker(
+            shell(&format!(r#"while read line; do echo '{{"Ok":{RESULT}}}'; done"#))
+                .with_workers(2)
+        ) -> Ok(result) if result.greetings == "Hello, Jane!"
+    );
+    create_test!(
+        worker_error(
+            shell(r#"while read line; do echo '{"Err":{"status_code":409,"error":"Busy","details":null}}'; done"#)
+                .with_workers(2)
+        ) -> Err(err) if err.error == "Busy"
+    );
+    create_test!(
+        worker_exited(shell("read line; exit 0").with_workers(1))
+            -> Err(err) if err.error == "CommandWorkerFailed" && err.retryable
+    );
+
+    #[tokio::test]
+    async fn worker_reused() {
+        // Each worker counts its own requests, so a reused worker replies with more.
+        let machine = shell(
+            r#"count=0; while read line; do count=$((count+1)); echo "{\"Ok\":{\"greetings\":\"$count\",\"narration\":\"\"}}"; done"#,
+        )
+        .with_workers(1);
+
+        for expected in ["1", "2", "3"] {
+            let result = call(&machine)
+                .await
+                .expect("The worker should have replied.");
+            assert_eq!(result.greetings, expected);
+        }
+    }
+}
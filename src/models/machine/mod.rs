@@ -1,12 +1,35 @@
-use crate::{CoffeeMachineError, ValidationError};
+use crate::{CoffeeMachineError, CoffeeShopError, ValidationError};
 use axum::http;
 
 use serde::{de::DeserializeOwned, Serialize};
 
 use super::message;
 
+mod blocking;
+pub use blocking::*;
+
+mod command;
+pub use command::*;
+
+mod tasks;
+pub use tasks::*;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
+
+/// The input passed to a [`Machine`] outside of this process, which serializes the same
+/// as a [`CombinedInput`](message::CombinedInput) without taking ownership of its parts.
+#[derive(Serialize)]
+struct MachineInput<'a, Q, I> {
+    query: &'a Q,
+    input: Option<&'a I>,
+    principal: Option<&'a message::Principal>,
+}
+
 #[cfg(doc)]
-use super::{Shop, Waiter};
+use super::{Barista, Shop, Waiter};
 
 /// A trait that defines the behavior of a coffee machine, i.e. the function
 /// that will be called when a ticket is received, and outputs the result
@@ -46,7 +69,15 @@ where
     ///
     /// A [`Machine`] is expected to process the input and return the output; if an error
     /// occurs, it should return a [`CoffeeMachineError`].
-    async fn call(&self, query: &Q, input: Option<&I>) -> message::MachineResult<O>;
+    ///
+    /// The `principal` is the authenticated identity behind the request, if
+    /// authentication is enabled on the [`Waiter`].
+    async fn call(
+        &self,
+        query: &Q,
+        input: Option<&I>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<O>;
 
     /// Validate the input before processing.
     ///
@@ -55,6 +86,58 @@ where
     /// with the given [`ValidationError`] as [details](serde_json::Value).
     async fn validator(&self, query: &Q, input: Option<&I>) -> Result<(), ValidationError>;
 
+    /// Prepare the [`Machine`] before any [`Barista`] starts, such as loading models or
+    /// opening connection pools.
+    ///
+    /// As this takes `&self`, any state set up here needs interior mutability, e.g.
+    /// [`OnceCell`](tokio::sync::OnceCell). If this fails, the [`Shop`] does not open.
+    ///
+    /// Defaults to doing nothing.
+    async fn setup(&self) -> Result<(), CoffeeMachineError> {
+        Ok(())
+    }
+
+    /// Warm up the [`Machine`] after [`Machine::setup`], before the [`Shop`] announces
+    /// that it is open, such as by running a dummy input through it.
+    ///
+    /// If this fails, the [`Shop`] does not open.
+    ///
+    /// Defaults to doing nothing.
+    async fn warm_up(&self) -> Result<(), CoffeeMachineError> {
+        Ok(())
+    }
+
+    /// Check if the [`Machine`] is healthy and ready to process tickets, such as
+    /// whether its downstream services are reachable.
+    ///
+    /// Defaults to always being healthy.
+    async fn health(&self) -> Result<(), CoffeeMachineError> {
+        Ok(())
+    }
+
+    /// Release the resources of the [`Machine`] after the [`Shop`] closes and all the
+    /// [`Barista`]s have drained.
+    ///
+    /// Defaults to doing nothing.
+    async fn teardown(&self) -> Result<(), CoffeeMachineError> {
+        Ok(())
+    }
+
+    /// Decode a request for the named `task` into the query and input of this machine.
+    ///
+    /// This backs the `/tasks/{task}/request` route of the [`Waiter`]; only machines
+    /// hosting several tasks, such as [`Tasks`], need to implement it. By default, no
+    /// task is found.
+    #[allow(unused_variables)]
+    fn decode_task(
+        &self,
+        task: &str,
+        uri: &http::Uri,
+        body: &[u8],
+    ) -> Result<(Q, Option<I>), CoffeeShopError> {
+        Err(CoffeeShopError::TaskNotFound(task.to_owned()))
+    }
+
     /// The default validator implementation that wraps the [details](serde_json::Value)
     /// in a [`CoffeeMachineError`] and returns it.
     ///
@@ -66,8 +149,16 @@ where
     /// # Note
     ///
     /// You can customise this method to return a different error as needed, as long
-    /// as it returns a [`CoffeeMachineError`].
-    async fn validate(&self, query: &Q, input: Option<&I>) -> Result<(), CoffeeMachineError> {
+    /// as it returns a [`CoffeeMachineError`]. This is also the place to authorize the
+    /// `principal` for the request, e.g. returning a [`http::StatusCode::FORBIDDEN`] error;
+    /// the default implementation does not use it.
+    #[allow(unused_variables)]
+    async fn validate(
+        &self,
+        query: &Q,
+        input: Option<&I>,
+        principal: Option<&message::Principal>,
+    ) -> Result<(), CoffeeMachineError> {
         self.validator(query, input)
             .await
             .map_err(|details| CoffeeMachineError::new(
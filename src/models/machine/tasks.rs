@@ -0,0 +1,501 @@
+use std::marker::PhantomData;
+
+use axum::{extract::Query, http};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::Machine;
+use crate::{
+    errors::handling::IntoCoffeeShopError,
+    models::message::{self, RawJson, TaskQuery},
+    CoffeeMachineError, CoffeeShopError, ValidationError,
+};
+
+#[cfg(doc)]
+use crate::models::{Barista, Shop, Waiter};
+
+/// A [`Machine`] hosting several named tasks, each with a [`Machine`] of its own query,
+/// input and output types, behind the same [`Shop`].
+///
+/// The [`Waiter`] serves each task at `/tasks/{name}/request`, which takes the query
+/// and input of the task itself. All the tasks share the AWS SQS queue of the [`Shop`];
+/// the name of the task is carried in the [`TaskQuery`] of each ticket, and the
+/// [`Barista`]s dispatch the ticket to the [`Machine`] of that task.
+///
+/// ```ignore
+/// let tasks = Tasks::new()
+///     .with_task("greet", GreetingMachine::new())
+///     .with_task("brew", BrewingMachine::new());
+///
+/// let shop = Shop::new("cafe".to_owned(), tasks, config, None).await?;
+/// ```
+#[derive(Default)]
+pub struct Tasks {
+    tasks: hashbrown::HashMap<String, Box<dyn ErasedTask>>,
+}
+
+impl Tasks {
+    /// Create a new [`Tasks`] machine with no tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder pattern - add a task with the given name, replacing any task of the same
+    /// name.
+    pub fn with_task<Q, I, O, M>(mut self, name: impl Into<String>, machine: M) -> Self
+    where
+        Q: message::QueryType + 'static,
+        I: DeserializeOwned + Serialize + Send + Sync + 'static,
+        O: DeserializeOwned + Serialize + Send + Sync + 'static,
+        M: Machine<Q, I, O> + 'static,
+    {
+        self.tasks.insert(
+            name.into(),
+            Box::new(Task {
+                machine,
+                _phantom: PhantomData,
+            }),
+        );
+        self
+    }
+
+    /// Get the names of the tasks.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tasks.keys().map(String::as_str)
+    }
+
+    /// Get the task of a [`TaskQuery`].
+    fn task(&self, query: &TaskQuery) -> Result<&dyn ErasedTask, CoffeeMachineError> {
+        self.tasks.get(&query.task).map(Box::as_ref).ok_or_else(|| {
+            CoffeeMachineError::new(
+                http::StatusCode::NOT_FOUND,
+                "TaskNotFound".to_owned(),
+                Some(serde_json::json!({
+                    "message": format!("Task {task:?} is not found.", task = query.task),
+                })),
+            )
+        })
+    }
+}
+
+impl std::fmt::Debug for Tasks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tasks")
+            .field("tasks", &self.tasks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A task with its query, input and output types erased, so that tasks of different
+/// types can be kept together.
+#[async_trait::async_trait]
+trait ErasedTask: Send + Sync {
+    /// See [`Machine::decode_task`].
+    fn decode(
+        &self,
+        task: &str,
+        uri: &http::Uri,
+        body: &[u8],
+    ) -> Result<(TaskQuery, Option<RawJson>), CoffeeShopError>;
+
+    /// See [`Machine::validate`].
+    async fn validate(
+        &self,
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+        principal: Option<&message::Principal>,
+    ) -> Result<(), CoffeeMachineError>;
+
+    /// See [`Machine::call`].
+    async fn call(
+        &self,
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<RawJson>;
+
+    /// See [`Machine::setup`].
+    async fn setup(&self) -> Result<(), CoffeeMachineError>;
+
+    /// See [`Machine::warm_up`].
+    async fn warm_up(&self) -> Result<(), CoffeeMachineError>;
+
+    /// See [`Machine::health`].
+    async fn health(&self) -> Result<(), CoffeeMachineError>;
+
+    /// See [`Machine::teardown`].
+    async fn teardown(&self) -> Result<(), CoffeeMachineError>;
+}
+
+/// A task backed by a [`Machine`] of concrete types.
+struct Task<Q, I, O, M> {
+    machine: M,
+    _phantom: PhantomData<(Q, I, O)>,
+}
+
+impl<Q, I, O, M> Task<Q, I, O, M>
+where
+    Q: message::QueryType,
+    I: DeserializeOwned + Serialize + Send + Sync,
+{
+    /// Deserialize the query and input of the task from a ticket.
+    fn parse(
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+    ) -> Result<(Q, Option<I>), CoffeeMachineError> {
+        let invalid = |err: serde_json::Error| {
+            CoffeeMachineError::new(
+                http::StatusCode::UNPROCESSABLE_ENTITY,
+                "TaskTicketInvalid".to_owned(),
+                Some(serde_json::json!({
+                    "message": format!(
+                        "The ticket does not match task {task:?}: {err}",
+                        task = query.task,
+                    ),
+                })),
+            )
+        };
+
+        Ok((
+            serde_json::from_str(&query.query).map_err(invalid)?,
+            input.map(RawJson::parse).transpose().map_err(invalid)?,
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl<Q, I, O, M> ErasedTask for Task<Q, I, O, M>
+where
+    Q: message::QueryType,
+    I: DeserializeOwned + Serialize + Send + Sync,
+    O: DeserializeOwned + Serialize + Send + Sync,
+    M: Machine<Q, I, O>,
+{
+    fn decode(
+        &self,
+        task: &str,
+        uri: &http::Uri,
+        body: &[u8],
+    ) -> Result<(TaskQuery, Option<RawJson>), CoffeeShopError> {
+        let Query(query) =
+            Query::<Q>::try_from_uri(uri).map_err(IntoCoffeeShopError::into_coffeeshop_error)?;
+
+        // Parse the input as the type of the task, so that malformed payloads are
+        // rejected as they would be for a single machine.
+        let input = (!body.is_empty())
+            .then(|| serde_json::from_slice::<I>(body))
+            .transpose()
+            .map_err(|err| CoffeeShopError::MalformedJsonPayload(err.to_string()))?;
+
+        let not_serializable = |err: serde_json::Error| CoffeeShopError::InvalidPayload {
+            kind: "TaskPayloadNotSerializable",
+            message: err.to_string(),
+        };
+
+        Ok((
+            TaskQuery::new(task, &query).map_err(not_serializable)?,
+            input
+                .as_ref()
+                .map(RawJson::from_value)
+                .transpose()
+                .map_err(not_serializable)?,
+        ))
+    }
+
+    async fn validate(
+        &self,
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+        principal: Option<&message::Principal>,
+    ) -> Result<(), CoffeeMachineError> {
+        let (query, input) = Self::parse(query, input)?;
+
+        self.machine
+            .validate(&query, input.as_ref(), principal)
+            .await
+    }
+
+    async fn call(
+        &self,
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<RawJson> {
+        let (query, input) = Self::parse(query, input)?;
+
+        let output = self.machine.call(&query, input.as_ref(), principal).await?;
+
+        RawJson::from_value(&output).map_err(|err| {
+            CoffeeMachineError::new(
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                "TaskOutputNotSerializable".to_owned(),
+                Some(serde_json::json!({
+                    "message": format!("The output of the task could not be serialized: {err}"),
+                })),
+            )
+        })
+    }
+
+    async fn setup(&self) -> Result<(), CoffeeMachineError> {
+        self.machine.setup().await
+    }
+
+    async fn warm_up(&self) -> Result<(), CoffeeMachineError> {
+        self.machine.warm_up().await
+    }
+
+    async fn health(&self) -> Result<(), CoffeeMachineError> {
+        self.machine.health().await
+    }
+
+    async fn teardown(&self) -> Result<(), CoffeeMachineError> {
+        self.machine.teardown().await
+    }
+}
+
+#[async_trait::async_trait]
+impl Machine<TaskQuery, RawJson, RawJson> for Tasks {
+    async fn call(
+        &self,
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<RawJson> {
+        self.task(query)?.call(query, input, principal).await
+    }
+
+    /// Unused, as [`Tasks::validate`] is dispatched to the task instead.
+    async fn validator(
+        &self,
+        _query: &TaskQuery,
+        _input: Option<&RawJson>,
+    ) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    async fn validate(
+        &self,
+        query: &TaskQuery,
+        input: Option<&RawJson>,
+        principal: Option<&message::Principal>,
+    ) -> Result<(), CoffeeMachineError> {
+        self.task(query)?.validate(query, input, principal).await
+    }
+
+    fn decode_task(
+        &self,
+        task: &str,
+        uri: &http::Uri,
+        body: &[u8],
+    ) -> Result<(TaskQuery, Option<RawJson>), CoffeeShopError> {
+        self.tasks
+            .get(task)
+            .ok_or_else(|| CoffeeShopError::TaskNotFound(task.to_owned()))?
+            .decode(task, uri, body)
+    }
+
+    async fn setup(&self) -> Result<(), CoffeeMachineError> {
+        for task in self.tasks.values() {
+            task.setup().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn warm_up(&self) -> Result<(), CoffeeMachineError> {
+        for task in self.tasks.values() {
+            task.warm_up().await?;
+        }
+
+        Ok(())
+    }
+
+    /// The [`Tasks`] are only healthy if all of the tasks are.
+    async fn health(&self) -> Result<(), CoffeeMachineError> {
+        futures::future::try_join_all(self.tasks.values().map(|task| task.health()))
+            .await
+            .map(|_| ())
+    }
+
+    /// All the tasks are torn down even if some of them fail; the first error is
+    /// returned.
+    async fn teardown(&self) -> Result<(), CoffeeMachineError> {
+        futures::future::join_all(self.tasks.values().map(|task| task.teardown()))
+            .await
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test::{TestMachine, TestPayload, TestQuery, TestResult, TestStatus};
+
+    /// A task of different types to [`TestMachine`], that echoes its input.
+    struct EchoMachine;
+
+    #[async_trait::async_trait]
+    impl Machine<TestQuery, serde_json::Value, serde_json::Value> for EchoMachine {
+        async fn call(
+            &self,
+            _query: &TestQuery,
+            input: Option<&serde_json::Value>,
+            _principal: Option<&message::Principal>,
+        ) -> message::MachineResult<serde_json::Value> {
+            Ok(input.cloned().unwrap_or_default())
+        }
+
+        async fn validator(
+            &self,
+            _query: &TestQuery,
+            _input: Option<&serde_json::Value>,
+        ) -> Result<(), ValidationError> {
+            Ok(())
+        }
+    }
+
+    fn tasks() -> Tasks {
+        Tasks::new()
+            .with_task::<TestQuery, TestPayload, TestResult, _>("greet", TestMachine::new())
+            .with_task::<TestQuery, serde_json::Value, serde_json::Value, _>("echo", EchoMachine)
+    }
+
+    #[tokio::test]
+    async fn dispatch() {
+        let tasks = tasks();
+
+        let uri = http::Uri::from_static("/tasks/greet/request?name=Jane&timeout=3");
+        let payload = serde_json::to_vec(&TestPayload {
+            action: TestStatus::Work,
+            duration: 1.0,
+        })
+        .unwrap();
+        let (query, input) = tasks.decode_task("greet", &uri, &payload).unwrap();
+
+        assert_eq!(query.task, "greet");
+        assert_eq!(query.timeout, Some(tokio::time::Duration::from_secs(3)));
+        tasks.validate(&query, input.as_ref(), None).await.unwrap();
+
+        let output = tasks
+            .call(&query, input.as_ref(), None)
+            .await
+            .unwrap()
+            .parse::<TestResult>()
+            .unwrap();
+        assert_eq!(output.greetings, "Hello, Jane!");
+
+        let uri = http::Uri::from_static("/tasks/echo/request?name=Jane");
+        let (query, input) = tasks.decode_task("echo", &uri, b"[1, 2, 3]").unwrap();
+        let output = tasks.call(&query, input.as_ref(), None).await.unwrap();
+        assert_eq!(output.get(), "[1,2,3]");
+    }
+
+    #[tokio::test]
+    async fn dispatch_to_validator() {
+        let tasks = tasks();
+
+        let uri = http::Uri::from_static("/tasks/greet/request?name=Jane");
+        let (query, input) = tasks.decode_task("greet", &uri, b"").unwrap();
+
+        let err = tasks
+            .validate(&query, input.as_ref(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code, http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let tasks = tasks();
+        let uri = http::Uri::from_static("/tasks/greet/request?name=Jane");
+
+        assert!(matches!(
+            tasks.decode_task("brew", &uri, b""),
+            Err(CoffeeShopError::TaskNotFound(task)) if task == "brew"
+        ));
+        assert!(matches!(
+            tasks.decode_task(
+                "greet",
+                &http::Uri::from_static("/tasks/greet/request?timeout=soon"),
+                b""
+            ),
+            Err(CoffeeShopError::InvalidQueryOptions(_))
+        ));
+        assert!(matches!(
+            tasks.decode_task("greet", &uri, br#"{"action": "Dance"}"#),
+            Err(CoffeeShopError::MalformedJsonPayload(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn unknown_task() {
+        let query = TaskQuery {
+            task: "brew".to_owned(),
+            query: "{}".to_owned(),
+            timeout: None,
+            is_async: false,
+            rate_limit_key: None,
+            max_attempts: None,
+        };
+
+        let err = tasks().call(&query, None, None).await.unwrap_err();
+        assert_eq!(err.error, "TaskNotFound");
+    }
+
+    /// A task that is never healthy, and records whether it was torn down.
+    #[derive(Default)]
+    struct SickMachine {
+        torn_down: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl Machine<TestQuery, serde_json::Value, serde_json::Value> for SickMachine {
+        async fn call(
+            &self,
+            _query: &TestQuery,
+            _input: Option<&serde_json::Value>,
+            _principal: Option<&message::Principal>,
+        ) -> message::MachineResult<serde_json::Value> {
+            Ok(serde_json::Value::Null)
+        }
+
+        async fn validator(
+            &self,
+            _query: &TestQuery,
+            _input: Option<&serde_json::Value>,
+        ) -> Result<(), ValidationError> {
+            Ok(())
+        }
+
+        async fn health(&self) -> Result<(), CoffeeMachineError> {
+            Err(CoffeeMachineError::new(
+                http::StatusCode::SERVICE_UNAVAILABLE,
+                "Sick".to_owned(),
+                None,
+            ))
+        }
+
+        async fn teardown(&self) -> Result<(), CoffeeMachineError> {
+            self.torn_down
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn lifecycle() {
+        let sick = SickMachine::default();
+        let torn_down = std::sync::Arc::clone(&sick.torn_down);
+        let tasks =
+            tasks().with_task::<TestQuery, serde_json::Value, serde_json::Value, _>("sick", sick);
+
+        tasks.setup().await.unwrap();
+        tasks.warm_up().await.unwrap();
+
+        let err = tasks.health().await.unwrap_err();
+        assert_eq!(err.error, "Sick");
+
+        tasks.teardown().await.unwrap();
+        assert!(torn_down.load(std::sync::atomic::Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,299 @@
+use std::path::Path;
+
+use axum::http;
+use serde::{de::DeserializeOwned, Serialize};
+use wasmtime::{Engine, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+use super::{BlockingMachine, MachineInput};
+use crate::{models::message, CoffeeMachineError, CoffeeShopError, ValidationError};
+
+#[cfg(doc)]
+use super::{BlockingAdapter, Machine};
+
+/// The default amount of fuel each ticket may consume.
+pub const DEFAULT_WASM_FUEL: u64 = 10_000_000_000;
+
+/// The default size in bytes the linear memory of each ticket may grow to.
+pub const DEFAULT_WASM_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// A [`BlockingMachine`] that processes each ticket with a sandboxed WebAssembly module.
+///
+/// Running a module is CPU-bound, so wrap it in a [`BlockingAdapter`] to use it as a
+/// [`Machine`].
+///
+/// Each ticket is run on a fresh instance of the module, which must export:
+///
+/// - `memory`, its linear memory;
+/// - `alloc(len: i32) -> i32`, returning a pointer to `len` bytes to write the input to;
+///   and
+/// - `call(ptr: i32, len: i32) -> i64`, which reads the JSON serialized
+///   [`CombinedInput`](message::CombinedInput) at `ptr`, and returns the pointer to its
+///   reply in the upper 32 bits and the length in the lower 32 bits.
+///
+/// The reply is JSON of either `{"Ok": <output>}` or `{"Err": <CoffeeMachineError>}`.
+/// No host functions are imported into the module.
+///
+/// Each instance is limited to [`WasmMachine::fuel`] and
+/// [`WasmMachine::memory_limit`]; exceeding either fails the ticket.
+pub struct WasmMachine {
+    /// The pre-linked module to instantiate for each ticket.
+    instance: InstancePre<StoreLimits>,
+
+    /// The amount of fuel each ticket may consume.
+    pub fuel: u64,
+
+    /// The size in bytes the linear memory of each ticket may grow to.
+    pub memory_limit: usize,
+}
+
+impl WasmMachine {
+    /// Compile a [`WasmMachine`] from the binary or text of a module.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, CoffeeShopError> {
+        let engine = Self::engine()?;
+        Self::from_module(&engine, Module::new(&engine, bytes))
+    }
+
+    /// Compile a [`WasmMachine`] from a module file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CoffeeShopError> {
+        let engine = Self::engine()?;
+        Self::from_module(&engine, Module::from_file(&engine, path))
+    }
+
+    /// Builder pattern - change the amount of fuel each ticket may consume.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Builder pattern - change the size in bytes the linear memory of each ticket may
+    /// grow to.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
+    /// Create the [`Engine`] to compile modules with, with fuel metering enabled.
+    fn engine() -> Result<Engine, CoffeeShopError> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+
+        Engine::new(&config).map_err(invalid_module)
+    }
+
+    /// Pre-link the compiled module, which has no imports.
+    fn from_module(
+        engine: &Engine,
+        module: wasmtime::Result<Module>,
+    ) -> Result<Self, CoffeeShopError> {
+        let instance = module
+            .and_then(|module| Linker::new(engine).instantiate_pre(&module))
+            .map_err(invalid_module)?;
+
+        Ok(Self {
+            instance,
+            fuel: DEFAULT_WASM_FUEL,
+            memory_limit: DEFAULT_WASM_MEMORY_LIMIT,
+        })
+    }
+
+    /// Run the input through a fresh instance of the module, and return its reply.
+    fn run(&self, input: &[u8]) -> wasmtime::Result<Vec<u8>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.memory_limit)
+            .trap_on_grow_failure(true)
+            .build();
+        let mut store = Store::new(self.instance.module().engine(), limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel)?;
+
+        let instance = self.instance.instantiate(&mut store)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("the module does not export `memory`"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let call = instance.get_typed_func::<(i32, i32), i64>(&mut store, "call")?;
+
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, usize::try_from(ptr)?, input)?;
+
+        let reply = call.call(&mut store, (ptr, len))? as u64;
+        let mut output = vec![0; usize::try_from(reply & u64::from(u32::MAX))?];
+        memory.read(&store, usize::try_from(reply >> 32)?, &mut output)?;
+
+        Ok(output)
+    }
+}
+
+impl std::fmt::Debug for WasmMachine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmMachine")
+            .field("fuel", &self.fuel)
+            .field("memory_limit", &self.memory_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The error for a module that could not be loaded.
+fn invalid_module(err: wasmtime::Error) -> CoffeeShopError {
+    CoffeeShopError::InvalidConfiguration {
+        field: "wasm_module",
+        message: format!("{err:#}"),
+    }
+}
+
+/// The error for a module that failed to produce a reply.
+fn wasm_error(err: wasmtime::Error) -> CoffeeMachineError {
+    let (error, message) = match err.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => (
+            "WasmFuelExhausted",
+            "The module ran out of fuel.".to_owned(),
+        ),
+        _ => ("WasmFailed", format!("The module failed: {err:#}")),
+    };
+
+    CoffeeMachineError::new(
+        http::StatusCode::INTERNAL_SERVER_ERROR,
+        error.to_owned(),
+        Some(serde_json::json!({ "message": message })),
+    )
+}
+
+impl<Q, I, O> BlockingMachine<Q, I, O> for WasmMachine
+where
+    Q: message::QueryType,
+    I: DeserializeOwned + Serialize + Send + Sync,
+    O: DeserializeOwned + Serialize + Send + Sync,
+{
+    fn call(
+        &self,
+        query: &Q,
+        input: Option<&I>,
+        principal: Option<&message::Principal>,
+    ) -> message::MachineResult<O> {
+        let input = serde_json::to_vec(&MachineInput {
+            query,
+            input,
+            principal,
+        })
+        .map_err(|err| wasm_error(err.into()))?;
+
+        let output = self.run(&input).map_err(wasm_error)?;
+
+        serde_json::from_slice::<Result<O, CoffeeMachineError>>(&output).map_err(|err| {
+            CoffeeMachineError::new(
+                http::StatusCode::BAD_GATEWAY,
+                "WasmOutputInvalid".to_owned(),
+                Some(serde_json::json!({
+                    "message": format!("The output of the module is not valid: {err}"),
+                })),
+            )
+        })?
+    }
+
+    fn validator(&self, _query: &Q, _input: Option<&I>) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test::{TestPayload, TestQuery, TestResult, TestStatus};
+
+    /// A module that replies with the given JSON from its data segment, after running
+    /// the given instructions.
+    fn module(reply: &str, instructions: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{data}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 4096))
+                (func (export "call") (param i32 i32) (result i64)
+                    {instructions}
+                    (i64.const {len}))
+            )"#,
+            data = reply.replace('"', "\\\""),
+            len = reply.len(),
+        )
+    }
+
+    fn call(machine: &WasmMachine) -> message::MachineResult<TestResult> {
+        BlockingMachine::<TestQuery, TestPayload, TestResult>::call(
+            machine,
+            &TestQuery {
+                name: "Jane".to_owned(),
+                timeout: None,
+                is_async: false,
+            },
+            Some(&TestPayload {
+                action: TestStatus::Work,
+                duration: 1.0,
+            }),
+            None,
+        )
+    }
+
+    macro_rules! create_test {
+        ($name:ident($machine:expr) -> $expected:pat $(if $guard:expr)?) => {
+            #[test]
+            fn $name() {
+                let machine: WasmMachine = $machine;
+
+                let result = call(&machine);
+                assert!(
+                    matches!(&result, $expected $(if $guard)?),
+                    "Unexpected result: {result:?}"
+                );
+            }
+        };
+    }
+
+    create_test!(
+        success(WasmMachine::from_bytes(module(
+            r#"{"Ok":{"greetings":"Hello, Jane!","narration":"You ran a module."}}"#,
+            "",
+        )).unwrap()) -> Ok(result) if result.greetings == "Hello, Jane!"
+    );
+    create_test!(
+        failure(WasmMachine::from_bytes(module(
+            r#"{"Err":{"status_code":418,"error":"Teapot","details":null}}"#,
+            "",
+        )).unwrap()) -> Err(err) if err.status_code == http::StatusCode::IM_A_TEAPOT
+    );
+    create_test!(
+        invalid_output(WasmMachine::from_bytes(module("Not JSON.", "")).unwrap())
+            -> Err(err) if err.error == "WasmOutputInvalid"
+    );
+    create_test!(
+        out_of_fuel(WasmMachine::from_bytes(module(
+            "{}",
+            "(loop $forever (br $forever))",
+        )).unwrap().with_fuel(1_000_000)) -> Err(err) if err.error == "WasmFuelExhausted"
+    );
+    create_test!(
+        out_of_memory(WasmMachine::from_bytes(module(
+            "{}",
+            "(drop (memory.grow (i32.const 16)))",
+        )).unwrap().with_memory_limit(4 * 65536)) -> Err(err) if err.error == "WasmFailed"
+    );
+
+    #[test]
+    fn missing_exports() {
+        let result = WasmMachine::from_bytes("(module)");
+        let machine = result.expect("An empty module is valid.");
+
+        assert!(matches!(call(&machine), Err(err) if err.error == "WasmFailed"));
+    }
+
+    #[test]
+    fn invalid_module() {
+        assert!(matches!(
+            WasmMachine::from_bytes("(module"),
+            Err(CoffeeShopError::InvalidConfiguration {
+                field: "wasm_module",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use axum::{http, response::IntoResponse};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{CombinedInput, QueryType, ResponseMetadata, Ticket};
+use crate::{errors::ErrorSchema, CoffeeShopError};
+
+/// A single item in a batch request.
+///
+/// If the `query` is not provided, the shared query from the URL query string of the
+/// batch request will be used instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Q: Serialize, I: Serialize",
+    deserialize = "Q: DeserializeOwned, I: DeserializeOwned"
+))]
+pub struct BatchRequestItem<Q, I> {
+    #[serde(default)]
+    pub query: Option<Q>,
+    #[serde(default)]
+    pub input: Option<I>,
+}
+
+impl<Q, I> BatchRequestItem<Q, I>
+where
+    Q: QueryType,
+    I: DeserializeOwned + Serialize,
+{
+    /// Combine this item with the shared query into a [`CombinedInput`].
+    ///
+    /// [`QueryType`] does not require [`Clone`], so the shared query is duplicated
+    /// through a [`serde_json::Value`] round trip instead.
+    pub fn into_combined_input(
+        self,
+        shared_query: Option<&Q>,
+    ) -> Result<CombinedInput<Q, I>, CoffeeShopError> {
+        let query =
+            match (self.query, shared_query) {
+                (Some(query), _) => query,
+                (None, Some(shared_query)) => serde_json::to_value(shared_query)
+                    .and_then(serde_json::from_value)
+                    .map_err(|err| CoffeeShopError::InvalidQueryOptions(err.to_string()))?,
+                (None, None) => return Err(CoffeeShopError::InvalidQueryOptions(
+                    "No query was provided for this item, and there is no shared query in the URL."
+                        .to_owned(),
+                )),
+            };
+
+        Ok(CombinedInput::new(query, self.input))
+    }
+}
+
+/// The outcome of a single item in a batch request.
+///
+/// Exactly one of `output` or `error` is present if the result of the item is available;
+/// otherwise only the `ticket` is present, and the item is still pending.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(serialize = "O: Serialize", deserialize = "O: DeserializeOwned"))]
+pub struct BatchItemResponse<O> {
+    /// The ticket of the item, if it was successfully enqueued.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket: Option<Ticket>,
+
+    /// The output of the item, if the batch had waited for the results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<O>,
+
+    /// The error of the item, if it could not be enqueued or processed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorSchema>,
+}
+
+impl<O> BatchItemResponse<O> {
+    /// Create a new [`BatchItemResponse`] for an item that is enqueued.
+    pub fn new_ticket(ticket: Ticket) -> Self {
+        Self {
+            ticket: Some(ticket),
+            output: None,
+            error: None,
+        }
+    }
+
+    /// Create a new [`BatchItemResponse`] for an item whose result is not available yet.
+    pub fn new_pending(ticket: Ticket) -> Self {
+        Self::new_ticket(ticket)
+    }
+
+    /// Create a new [`BatchItemResponse`] for an item that had been processed.
+    pub fn new_output(ticket: Ticket, output: O) -> Self {
+        Self {
+            ticket: Some(ticket),
+            output: Some(output),
+            error: None,
+        }
+    }
+
+    /// Create a new [`BatchItemResponse`] for an item that had failed.
+    pub fn new_error(ticket: Option<Ticket>, error: ErrorSchema) -> Self {
+        Self {
+            ticket,
+            output: None,
+            error: Some(error),
+        }
+    }
+
+    /// Check if this item had failed.
+    pub fn is_err(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Check if this item is still pending, i.e. neither an output nor an error
+    /// is available.
+    pub fn is_pending(&self) -> bool {
+        self.output.is_none() && self.error.is_none()
+    }
+}
+
+/// Response message for a batch request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(serialize = "O: Serialize", deserialize = "O: DeserializeOwned"))]
+pub struct BatchResponse<O> {
+    pub metadata: ResponseMetadata,
+
+    /// The outcome of each item, in the same order as the request.
+    pub results: Vec<BatchItemResponse<O>>,
+
+    /// The HTTP status code of the response as a whole.
+    #[serde(skip, default = "default_batch_status_code")]
+    pub status_code: http::StatusCode,
+}
+
+/// The default status code of a [`BatchResponse`] when it is deserialized.
+fn default_batch_status_code() -> http::StatusCode {
+    http::StatusCode::OK
+}
+
+impl<O> BatchResponse<O> {
+    /// Create a new [`BatchResponse`] instance.
+    pub fn new(
+        start_time: &tokio::time::Instant,
+        results: Vec<BatchItemResponse<O>>,
+        status_code: http::StatusCode,
+    ) -> Self {
+        Self {
+            metadata: ResponseMetadata::new(start_time),
+            results,
+            status_code,
+        }
+    }
+}
+
+impl<O> IntoResponse for BatchResponse<O>
+where
+    O: Serialize,
+{
+    /// Errors of individual items are reported in their respective
+    /// [`BatchItemResponse`]s, and do not affect the status code of the response.
+    fn into_response(self) -> axum::response::Response<axum::body::Body> {
+        (
+            self.status_code,
+            [
+                (http::header::CONTENT_TYPE, "application/json"),
+                (http::header::CACHE_CONTROL, "no-store"),
+            ],
+            axum::Json(self),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use super::ResponseMetadata;
+
+/// The outcome of a single check of a [`HealthResponse`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HealthCheck {
+    /// Whether the check has passed.
+    pub healthy: bool,
+
+    /// The reason the check has failed, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl HealthCheck {
+    /// A check that has passed.
+    pub fn pass() -> Self {
+        Self {
+            healthy: true,
+            message: None,
+        }
+    }
+
+    /// A check that has failed for the given reason.
+    pub fn fail(message: impl Into<String>) -> Self {
+        Self {
+            healthy: false,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Health report of the shop, for the liveness and readiness probes.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HealthResponse {
+    /// Metadata of the response.
+    pub metadata: ResponseMetadata,
+
+    /// Whether all the checks have passed.
+    pub healthy: bool,
+
+    /// The outcome of each check by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, HealthCheck>,
+}
+
+impl HealthResponse {
+    /// Create a new [`HealthResponse`] from the outcomes of the checks.
+    pub fn new(start_time: &tokio::time::Instant, checks: BTreeMap<String, HealthCheck>) -> Self {
+        Self {
+            metadata: ResponseMetadata::new(start_time),
+            healthy: checks.values().all(|check| check.healthy),
+            checks,
+        }
+    }
+}
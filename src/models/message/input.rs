@@ -1,4 +1,5 @@
-use super::QueryType;
+use super::{Principal, QueryType};
+use crate::helpers::telemetry::TraceContext;
 
 /// A struct that combines a query and an input into a single struct.
 ///
@@ -12,6 +13,16 @@ where
 {
     pub query: Q,
     pub input: Option<I>,
+
+    /// The authenticated principal behind the request, if authentication is enabled.
+    pub principal: Option<Principal>,
+
+    /// The trace this input belongs to, if any.
+    ///
+    /// This is not part of the message body; it is carried in the
+    /// [`TRACEPARENT`](crate::helpers::telemetry::TRACEPARENT) message attribute instead.
+    #[serde(skip)]
+    pub trace_context: Option<TraceContext>,
 }
 
 impl<Q, I> CombinedInput<Q, I>
@@ -21,7 +32,24 @@ where
 {
     /// Create a new [`CombinedInput`] instance.
     pub fn new(query: Q, input: Option<I>) -> Self {
-        Self { query, input }
+        Self {
+            query,
+            input,
+            principal: None,
+            trace_context: None,
+        }
+    }
+
+    /// Builder pattern - attach the authenticated principal to the input.
+    pub fn with_principal(mut self, principal: Option<Principal>) -> Self {
+        self.principal = principal;
+        self
+    }
+
+    /// Builder pattern - attach the trace context to the input.
+    pub fn with_trace_context(mut self, trace_context: Option<TraceContext>) -> Self {
+        self.trace_context = trace_context;
+        self
     }
 }
 
@@ -56,6 +84,7 @@ where
         enum CombinedInputField {
             Query,
             Input,
+            Principal,
             Ignored,
         }
         #[doc(hidden)]
@@ -72,6 +101,7 @@ where
                 match value {
                     0u64 => Ok(CombinedInputField::Query),
                     1u64 => Ok(CombinedInputField::Input),
+                    2u64 => Ok(CombinedInputField::Principal),
                     _ => Ok(CombinedInputField::Ignored),
                 }
             }
@@ -82,6 +112,7 @@ where
                 match __value {
                     "query" => Ok(CombinedInputField::Query),
                     "input" => Ok(CombinedInputField::Input),
+                    "principal" => Ok(CombinedInputField::Principal),
                     _ => Ok(CombinedInputField::Ignored),
                 }
             }
@@ -92,6 +123,7 @@ where
                 match __value {
                     b"query" => Ok(CombinedInputField::Query),
                     b"input" => Ok(CombinedInputField::Input),
+                    b"principal" => Ok(CombinedInputField::Principal),
                     _ => Ok(CombinedInputField::Ignored),
                 }
             }
@@ -134,7 +166,7 @@ where
                     None => {
                         return Err(serde::de::Error::invalid_length(
                             0usize,
-                            &"struct CombinedInput with 2 elements",
+                            &"struct CombinedInput with 3 elements",
                         ));
                     }
                 };
@@ -143,11 +175,19 @@ where
                     None => {
                         return Err(serde::de::Error::invalid_length(
                             1usize,
-                            &"struct CombinedInput with 2 elements",
+                            &"struct CombinedInput with 3 elements",
                         ));
                     }
                 };
-                Ok(CombinedInput { query, input })
+                // Messages from older versions do not have a principal.
+                let principal =
+                    serde::de::SeqAccess::next_element::<Option<Principal>>(&mut seq)?.flatten();
+                Ok(CombinedInput {
+                    query,
+                    input,
+                    principal,
+                    trace_context: None,
+                })
             }
             #[inline]
             fn visit_map<__A>(self, mut map: __A) -> Result<Self::Value, __A::Error>
@@ -156,6 +196,7 @@ where
             {
                 let mut query: Option<Q> = None;
                 let mut input: Option<Option<I>> = None;
+                let mut principal: Option<Option<Principal>> = None;
                 while let Some(key) =
                     serde::de::MapAccess::next_key::<CombinedInputField>(&mut map)?
                 {
@@ -176,6 +217,16 @@ where
                             }
                             input = Some(serde::de::MapAccess::next_value::<Option<I>>(&mut map)?);
                         }
+                        CombinedInputField::Principal => {
+                            if Option::is_some(&principal) {
+                                return Err(<__A::Error as serde::de::Error>::duplicate_field(
+                                    "principal",
+                                ));
+                            }
+                            principal = Some(
+                                serde::de::MapAccess::next_value::<Option<Principal>>(&mut map)?,
+                            );
+                        }
                         _ => {
                             let _ = serde::de::MapAccess::next_value::<serde::de::IgnoredAny>(
                                 &mut map,
@@ -185,17 +236,19 @@ where
                 }
                 let query = match query {
                     Some(value) => value,
-                    None => serde::__private::de::missing_field("query")?,
-                };
-                let input = match input {
-                    Some(value) => value,
-                    None => serde::__private::de::missing_field("input")?,
+                    None => return Err(<__A::Error as serde::de::Error>::missing_field("query")),
                 };
-                Ok(CombinedInput { query, input })
+                Ok(CombinedInput {
+                    query,
+                    // A missing input is the same as a `null` one.
+                    input: input.flatten(),
+                    principal: principal.flatten(),
+                    trace_context: None,
+                })
             }
         }
         #[doc(hidden)]
-        const FIELDS: &[&str] = &["query", "input"];
+        const FIELDS: &[&str] = &["query", "input", "principal"];
         serde::Deserializer::deserialize_struct(
             deserializer,
             "CombinedInput",
@@ -1,12 +1,21 @@
 //! This module contains the internal data structures for messaging between
 //! structs.
 
+mod batch;
+pub use batch::*;
+
+mod health;
+pub use health::*;
+
 mod input;
 pub use input::*;
 
 mod metadata;
 pub use metadata::*;
 
+mod principal;
+pub use principal::*;
+
 mod process_result;
 pub use process_result::*;
 
@@ -22,5 +31,8 @@ pub use status::*;
 mod response;
 pub use response::*;
 
+mod task;
+pub use task::*;
+
 mod ticket;
 pub use ticket::*;
@@ -0,0 +1,123 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The scheme by which a [`Principal`] was authenticated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthScheme {
+    /// A static API key.
+    ApiKey,
+
+    /// A JSON Web Token.
+    Jwt,
+}
+
+/// The authenticated identity behind a request.
+///
+/// This is passed from the [`Waiter`](crate::models::Waiter) to the
+/// [`Machine`](crate::models::Machine) alongside the ticket, so it is serialized into
+/// the AWS SQS message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Principal {
+    /// The identifier of the principal; the name of the API key, or the `sub` claim
+    /// of the JSON Web Token.
+    pub subject: String,
+
+    /// The scheme by which the principal was authenticated.
+    pub scheme: AuthScheme,
+
+    /// The claims of the JSON Web Token, if any, as a JSON string.
+    ///
+    /// This is kept as a string since the AWS SQS messages are not self-describing;
+    /// use [`Principal::claims`] to parse it.
+    pub claims: Option<String>,
+
+    /// Whether the principal is an administrator, who can access tickets owned by
+    /// anyone.
+    ///
+    /// This is only known to the [`Waiter`](crate::models::Waiter) that authenticated
+    /// the principal, so it is never serialized.
+    #[serde(skip)]
+    pub admin: bool,
+}
+
+impl Principal {
+    /// Create a new [`Principal`] authenticated by an API key.
+    pub fn from_api_key(name: String) -> Self {
+        Self {
+            subject: name,
+            scheme: AuthScheme::ApiKey,
+            claims: None,
+            admin: false,
+        }
+    }
+
+    /// Create a new [`Principal`] authenticated by a JSON Web Token.
+    pub fn from_jwt(subject: String, claims: &serde_json::Value) -> Self {
+        Self {
+            subject,
+            scheme: AuthScheme::Jwt,
+            claims: Some(claims.to_string()),
+            admin: false,
+        }
+    }
+
+    /// Get the identifier of the principal as recorded against the tickets it owns,
+    /// in the form of `<scheme>:<subject>`, e.g. `api_key:alice`.
+    ///
+    /// The scheme is included so that an API key cannot impersonate a JSON Web Token
+    /// subject of the same name, and vice versa.
+    pub fn owner(&self) -> String {
+        let scheme = match self.scheme {
+            AuthScheme::ApiKey => "api_key",
+            AuthScheme::Jwt => "jwt",
+        };
+
+        format!("{scheme}:{subject}", subject = self.subject)
+    }
+
+    /// Check if the principal can access a ticket with the given owner.
+    ///
+    /// Tickets without an owner were created while authentication was disabled, and
+    /// are accessible to everyone.
+    pub fn can_access(&self, owner: Option<&str>) -> bool {
+        self.admin || owner.is_none_or(|owner| owner == self.owner())
+    }
+
+    /// Parse the claims of the JSON Web Token into a custom type.
+    ///
+    /// Returns [`None`] if the principal was not authenticated by a JSON Web Token.
+    pub fn claims<T: DeserializeOwned>(&self) -> Option<Result<T, serde_json::Error>> {
+        self.claims
+            .as_deref()
+            .map(|claims| serde_json::from_str(claims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! create_test {
+        ($name:ident($principal:expr, $owner:expr) -> $expected:literal) => {
+            #[test]
+            fn $name() {
+                let principal: Principal = $principal;
+                assert_eq!(principal.can_access($owner), $expected);
+            }
+        };
+    }
+
+    create_test!(own_ticket(Principal::from_api_key("alice".to_owned()), Some("api_key:alice")) -> true);
+    create_test!(other_ticket(Principal::from_api_key("alice".to_owned()), Some("api_key:bob")) -> false);
+    create_test!(other_scheme(Principal::from_api_key("alice".to_owned()), Some("jwt:alice")) -> false);
+    create_test!(unowned_ticket(Principal::from_api_key("alice".to_owned()), None) -> true);
+    create_test!(
+        admin(
+            Principal {
+                admin: true,
+                ..Principal::from_jwt("root".to_owned(), &serde_json::json!({"sub": "root"}))
+            },
+            Some("api_key:bob")
+        ) -> true
+    );
+}
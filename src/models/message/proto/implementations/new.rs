@@ -1,6 +1,6 @@
 use super::{MulticastMessage, MulticastMessageKind, MulticastMessageStatus};
 
-use crate::models::Ticket;
+use crate::{helpers::telemetry::TraceContext, models::Ticket};
 
 impl MulticastMessage {
     /// Creates a new `MulticastMessage` with the given `id` and `kind`.
@@ -16,9 +16,21 @@ impl MulticastMessage {
             kind: kind.into(),
             timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
             status: status.into(),
+            traceparent: String::new(),
         }
     }
 
+    /// Builder pattern - attach the trace context of the span that sent this message.
+    pub fn with_trace_context(mut self, context: &TraceContext) -> Self {
+        self.traceparent = context.traceparent();
+        self
+    }
+
+    /// Get the trace context of the span that sent this message, if any.
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        TraceContext::from_traceparent(&self.traceparent)
+    }
+
     /// Creates a new `MulticastMessage` with the given `id` and `kind` set to `Ticket`,
     /// and `status` set to `Success`.
     pub fn new_ticket_complete(task: &str, ticket: &Ticket) -> Self {
@@ -79,4 +91,15 @@ mod tests {
             MulticastMessageStatus::Aborted
         );
     }
+
+    #[test]
+    fn trace_context() {
+        let ticket = "myId".to_owned();
+        let message = MulticastMessage::new_ticket_complete("myTask", &ticket);
+        assert_eq!(message.trace_context(), None);
+
+        let context = TraceContext::new_root();
+        let message = message.with_trace_context(&context);
+        assert_eq!(message.trace_context(), Some(context));
+    }
 }
@@ -25,4 +25,24 @@ pub trait QueryType: serde::de::DeserializeOwned + serde::Serialize + Send + Syn
     fn is_async(&self) -> bool {
         false
     }
+
+    /// A key to identify the client making the query, for rate limiting.
+    ///
+    /// This is only used if [`Config::rate_limit_key`](crate::cli::Config::rate_limit_key)
+    /// is set to [`RateLimitKey::Query`](crate::helpers::rate_limit::RateLimitKey::Query);
+    /// if [`None`] is returned, the client IP address is used instead.
+    ///
+    /// Defaults to a function that always returns [`None`].
+    fn rate_limit_key(&self) -> Option<String> {
+        None
+    }
+
+    /// The maximum number of times the query is attempted if the machine fails with a
+    /// [retryable](crate::errors::ErrorSchema::retryable) error.
+    ///
+    /// Defaults to a function that always returns [`None`], which falls back to
+    /// [`Config::max_attempts`](crate::cli::Config::max_attempts).
+    fn get_max_attempts(&self) -> Option<u32> {
+        None
+    }
 }
@@ -1,4 +1,5 @@
-use super::ResponseMetadata;
+use super::{ResponseMetadata, Ticket};
+use crate::cli::Config;
 
 /// Status report of the waiter.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -6,6 +7,234 @@ pub struct StatusResponse {
     /// Metadata of the response.
     pub metadata: ResponseMetadata,
 
+    /// The version of `coffeeshop` the shop is built with.
+    #[serde(default)]
+    pub version: String,
+
+    /// The name of the task that the shop is responsible for.
+    #[serde(default)]
+    pub name: String,
+
+    /// The URL of the AWS SQS queue of the tickets; [`None`] if the
+    /// [details are withheld](StatusResponse::without_details).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqs_queue: Option<String>,
+
+    /// The name of the AWS DynamoDB table of the results; [`None`] if the
+    /// [details are withheld](StatusResponse::without_details).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dynamodb_table: Option<String>,
+
     /// dequest count.
     pub request_count: usize,
+
+    /// The number of tickets created by this shop that are not yet fulfilled.
+    pub outstanding_tickets: usize,
+
+    /// The number of tickets in this shop that are fulfilled, but not yet cleaned up.
+    #[serde(default)]
+    pub fulfilled_tickets: usize,
+
+    /// The maximum number of outstanding tickets before new requests are rejected.
+    pub max_tickets: usize,
+
+    /// The latest sample of the approximate number of tickets in the AWS SQS queue,
+    /// if the queue depth is being monitored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_depth: Option<usize>,
+
+    /// The status of each barista, including the additional ones that are not serving.
+    #[serde(default)]
+    pub baristas: Vec<BaristaStatus>,
+
+    /// The status of the announcer.
+    #[serde(default)]
+    pub announcer: AnnouncerStatus,
+
+    /// The operational settings of the shop.
+    #[serde(default)]
+    pub config: ConfigStatus,
+}
+
+impl StatusResponse {
+    /// Withhold the details that should not be shown to every client, i.e. the raw
+    /// tickets in flight and the AWS resources of the shop.
+    ///
+    /// See [`Config::reveals_status_details`] for when this applies.
+    pub fn without_details(mut self) -> Self {
+        self.sqs_queue = None;
+        self.dynamodb_table = None;
+        self.baristas
+            .iter_mut()
+            .for_each(|barista| barista.tickets = None);
+
+        self
+    }
+}
+
+/// Status report of a barista.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BaristaStatus {
+    /// Whether the barista is serving.
+    pub serving: bool,
+
+    /// The total number of tickets the barista had processed.
+    pub process_count: usize,
+
+    /// The number of tickets the barista is currently processing.
+    #[serde(default)]
+    pub in_flight: usize,
+
+    /// The tickets the barista is currently processing; [`None`] if the
+    /// [details are withheld](StatusResponse::without_details).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tickets: Option<Vec<Ticket>>,
+}
+
+/// Status report of the announcer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AnnouncerStatus {
+    /// Whether the announcer is listening for announcements.
+    pub listening: bool,
+
+    /// The number of multicast messages sent.
+    pub sent: usize,
+
+    /// The number of multicast messages received.
+    pub received: usize,
+
+    /// The number of multicast messages that failed to be sent, received or decoded.
+    pub failed: usize,
+}
+
+/// The operational settings of a shop, as reported in its status.
+///
+/// Settings that relate to authentication are left out.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConfigStatus {
+    /// See [`Config::port`].
+    pub port: u16,
+
+    /// See [`Config::multicast_addr`].
+    pub multicast_addr: Option<std::net::SocketAddr>,
+
+    /// See [`Config::baristas`].
+    pub baristas: u16,
+
+    /// See [`Config::max_baristas`].
+    pub max_baristas: Option<u16>,
+
+    /// See [`Config::barista_concurrency`].
+    pub barista_concurrency: u16,
+
+    /// See [`Config::max_queue_depth`].
+    pub max_queue_depth: Option<usize>,
+
+    /// See [`Config::rate_limit`].
+    pub rate_limit: Option<f32>,
+
+    /// See [`Config::max_execution_time`].
+    pub max_execution_time: Option<f32>,
+
+    /// See [`Config::max_attempts`].
+    pub max_attempts: u32,
+
+    /// See [`Config::retry_backoff`].
+    pub retry_backoff: f32,
+
+    /// See [`Config::result_ttl`].
+    pub result_ttl: f32,
+
+    /// See [`Config::drain_timeout`].
+    pub drain_timeout: f32,
+}
+
+impl From<&Config> for ConfigStatus {
+    fn from(config: &Config) -> Self {
+        Self {
+            port: config.port,
+            multicast_addr: Some(config.multicast_addr()),
+            baristas: config.baristas,
+            max_baristas: config.max_baristas,
+            barista_concurrency: config.barista_concurrency,
+            max_queue_depth: config.max_queue_depth,
+            rate_limit: config.rate_limit,
+            max_execution_time: config.max_execution_time,
+            max_attempts: config.max_attempts,
+            retry_backoff: config.retry_backoff,
+            result_ttl: config.result_ttl,
+            drain_timeout: config.drain_timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_without_details() {
+        let status: StatusResponse = serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "hostname": "host",
+                "timestamp": "2024-12-01T00:00:00Z",
+                "uptime": 1.5,
+            },
+            "request_count": 3,
+            "outstanding_tickets": 1,
+            "max_tickets": 256,
+        }))
+        .unwrap();
+
+        assert_eq!(status.request_count, 3);
+        assert_eq!(status.sqs_queue, None);
+        assert_eq!(status.fulfilled_tickets, 0);
+        assert!(status.baristas.is_empty());
+        assert_eq!(status.announcer, AnnouncerStatus::default());
+        assert_eq!(status.config, ConfigStatus::default());
+    }
+
+    #[test]
+    fn without_details() {
+        let status: StatusResponse = serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "hostname": "host",
+                "timestamp": "2024-12-01T00:00:00Z",
+                "uptime": 1.5,
+            },
+            "sqs_queue": "https://sqs.us-east-1.amazonaws.com/123456789012/tickets",
+            "dynamodb_table": "results",
+            "request_count": 3,
+            "outstanding_tickets": 1,
+            "max_tickets": 256,
+            "baristas": [
+                {"serving": true, "process_count": 2, "in_flight": 1, "tickets": ["ticket-1"]},
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            status.baristas[0].tickets,
+            Some(vec!["ticket-1".to_owned()])
+        );
+
+        let withheld = serde_json::to_value(status.without_details()).unwrap();
+
+        assert!(withheld.get("sqs_queue").is_none());
+        assert!(withheld.get("dynamodb_table").is_none());
+        assert_eq!(
+            withheld["baristas"][0],
+            serde_json::json!({"serving": true, "process_count": 2, "in_flight": 1})
+        );
+    }
+
+    #[test]
+    fn config_status() {
+        let config = Config::default().with_baristas(3).unwrap();
+        let status = ConfigStatus::from(&config);
+
+        assert_eq!(status.baristas, 3);
+        assert_eq!(status.multicast_addr, Some(config.multicast_addr()));
+        assert_eq!(status.drain_timeout, config.drain_timeout);
+    }
 }
@@ -0,0 +1,157 @@
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+
+use super::QueryType;
+
+#[cfg(doc)]
+use crate::models::Tasks;
+
+/// A JSON value kept in its serialized form, to carry the inputs and outputs of the
+/// different tasks of a [`Tasks`] machine through the same [`Shop`](crate::models::Shop).
+///
+/// This is serialized as the JSON value itself in human readable formats, such as the
+/// HTTP responses; and as a JSON string in binary formats, such as the AWS SQS
+/// messages, which cannot represent arbitrary JSON values.
+#[derive(Debug, Clone)]
+pub struct RawJson(Box<RawValue>);
+
+impl RawJson {
+    /// Serialize a value into a [`RawJson`].
+    pub fn from_value<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Self> {
+        serde_json::value::to_raw_value(value).map(Self)
+    }
+
+    /// Deserialize the value back from the [`RawJson`].
+    pub fn parse<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(self.0.get())
+    }
+
+    /// Get the serialized JSON.
+    pub fn get(&self) -> &str {
+        self.0.get()
+    }
+}
+
+impl PartialEq for RawJson {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl Serialize for RawJson {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.0.serialize(serializer)
+        } else {
+            serializer.serialize_str(self.0.get())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Box::<RawValue>::deserialize(deserializer).map(Self)
+        } else {
+            RawValue::from_string(String::deserialize(deserializer)?)
+                .map(Self)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/// The query of a ticket for one of the tasks of a [`Tasks`] machine.
+///
+/// The query of the task itself is kept serialized, alongside the properties the
+/// [`Waiter`](crate::models::Waiter) and [`Barista`](crate::models::Barista) need from
+/// its [`QueryType`].
+#[serde_with::serde_as]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TaskQuery {
+    /// The name of the task.
+    pub task: String,
+
+    /// The JSON serialized query of the task.
+    pub query: String,
+
+    #[serde_as(as = "Option<serde_with::DurationSecondsWithFrac<f64>>")]
+    pub timeout: Option<tokio::time::Duration>,
+
+    #[serde(rename = "async", default)]
+    pub is_async: bool,
+
+    #[serde(default)]
+    pub rate_limit_key: Option<String>,
+
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+}
+
+impl TaskQuery {
+    /// Create a new [`TaskQuery`] from the query of the named task.
+    pub fn new<Q: QueryType>(task: &str, query: &Q) -> serde_json::Result<Self> {
+        Ok(Self {
+            task: task.to_owned(),
+            query: serde_json::to_string(query)?,
+            timeout: query.get_timeout(),
+            is_async: query.is_async(),
+            rate_limit_key: query.rate_limit_key(),
+            max_attempts: query.get_max_attempts(),
+        })
+    }
+}
+
+impl QueryType for TaskQuery {
+    fn get_timeout(&self) -> Option<tokio::time::Duration> {
+        self.timeout
+    }
+
+    fn is_async(&self) -> bool {
+        self.is_async
+    }
+
+    fn rate_limit_key(&self) -> Option<String> {
+        self.rate_limit_key.clone()
+    }
+
+    fn get_max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::message::CombinedInput;
+
+    #[tokio::test]
+    async fn raw_json_round_trip() {
+        let raw = RawJson::from_value(&serde_json::json!({"name": "Jane", "age": 42})).unwrap();
+
+        // Human readable formats carry the JSON value as is.
+        let json = serde_json::to_string(&raw).unwrap();
+        assert_eq!(json, r#"{"age":42,"name":"Jane"}"#);
+        assert_eq!(serde_json::from_str::<RawJson>(&json).unwrap(), raw);
+
+        // Binary formats carry the JSON string instead.
+        let input = CombinedInput::new(
+            TaskQuery {
+                task: "greet".to_owned(),
+                query: r#"{"name":"Jane"}"#.to_owned(),
+                timeout: Some(tokio::time::Duration::from_secs(3)),
+                is_async: true,
+                rate_limit_key: None,
+                max_attempts: Some(2),
+            },
+            Some(raw.clone()),
+        );
+        let encoded = crate::helpers::serde::serialize(input.clone())
+            .await
+            .unwrap();
+        let decoded: CombinedInput<TaskQuery, RawJson> =
+            crate::helpers::serde::deserialize(encoded).unwrap();
+
+        assert_eq!(decoded.query, input.query);
+        assert_eq!(decoded.input, Some(raw));
+    }
+}
@@ -23,6 +23,78 @@ impl QueryType for TicketQuery {
     }
 }
 
+/// The mode of waiting for the results of a batch retrieval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BatchRetrieveMode {
+    /// Wait until all the tickets are fulfilled, or the timeout is reached.
+    #[default]
+    All,
+
+    /// Wait until at least one of the tickets is fulfilled, or the timeout is reached.
+    Any,
+
+    /// Do not wait; return whatever results are available right now.
+    AvailableNow,
+}
+
+/// A query structure to retrieve the results of multiple tickets.
+///
+/// Unlike [`TicketQuery`], this is sent as a JSON body rather than the URL query.
+#[serde_with::serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BatchTicketQuery {
+    pub tickets: Vec<Ticket>,
+    #[serde(default)]
+    pub mode: BatchRetrieveMode,
+    #[serde_as(as = "Option<serde_with::DurationSecondsWithFrac<f64>>")]
+    #[serde(default)]
+    pub timeout: Option<tokio::time::Duration>,
+}
+
+/// Implement the [`QueryType`] trait for [`BatchTicketQuery`].
+impl QueryType for BatchTicketQuery {
+    fn get_timeout(&self) -> Option<tokio::time::Duration> {
+        self.timeout
+    }
+}
+
+/// The state of a ticket, as reported by [`TicketStatusResponse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TicketState {
+    /// The ticket is still waiting in the queue, or being processed.
+    Pending,
+
+    /// The ticket had been processed successfully; its output can be retrieved.
+    Succeeded,
+
+    /// The ticket had failed; its error can be retrieved.
+    Failed,
+}
+
+/// A response structure to report the state of a ticket, without its result.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TicketStatusResponse {
+    pub metadata: ResponseMetadata,
+    pub ticket: Ticket,
+    pub state: TicketState,
+}
+
+impl IntoResponse for TicketStatusResponse {
+    fn into_response(self) -> axum::response::Response<axum::body::Body> {
+        (
+            axum::http::StatusCode::OK,
+            [
+                (axum::http::header::CONTENT_TYPE, "application/json"),
+                (axum::http::header::CACHE_CONTROL, "no-store"),
+            ],
+            axum::Json(self),
+        )
+            .into_response()
+    }
+}
+
 /// A response structure to return the result of a ticket.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct TicketResponse {
@@ -28,7 +28,9 @@ mod shop;
 pub use shop::*;
 
 mod machine;
-pub use machine::Machine;
+#[cfg(feature = "wasm")]
+pub use machine::WasmMachine;
+pub use machine::{BlockingAdapter, BlockingMachine, CommandMachine, Machine, Tasks};
 
 mod waiter;
 pub use waiter::*;
@@ -34,6 +34,17 @@ pub struct Order {
 
     /// A [`Notify`](tokio::sync::Notify) instance to notify the waiter that the ticket is ready.
     notify: tokio::sync::Notify,
+
+    /// The time at which this order was created.
+    created_at: tokio::time::Instant,
+
+    /// The [owner](crate::models::message::Principal::owner) of the ticket, if it was
+    /// created by an authenticated principal.
+    owner: Option<String>,
+
+    /// The slot reserved for the ticket by the [`Waiter`] that created it, released once
+    /// the order is fulfilled.
+    reservation: Option<helpers::capacity::TicketReservation>,
 }
 
 impl Order {
@@ -43,9 +54,32 @@ impl Order {
             ticket,
             result: std::sync::OnceLock::new(),
             notify: tokio::sync::Notify::new(),
+            created_at: tokio::time::Instant::now(),
+            owner: None,
+            reservation: None,
         }
     }
 
+    /// Builder pattern - set the owner of the ticket.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Builder pattern - hold the slot reserved for the ticket until it is fulfilled.
+    pub fn with_reservation(
+        mut self,
+        reservation: Option<helpers::capacity::TicketReservation>,
+    ) -> Self {
+        self.reservation = reservation;
+        self
+    }
+
+    /// Get the owner of the ticket, if any.
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
     /// Get the result of the ticket if one is available.
     pub fn result(&self) -> Option<&(tokio::time::Instant, bool)> {
         self.result.get()
@@ -56,6 +90,12 @@ impl Order {
         self.result().map(|(instant, _)| instant.elapsed())
     }
 
+    /// Get the time it took for this order to be fulfilled, measured from its creation.
+    pub fn processing_time(&self) -> Option<tokio::time::Duration> {
+        self.result()
+            .map(|(instant, _)| instant.saturating_duration_since(self.created_at))
+    }
+
     /// Complete the ticket with the result and the timestamp.
     pub fn complete_with_timestamp(
         &self,
@@ -67,6 +107,7 @@ impl Order {
             // Add the timestamp to the result.
             .set((timestamp, success))
             .map_err(|_| CoffeeShopError::ResultAlreadySet)?;
+        self.release_reservation();
         self.notify.notify_waiters();
 
         Ok(())
@@ -82,6 +123,23 @@ impl Order {
         self.result().is_some()
     }
 
+    /// Check if this order is abandoned.
+    ///
+    /// An order is considered abandoned if it has not been fulfilled within `max_age`
+    /// of its creation.
+    pub fn is_abandoned(&self, max_age: std::time::Duration) -> bool {
+        !self.is_fulfilled() && self.created_at.elapsed() >= max_age
+    }
+
+    /// Release the slot reserved for the ticket, if any.
+    ///
+    /// Returns `true` if a slot was released by this call.
+    pub fn release_reservation(&self) -> bool {
+        self.reservation
+            .as_ref()
+            .is_some_and(helpers::capacity::TicketReservation::release)
+    }
+
     /// Check if this result is stale.
     ///
     /// A result is considered stale if it has a result set for more than a certain timeout,
@@ -274,4 +332,38 @@ mod tests {
         drop = false,
         expected = false
     ));
+
+    #[tokio::test]
+    async fn processing_time() {
+        let order = Order::new("test_ticket".to_owned());
+        assert_eq!(order.processing_time(), None);
+
+        let elapsed = tokio::time::Duration::from_secs(3);
+        order
+            .complete_with_timestamp(true, order.created_at + elapsed)
+            .expect("Failed to complete the order.");
+
+        assert_eq!(order.processing_time(), Some(elapsed));
+    }
+
+    #[test]
+    fn abandoned_order_releases_reservation() {
+        let capacity = helpers::capacity::TicketCapacity::new();
+        let reservation = capacity.reserve_up_to(1, 1).pop();
+        let order = Order::new("test_ticket".to_owned()).with_reservation(reservation);
+
+        // The order is never completed.
+        assert!(!order.is_abandoned(STALE_AGE));
+        assert!(order.is_abandoned(tokio::time::Duration::ZERO));
+        assert_eq!(capacity.in_flight(), 1);
+
+        assert!(order.release_reservation());
+        assert!(!order.release_reservation());
+        assert_eq!(capacity.in_flight(), 0);
+
+        // A late result does not release the slot again.
+        order.complete(true).expect("Failed to complete the order.");
+        assert!(!order.is_abandoned(tokio::time::Duration::ZERO));
+        assert_eq!(capacity.in_flight(), 0);
+    }
 }
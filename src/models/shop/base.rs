@@ -1,7 +1,10 @@
 use serde::{de::DeserializeOwned, Serialize};
 use std::{marker::PhantomData, sync::Arc};
 
-use super::super::{message, Announcer, Barista, Machine, Orders, Waiter};
+use super::{
+    super::{message, Announcer, Barista, Machine, Orders, Waiter},
+    HealthMonitor,
+};
 use crate::{cli::Config, helpers, CoffeeShopError};
 
 #[cfg(doc)]
@@ -103,6 +106,9 @@ where
     /// Reference to the announcer that will announce the ticket is ready.
     pub announcer: Announcer<Q, I, O, F>,
 
+    /// The record of the recent successful calls to AWS, for the readiness check.
+    pub health: HealthMonitor,
+
     /// Phantom data to attach the input and output types to the shop.
     _phantom: PhantomData<(Q, I, O)>,
 }
@@ -124,6 +130,19 @@ where
         #[cfg(feature = "tokio_debug")]
         console_subscriber::init();
 
+        let log_format = crate::logger::init_with_format(config.log_format);
+        if log_format != config.log_format {
+            crate::warn!(
+                target: LOG_TARGET,
+                "The logger had already been initialised as {log_format:?} before the shop was created; ignoring the log format {requested:?}.",
+                requested = config.log_format,
+            );
+        }
+
+        if let Some(path) = &config.trace_file {
+            helpers::telemetry::init_json_export(path)?;
+        }
+
         // If the table has not been set, use the default table name with the prefix.
         // Otherwise, remove the name from `config` and put it into the [`Shop`].
         let dynamodb_table = config
@@ -142,7 +161,9 @@ where
             helpers::aws::get_aws_config().await?
         };
 
-        let baristas = config.baristas;
+        let ticket_signer = helpers::ticket_token::TicketSigner::from_config(&config)?;
+
+        let baristas = config.max_baristas();
         let shop = Arc::new_cyclic(|me| Self {
             name,
             orders: Orders::new(),
@@ -151,11 +172,12 @@ where
             sqs_queue,
             config,
             aws_config,
-            waiter: Arc::new(Waiter::new(me.clone())),
+            waiter: Arc::new(Waiter::new(me.clone()).with_ticket_signer(ticket_signer)),
             baristas: (0..baristas)
                 .map(|_| Barista::new(me.clone()))
                 .collect::<Vec<Barista<Q, I, O, F>>>(),
             announcer: Announcer::new(me.clone()),
+            health: HealthMonitor::new(),
             _phantom: PhantomData,
         });
 
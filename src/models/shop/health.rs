@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::Shop;
+use crate::{
+    helpers,
+    models::{message, Machine},
+};
+
+#[cfg(doc)]
+use crate::models::{Announcer, Barista, Waiter};
+
+const LOG_TARGET: &str = "coffeeshop::models::shop::health";
+
+/// How long a successful call to a dependency is trusted for, before the readiness
+/// check calls it again.
+const HEALTH_STALE_AFTER: tokio::time::Duration = tokio::time::Duration::from_secs(60);
+
+/// The ticket looked up in the DynamoDB table to check that it is reachable; it is
+/// never expected to exist.
+const HEALTH_CHECK_TICKET: &str = "coffeeshop-health-check";
+
+/// An external dependency of the [`Shop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dependency {
+    /// The AWS SQS queue of the tickets.
+    Sqs,
+
+    /// The AWS DynamoDB table of the results.
+    DynamoDB,
+}
+
+/// Records the last successful calls to the [`Dependency`]s of a [`Shop`].
+///
+/// These are recorded as a side effect of the normal operation of the [`Waiter`] and
+/// the [`Barista`]s, so that the readiness check does not need to call the
+/// dependencies every time.
+#[derive(Debug, Default)]
+pub struct HealthMonitor {
+    sqs: Mutex<Option<tokio::time::Instant>>,
+    dynamodb: Mutex<Option<tokio::time::Instant>>,
+}
+
+impl HealthMonitor {
+    /// Create a new [`HealthMonitor`] without any successful calls.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn slot(&self, dependency: Dependency) -> &Mutex<Option<tokio::time::Instant>> {
+        match dependency {
+            Dependency::Sqs => &self.sqs,
+            Dependency::DynamoDB => &self.dynamodb,
+        }
+    }
+
+    /// Record a successful call to the dependency.
+    pub fn record(&self, dependency: Dependency) {
+        *self
+            .slot(dependency)
+            .lock()
+            .expect("The health monitor lock is poisoned; this should not be possible.") =
+            Some(tokio::time::Instant::now());
+    }
+
+    /// Get the time of the last successful call to the dependency, if any.
+    pub fn last_success(&self, dependency: Dependency) -> Option<tokio::time::Instant> {
+        *self
+            .slot(dependency)
+            .lock()
+            .expect("The health monitor lock is poisoned; this should not be possible.")
+    }
+
+    /// Check if the dependency had a successful call within the given duration.
+    pub fn is_recent(&self, dependency: Dependency, within: tokio::time::Duration) -> bool {
+        self.last_success(dependency)
+            .is_some_and(|instant| instant.elapsed() <= within)
+    }
+}
+
+impl<Q, I, O, F> Shop<Q, I, O, F>
+where
+    Q: message::QueryType + 'static,
+    I: Serialize + DeserializeOwned + Send + Sync + 'static,
+    O: Serialize + DeserializeOwned + Send + Sync + 'static,
+    F: Machine<Q, I, O>,
+{
+    /// Check if the shop is ready to take orders.
+    ///
+    /// The shop is ready if:
+    /// - it is not closing;
+    /// - the SQS queue and the DynamoDB table had a recent successful call, or respond
+    ///   to one now;
+    /// - the [`Announcer`] is listening;
+    /// - all the permanent [`Barista`]s are serving; and
+    /// - the [`Machine`] is [healthy](Machine::health).
+    pub async fn check_readiness(&self) -> BTreeMap<String, message::HealthCheck> {
+        let (sqs, dynamodb, machine) = tokio::join!(
+            self.check_dependency(Dependency::Sqs),
+            self.check_dependency(Dependency::DynamoDB),
+            async {
+                match self.coffee_machine.health().await {
+                    Ok(()) => message::HealthCheck::pass(),
+                    Err(err) => message::HealthCheck::fail(err.to_string()),
+                }
+            },
+        );
+
+        let draining = if self.waiter.is_closing() {
+            message::HealthCheck::fail("The shop is closing.")
+        } else {
+            message::HealthCheck::pass()
+        };
+
+        let announcer = if self.announcer.is_listening() {
+            message::HealthCheck::pass()
+        } else {
+            message::HealthCheck::fail("The announcer is not listening.")
+        };
+
+        let required = usize::from(self.config.baristas).min(self.baristas.len());
+        let serving = self
+            .baristas
+            .iter()
+            .take(required)
+            .filter(|barista| barista.is_serving())
+            .count();
+        let baristas = if serving == required {
+            message::HealthCheck::pass()
+        } else {
+            message::HealthCheck::fail(format!(
+                "Only {serving} of {required} baristas are serving."
+            ))
+        };
+
+        BTreeMap::from([
+            ("draining".to_owned(), draining),
+            ("sqs".to_owned(), sqs),
+            ("dynamodb".to_owned(), dynamodb),
+            ("announcer".to_owned(), announcer),
+            ("baristas".to_owned(), baristas),
+            ("machine".to_owned(), machine),
+        ])
+    }
+
+    /// Check a dependency, calling it only if there had not been a recent successful
+    /// call.
+    async fn check_dependency(&self, dependency: Dependency) -> message::HealthCheck {
+        if self.health.is_recent(dependency, HEALTH_STALE_AFTER) {
+            return message::HealthCheck::pass();
+        }
+
+        let result = match dependency {
+            Dependency::Sqs => helpers::sqs::get_ticket_count(self).await.map(|_| ()),
+            Dependency::DynamoDB => helpers::dynamodb::get_ticket_record_by_ticket(
+                self,
+                &HEALTH_CHECK_TICKET.to_owned(),
+            )
+            .await
+            .map(|_| ()),
+        };
+
+        match result {
+            Ok(()) => {
+                self.health.record(dependency);
+                message::HealthCheck::pass()
+            }
+            Err(err) => {
+                crate::warn!(
+                    target: LOG_TARGET,
+                    "The readiness check of {dependency:?} failed: {err}",
+                );
+
+                message::HealthCheck::fail(err.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_records_recent_successes() {
+        let monitor = HealthMonitor::new();
+        let within = tokio::time::Duration::from_secs(60);
+
+        assert!(!monitor.is_recent(Dependency::Sqs, within));
+
+        monitor.record(Dependency::Sqs);
+        assert!(monitor.is_recent(Dependency::Sqs, within));
+        assert!(!monitor.is_recent(Dependency::DynamoDB, within));
+    }
+}
@@ -113,8 +113,7 @@ where
                 dynamodb::get_process_successes_by_tickets::<_>(self, unfulfilled_tickets.iter())
                     .await
             }
-            .await?
-            .into_iter(),
+            .await?,
         );
 
         for segment in self.orders().iter().await {
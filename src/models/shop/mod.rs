@@ -3,6 +3,9 @@
 mod base;
 pub use base::*;
 
+mod health;
+pub use health::*;
+
 mod open;
 mod order;
 
@@ -7,6 +7,7 @@ use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
     helpers,
+    logger::LogContext,
     models::{message, Barista, Machine},
     CoffeeShopError,
 };
@@ -16,6 +17,10 @@ const LOG_TARGET: &str = "coffeeshop::models::shop";
 /// The interval to check for fulfilled orders in DynamoDB.
 const CHECK_DYNAMODB_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(15);
 
+/// The interval to release the capacity held by abandoned orders.
+const RELEASE_ABANDONED_ORDERS_INTERVAL: tokio::time::Duration =
+    tokio::time::Duration::from_secs(60);
+
 impl<Q, I, O, F> Shop<Q, I, O, F>
 where
     Q: message::QueryType + 'static,
@@ -27,6 +32,19 @@ where
     ///
     /// This function will start the waiter, baristas, and announcer.
     ///
+    /// The [`Machine`] is [set up](Machine::setup) and [warmed up](Machine::warm_up)
+    /// before any of them start, failing fast if either fails; and
+    /// [torn down](Machine::teardown) after all of them have stopped.
+    ///
+    /// The shop closes on Ctrl-C, SIGTERM or the `shutdown_signal`, in this order:
+    ///
+    /// 1. the waiter rejects any new orders with [`CoffeeShopError::ShopClosing`];
+    /// 2. the baristas stop fetching tickets and complete the ones in flight, up to the
+    ///    [drain timeout](crate::cli::Config::drain_timeout), after which the rest are
+    ///    returned to the queue;
+    /// 3. the waiter, the announcer and the periodic tasks stop, and the announcer
+    ///    announces the shop as closed.
+    ///
     /// # Parameters
     ///
     /// - `shutdown_signal` - A signal to shutdown the shop. This will be used internally
@@ -35,6 +53,8 @@ where
     /// - `additional_routes` - Additional routes to be added to the waiter. This is useful
     ///   when you want to add custom routes to the waiter. If you do not want to add any,
     ///   pass a `vec![].into_iter()`.
+    ///
+    /// Everything logged while the shop is open is in the [`LogContext`] of the shop.
     pub async fn open(
         &self,
         shutdown_signal: Option<Arc<Notify>>,
@@ -44,6 +64,23 @@ where
                 axum::routing::method_routing::MethodRouter<()>,
             ),
         >,
+    ) -> Result<(), CoffeeShopError> {
+        LogContext::current()
+            .with_shop(&self.name)
+            .scope(self.open_until_closed(shutdown_signal, additional_routes))
+            .await
+    }
+
+    /// The body of [`Shop::open`], without the [`LogContext`].
+    async fn open_until_closed(
+        &self,
+        shutdown_signal: Option<Arc<Notify>>,
+        additional_routes: impl Iterator<
+            Item = (
+                &'static str,
+                axum::routing::method_routing::MethodRouter<()>,
+            ),
+        >,
     ) -> Result<(), CoffeeShopError> {
         // If the shutdown signal is not provided, create a new one.
         let shutdown_signal = shutdown_signal.unwrap_or_else(|| Arc::new(Notify::new()));
@@ -53,12 +90,34 @@ where
 
         let max_execution_time = self.config.max_execution_time();
 
+        crate::info!(target: LOG_TARGET, "Setting up the coffee machine...");
+        self.coffee_machine.setup().await.map_err(|error| {
+            CoffeeShopError::MachineLifecycleFailed {
+                hook: "setup",
+                error,
+            }
+        })?;
+
+        crate::info!(target: LOG_TARGET, "Warming up the coffee machine...");
+        if let Err(error) = self.coffee_machine.warm_up().await {
+            // The warm up error takes precedence; any teardown error is only logged.
+            let _ = self.teardown_machine().await;
+
+            return Err(CoffeeShopError::MachineLifecycleFailed {
+                hook: "warm_up",
+                error,
+            });
+        }
+
+        // Notified once the baristas have drained, to close the rest of the shop.
+        let closed_signal = Arc::new(Notify::new());
+
         // Using join instead of select to allow all tasks to gracefully shutdown.
-        tokio::try_join! {
+        let result = tokio::try_join! {
             // Termination signal.
             async {
                 tokio::select!(
-                    _ = tokio::signal::ctrl_c() => {
+                    _ = termination_signal() => {
                         crate::warn!(target: LOG_TARGET, "Received termination signal. Shutting down the shop.");
                         shutdown_signal.clone().notify_waiters();
                     },
@@ -67,28 +126,36 @@ where
                     },
                 );
 
+                self.waiter.close();
+
                 Ok::<(), CoffeeShopError>(())
             },
             // Waiter.
             async {
-                self.waiter.serve(additional_routes, shutdown_signal.clone(), max_execution_time).await
+                self.waiter.serve(additional_routes, closed_signal.clone(), max_execution_time).await
                 .inspect_err(
                     |err| crate::error!(target: LOG_TARGET, "The waiter has stopped serving requests. Error: {:?}", err)
                 )
             },
             // Baristas.
             async {
-                Barista::serve_all(&self.baristas, shutdown_signal.clone()).await
+                let result = Barista::serve_all(&self.baristas, shutdown_signal.clone()).await
                 .inspect_err(
                     |err| crate::error!(target: LOG_TARGET, "The baristas have stopped serving requests. Error: {:?}", err)
-                )
+                );
+
+                // Only close the rest of the shop once the baristas have drained, so
+                // that the outstanding orders can still be collected.
+                closed_signal.notify_waiters();
+
+                result
             },
             // Announcer.
             async {
                 // Announce the shop is now opening.
                 // Since the announcer already bound its sockets, it will in fact hear this announcement.
                 self.announcer.announce_status(message::MulticastMessageStatus::Success).await?;
-                let result = self.announcer.listen_for_announcements(shutdown_signal.clone()).await
+                let result = self.announcer.listen_for_announcements(closed_signal.clone()).await
                     .inspect_err(
                         |err| crate::error!(target: LOG_TARGET, "The announcer has stopped listening for announcements. Error: {:?}", err)
                     );
@@ -106,14 +173,27 @@ where
 
                 result
             },
+            // Waiter periodic sampling of the SQS queue depth for admission control.
+            async {
+                self.waiter.periodically_sample_queue_depth(closed_signal.clone()).await
+            },
             // Shop periodic checking of DynamoDB as a final line of defence.
             async {
-                self.periodically_check_for_fulfilled_orders(CHECK_DYNAMODB_INTERVAL, shutdown_signal.clone()).await
+                self.periodically_check_for_fulfilled_orders(CHECK_DYNAMODB_INTERVAL, closed_signal.clone()).await
                 .inspect_err(
                     |err| crate::error!(target: LOG_TARGET, "The shop has stopped checking for fulfilled orders. Error: {:?}", err)
                 )
             },
-        }
+            // Shop periodic release of the capacity held by abandoned orders.
+            async {
+                self.periodically_release_abandoned_orders(RELEASE_ABANDONED_ORDERS_INTERVAL, closed_signal.clone()).await
+            },
+        };
+
+        let torn_down = self.teardown_machine().await;
+
+        result
+        .and(torn_down)
         .map(|_|
             crate::info!(target: LOG_TARGET, "The shop has been closed. See you!")
         )
@@ -122,4 +202,35 @@ where
                 crate::error!(target: LOG_TARGET, "The shop has been shutdown due to the above error.")
         )
     }
+
+    /// Tear down the [`Machine`], logging any error.
+    async fn teardown_machine(&self) -> Result<(), CoffeeShopError> {
+        crate::info!(target: LOG_TARGET, "Tearing down the coffee machine...");
+
+        self.coffee_machine
+            .teardown()
+            .await
+            .map_err(|error| CoffeeShopError::MachineLifecycleFailed {
+                hook: "teardown",
+                error,
+            })
+            .inspect_err(|err| crate::error!(target: LOG_TARGET, "{err}"))
+    }
+}
+
+/// Wait for Ctrl-C, or SIGTERM on Unix.
+async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM.");
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = sigterm.recv() => (),
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
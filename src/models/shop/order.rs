@@ -6,12 +6,13 @@ use std::sync::Arc;
 
 use super::Shop;
 use crate::{
-    helpers,
+    helpers::{self, capacity::TicketReservation, dynamodb::TicketRecord},
     models::{
         message,
         order::{Order, OrderSegment},
         Machine, Ticket,
     },
+    CoffeeShopError,
 };
 
 const LOG_TARGET: &str = "coffeeshop::models::shop::order";
@@ -37,15 +38,135 @@ where
         self.orders.get(ticket).await
     }
 
+    /// Count the number of [`Order`]s in the shop that are not yet fulfilled.
+    ///
+    /// # Cost
+    ///
+    /// This command is `O(n)` over all the orders in the shop, including the fulfilled
+    /// ones that had not been cleaned up yet.
+    pub async fn outstanding_orders(&self) -> usize {
+        self.orders
+            .iter()
+            .await
+            .filter(|segment| !segment.value().is_fulfilled())
+            .count()
+    }
+
+    /// Count the number of [`Order`]s in the shop that are fulfilled, but not yet
+    /// cleaned up.
+    ///
+    /// # Cost
+    ///
+    /// This command is `O(n)` over all the orders in the shop.
+    pub async fn fulfilled_orders(&self) -> usize {
+        self.orders
+            .iter()
+            .await
+            .filter(|segment| segment.value().is_fulfilled())
+            .count()
+    }
+
+    /// Get the average [processing time](Order::processing_time) of the fulfilled
+    /// [`Order`]s that are still in the shop.
+    ///
+    /// Returns [`None`] if no orders had been fulfilled recently.
+    pub async fn average_processing_time(&self) -> Option<tokio::time::Duration> {
+        let (count, total) = self
+            .orders
+            .iter()
+            .await
+            .filter_map(|segment| segment.value().processing_time())
+            .fold(
+                (0_u32, tokio::time::Duration::ZERO),
+                |(count, total), time| (count + 1, total + time),
+            );
+
+        (count > 0).then(|| total / count)
+    }
+
+    /// Release the slots reserved by the [`Order`]s in the shop that had not been
+    /// fulfilled within `max_age`, returning the number of slots released.
+    ///
+    /// Unfulfilled orders are never purged from the shop, so an order whose ticket is
+    /// lost would otherwise hold its slot of
+    /// [`Config::max_tickets`](crate::cli::Config::max_tickets) forever.
+    ///
+    /// # Cost
+    ///
+    /// This command is `O(n)` over all the orders in the shop.
+    pub async fn release_abandoned_orders(&self, max_age: tokio::time::Duration) -> usize {
+        self.orders
+            .iter()
+            .await
+            .filter(|segment| {
+                let order = segment.value();
+                order.is_abandoned(max_age) && order.release_reservation()
+            })
+            .inspect(|segment| {
+                crate::warn!(
+                    target: LOG_TARGET,
+                    "Order for ticket {} had not been fulfilled in {:?}; releasing its slot.",
+                    segment.key(),
+                    max_age,
+                )
+            })
+            .count()
+    }
+
+    /// Periodically [release the abandoned orders](Shop::release_abandoned_orders).
+    ///
+    /// An order is abandoned once it is older than the
+    /// [result TTL](crate::cli::Config::result_ttl), by when its pending record would
+    /// have expired from DynamoDB anyway.
+    ///
+    /// This function will loop until the `shutdown_signal` is triggered.
+    pub async fn periodically_release_abandoned_orders(
+        &self,
+        interval: tokio::time::Duration,
+        shutdown_signal: Arc<tokio::sync::Notify>,
+    ) -> Result<(), CoffeeShopError> {
+        let max_age = self.config.dynamodb_ttl();
+
+        tokio::select! {
+            _ = async {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    self.release_abandoned_orders(max_age).await;
+                }
+            } => Ok(()),
+            _ = shutdown_signal.notified() => {
+                crate::warn!(target: LOG_TARGET, "A 3rd party had requested shutdown; stop releasing abandoned orders.");
+                Ok(())
+            },
+        }
+    }
+
     /// Spawn a [`Order`] order for a given [`Ticket`] in the shop.
     ///
-    /// Get the ticket if it exists, otherwise create a new one
+    /// Get the ticket if it exists, otherwise create a new one with the given `owner`
     /// before returning the [`Arc`] reference to the [`Order`].
-    pub async fn spawn_order(&self, ticket: Ticket) -> Arc<OrderSegment> {
+    ///
+    /// The `reservation` of the [`Waiter`](crate::models::Waiter) that created the
+    /// ticket, if any, is held by the new [`Order`] until it is fulfilled; it is
+    /// released straight away if the order already exists.
+    pub async fn spawn_order(
+        &self,
+        ticket: Ticket,
+        owner: Option<String>,
+        reservation: Option<TicketReservation>,
+    ) -> Arc<OrderSegment> {
         #[cfg(feature = "debug")]
         let start_time = tokio::time::Instant::now();
 
-        let result = self.orders.insert(ticket.clone(), Order::new(ticket)).await;
+        let result = self
+            .orders
+            .insert(
+                ticket.clone(),
+                Order::new(ticket)
+                    .with_owner(owner)
+                    .with_reservation(reservation),
+            )
+            .await;
 
         crate::debug!(
             target: LOG_TARGET,
@@ -64,4 +185,97 @@ where
             ),
         }
     }
+
+    /// Recover an [`Order`] for a [`Ticket`] that was not spawned by this shop.
+    ///
+    /// Behind a load balancer, an asynchronous ticket is typically retrieved from
+    /// a different [`Shop`] than the one that created it. The DynamoDB table is
+    /// consulted to find out if the ticket is known to the cluster:
+    ///
+    /// - If a result is already available, the [`Order`] is spawned and completed
+    ///   straight away.
+    /// - If the ticket is pending, the [`Order`] is spawned, and will be completed
+    ///   by either the multicast announcement or the periodic DynamoDB check.
+    /// - Otherwise, the ticket is unknown or had expired, and a
+    ///   [`CoffeeShopError::TicketNotFound`] is returned.
+    pub async fn recover_order(
+        &self,
+        ticket: &Ticket,
+    ) -> Result<Arc<OrderSegment>, CoffeeShopError> {
+        self.recover_orders(std::slice::from_ref(ticket))
+            .await?
+            .pop()
+            .expect("Exactly one result should be returned for one ticket.")
+    }
+
+    /// Recover the [`Order`]s for multiple [`Ticket`]s, spawning them if necessary.
+    ///
+    /// See [`Shop::recover_order`] for details. Tickets that are already in this shop
+    /// do not require a lookup; the rest are looked up in DynamoDB in batches.
+    ///
+    /// The outer [`Result`] reports any failures looking up DynamoDB, while the inner
+    /// [`Result`]s are in the same order as the given tickets.
+    pub async fn recover_orders(
+        &self,
+        tickets: &[Ticket],
+    ) -> Result<Vec<Result<Arc<OrderSegment>, CoffeeShopError>>, CoffeeShopError> {
+        let mut orders = Vec::with_capacity(tickets.len());
+        for ticket in tickets {
+            orders.push(self.get_order(ticket).await);
+        }
+
+        let unknown_tickets = tickets
+            .iter()
+            .zip(orders.iter())
+            .filter_map(|(ticket, order)| order.is_none().then_some(ticket))
+            .collect::<Vec<_>>();
+
+        let records: hashbrown::HashMap<Ticket, (TicketRecord, Option<String>)> =
+            if unknown_tickets.is_empty() {
+                hashbrown::HashMap::new()
+            } else {
+                helpers::dynamodb::get_ticket_records_by_tickets(self, unknown_tickets.into_iter())
+                    .await?
+                    .into_iter()
+                    .map(|(ticket, record, owner)| (ticket, (record, owner)))
+                    .collect()
+            };
+
+        let mut results = Vec::with_capacity(tickets.len());
+
+        for (ticket, order) in tickets.iter().zip(orders) {
+            if let Some(order) = order {
+                results.push(Ok(order));
+                continue;
+            }
+
+            let Some((record, owner)) = records.get(ticket) else {
+                results.push(Err(CoffeeShopError::TicketNotFound(ticket.clone())));
+                continue;
+            };
+
+            crate::info!(
+                target: LOG_TARGET,
+                "Recovered ticket {ticket} from DynamoDB as {record:?}.",
+            );
+
+            // Recovered orders are only retrieved here, so they do not take up capacity.
+            let order = self.spawn_order(ticket.clone(), owner.clone(), None).await;
+
+            if let TicketRecord::Fulfilled { success } = record {
+                match order.value().complete(*success) {
+                    // Another party could have completed the order in the meantime.
+                    Ok(()) | Err(CoffeeShopError::ResultAlreadySet) => (),
+                    Err(err) => {
+                        results.push(Err(err));
+                        continue;
+                    }
+                }
+            }
+
+            results.push(Ok(order));
+        }
+
+        Ok(results)
+    }
 }
@@ -198,7 +198,7 @@ mod announcer {
             let message_received = message_received.clone();
 
             crate::info!(target: LOG_TARGET, "Spawning order for ticket {}...", ticket);
-            let order = shop.spawn_order(ticket.clone()).await;
+            let order = shop.spawn_order(ticket.clone(), None, None).await;
 
             crate::info!(target: LOG_TARGET, "Waiting for ticket to be finished...");
 
@@ -238,6 +238,49 @@ mod announcer {
     }
 }
 
+#[cfg(feature = "test_on_aws")]
+mod capacity {
+    use super::*;
+
+    #[tokio::test]
+    async fn abandoned_order() {
+        let shop = new_shop().await;
+        let capacity = &shop.waiter.capacity;
+        let max_tickets = 2;
+
+        let mut reservations = capacity.reserve_up_to(max_tickets, max_tickets);
+        let abandoned = shop
+            .spawn_order(get_random_ticket(), None, reservations.pop())
+            .await;
+        let fulfilled = shop
+            .spawn_order(get_random_ticket(), None, reservations.pop())
+            .await;
+        fulfilled
+            .value()
+            .complete(true)
+            .expect("Failed to complete the order.");
+
+        // The order that never completes keeps its slot until it is abandoned.
+        assert_eq!(capacity.in_flight(), 1);
+        assert_eq!(shop.release_abandoned_orders(STALE_AGE).await, 0);
+        assert_eq!(capacity.in_flight(), 1);
+        assert_eq!(capacity.reserve_up_to(max_tickets, max_tickets).len(), 1);
+
+        assert_eq!(
+            shop.release_abandoned_orders(tokio::time::Duration::ZERO)
+                .await,
+            1
+        );
+        assert_eq!(capacity.in_flight(), 0);
+        assert_eq!(
+            shop.release_abandoned_orders(tokio::time::Duration::ZERO)
+                .await,
+            0
+        );
+        assert!(!abandoned.value().is_fulfilled());
+    }
+}
+
 /// Test that opens the shop.
 mod shop {
     use super::*;
@@ -63,19 +63,14 @@ pub fn get_random_ticket() -> Ticket {
     uuid::Uuid::new_v4().to_string()
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub enum TestStatus {
+    #[default]
     Eat,
     Sleep,
     Work,
 }
 
-impl Default for TestStatus {
-    fn default() -> Self {
-        Self::Eat
-    }
-}
-
 #[serde_with::serde_as]
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct TestQuery {
@@ -130,8 +125,9 @@ impl Machine<TestQuery, TestPayload, TestResult> for TestMachine {
         &self,
         query: &TestQuery,
         input: Option<&TestPayload>,
+        principal: Option<&message::Principal>,
     ) -> message::MachineResult<TestResult> {
-        self.validate(query, input).await?;
+        self.validate(query, input, principal).await?;
 
         let payload = input.unwrap();
 
@@ -3,33 +3,57 @@
 //! For synchronous requests, the waiter will also asynchronously await a [`Notify`](tokio::sync::Notify)
 //! event from the multicast channel and report back to the client when the request had been processed.
 
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc, Weak,
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
 };
 
 use axum::extract::{
     rejection::{JsonRejection, QueryRejection},
-    Json, Query,
+    ConnectInfo, Extension, Json, Path, Query,
 };
 use axum::{
-    http::{header, StatusCode},
+    http::{header, HeaderMap, StatusCode, Uri},
     response::IntoResponse,
 };
+use futures::StreamExt;
 use tokio::sync::Notify;
 use tower_http::{timeout::TimeoutLayer, trace::TraceLayer};
+use tracing::Instrument;
 
 use super::{
     message::{self, QueryType},
-    Machine, OrderSegment, Shop,
+    Dependency, Machine, Order, OrderSegment, Shop,
+};
+use crate::{
+    errors::handling::IntoCoffeeShopError,
+    helpers::{
+        self,
+        telemetry::{context_span, TraceContext},
+    },
+    logger::LogContext,
+    CoffeeShopError,
 };
-use crate::{errors::handling::IntoCoffeeShopError, helpers, CoffeeShopError};
-
-#[cfg(doc)]
-use super::Order;
 
 const LOG_TARGET: &str = "coffeeshop::models::waiter";
 
+/// The maximum number of items accepted in a single batch request.
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// The time to wait for a batch of tickets if the query does not set a timeout, so that
+/// a ticket that is never announced does not hold the connection open indefinitely.
+const DEFAULT_BATCH_RETRIEVE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(60);
+
+/// The minimum `Retry-After` duration reported when too many tickets are outstanding.
+/// This is also used if there are no recent processing times to estimate from.
+const DEFAULT_RETRY_AFTER: tokio::time::Duration = tokio::time::Duration::from_secs(1);
+
+/// The maximum `Retry-After` duration reported when too many tickets are outstanding.
+const MAX_RETRY_AFTER: tokio::time::Duration = tokio::time::Duration::from_secs(300);
+
 /// A [`Waiter`] instance that acts as an async REST API host.
 #[derive(Debug)]
 pub struct Waiter<Q, I, O, F>
@@ -49,6 +73,35 @@ where
     /// Internally, this is done by [`create_order`](Self::create_order).
     pub request_count: Arc<AtomicUsize>,
     pub start_time: tokio::time::Instant,
+
+    /// The latest sample of the approximate number of tickets in the AWS SQS queue.
+    ///
+    /// This is only sampled if [`Config::max_queue_depth`](crate::cli::Config::max_queue_depth)
+    /// is set; see [`periodically_sample_queue_depth`](Self::periodically_sample_queue_depth).
+    pub queue_depth: std::sync::RwLock<Option<usize>>,
+
+    /// The per-client rate limit buckets kept by this waiter.
+    ///
+    /// These are only used if [`Config::rate_limit`](crate::cli::Config::rate_limit) is set.
+    pub rate_limiter: helpers::rate_limit::RateLimiter,
+
+    /// The signer of the ticket tokens handed out to clients, if enabled.
+    ///
+    /// If set, clients never see the raw AWS SQS message IDs; see
+    /// [`Config::ticket_token_secret_env`](crate::cli::Config::ticket_token_secret_env).
+    pub ticket_signer: Option<helpers::ticket_token::TicketSigner>,
+
+    /// The tickets created by this waiter that are not yet fulfilled, bounded by
+    /// [`Config::max_tickets`](crate::cli::Config::max_tickets).
+    ///
+    /// Orders recovered for retrieval only are not counted.
+    pub capacity: helpers::capacity::TicketCapacity,
+
+    /// Whether the shop is closing, in which case no new orders are accepted.
+    ///
+    /// The waiter keeps serving the outstanding orders until the baristas have
+    /// drained; see [`Shop::open`].
+    pub closing: AtomicBool,
 }
 
 impl<Q, I, O, F> Waiter<Q, I, O, F>
@@ -64,6 +117,39 @@ where
             shop,
             request_count: Arc::new(AtomicUsize::new(0)),
             start_time: tokio::time::Instant::now(),
+            queue_depth: std::sync::RwLock::new(None),
+            rate_limiter: helpers::rate_limit::RateLimiter::new(),
+            ticket_signer: None,
+            capacity: helpers::capacity::TicketCapacity::new(),
+            closing: AtomicBool::new(false),
+        }
+    }
+
+    /// Builder pattern - sign the tickets handed out to clients.
+    pub fn with_ticket_signer(
+        mut self,
+        ticket_signer: Option<helpers::ticket_token::TicketSigner>,
+    ) -> Self {
+        self.ticket_signer = ticket_signer;
+        self
+    }
+
+    /// Convert a [`Ticket`](message::Ticket) into the one handed out to clients.
+    pub fn issue_ticket(&self, ticket: message::Ticket) -> message::Ticket {
+        match &self.ticket_signer {
+            Some(signer) => signer.sign(&ticket),
+            None => ticket,
+        }
+    }
+
+    /// Convert a ticket given by a client back into the [`Ticket`](message::Ticket).
+    ///
+    /// This does not consult DynamoDB; any forged, tampered or expired tokens are
+    /// rejected straight away.
+    pub fn redeem_ticket(&self, ticket: &str) -> Result<message::Ticket, CoffeeShopError> {
+        match &self.ticket_signer {
+            Some(signer) => signer.verify(ticket),
+            None => Ok(ticket.to_owned()),
         }
     }
 
@@ -72,31 +158,138 @@ where
         self.shop.upgrade().expect("Shop has been dropped; this should not be possible in normal use. Please report this to the maintainer.")
     }
 
-    /// `GET` Handler for getting the status of the waiter.
-    pub async fn status(&self) -> impl IntoResponse {
+    /// `GET` Handler for getting the status of the shop.
+    ///
+    /// This reports the baristas, the orders, the queue depth, the announcer and the
+    /// operational settings of the shop.
+    ///
+    /// The raw tickets in flight and the AWS resources of the shop are withheld unless
+    /// [`Config::reveals_status_details`](crate::cli::Config::reveals_status_details)
+    /// allows them for the `principal`.
+    pub async fn status(&self, principal: Option<&message::Principal>) -> impl IntoResponse {
+        let shop = self.shop();
+
+        let status = message::StatusResponse {
+            metadata: message::ResponseMetadata::new(&self.start_time),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            name: shop.name.clone(),
+            sqs_queue: Some(shop.sqs_queue.clone()),
+            dynamodb_table: Some(shop.dynamodb_table.clone()),
+            request_count: self.request_count.load(Ordering::Relaxed),
+            outstanding_tickets: self.capacity.in_flight(),
+            fulfilled_tickets: shop.fulfilled_orders().await,
+            max_tickets: shop.config.max_tickets,
+            queue_depth: self.queue_depth(),
+            baristas: shop
+                .baristas
+                .iter()
+                .map(|barista| barista.status())
+                .collect(),
+            announcer: shop.announcer.status(),
+            config: message::ConfigStatus::from(&shop.config),
+        };
+
         (
             StatusCode::OK,
             [
                 (header::CONTENT_TYPE, "application/json"),
                 (header::CACHE_CONTROL, "no-store"),
             ],
-            Json(message::StatusResponse {
-                metadata: message::ResponseMetadata::new(&self.start_time),
-                request_count: self.request_count.load(Ordering::Relaxed),
+            Json(if shop.config.reveals_status_details(principal) {
+                status
+            } else {
+                status.without_details()
             }),
         )
     }
 
+    /// `GET` Handler for the metrics in the Prometheus text format.
+    ///
+    /// The gauges of the orders in flight and the queue depth are updated on each
+    /// scrape.
+    pub async fn metrics(&self) -> Result<impl IntoResponse, CoffeeShopError> {
+        let shop = self.shop();
+        let metrics = helpers::metrics::metrics();
+
+        metrics
+            .orders_in_flight
+            .set(shop.outstanding_orders().await as i64);
+        if let Some(depth) = self.queue_depth() {
+            metrics.queue_depth.set(depth as i64);
+        }
+
+        Ok((
+            [
+                (header::CONTENT_TYPE, "text/plain; version=0.0.4"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            metrics.encode()?,
+        ))
+    }
+
+    /// `GET` Handler for the liveness probe.
+    ///
+    /// This always succeeds as long as the waiter is responding.
+    pub async fn live(&self) -> impl IntoResponse {
+        (
+            StatusCode::OK,
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(message::HealthResponse::new(
+                &self.start_time,
+                Default::default(),
+            )),
+        )
+    }
+
+    /// `GET` Handler for the readiness probe.
+    ///
+    /// This fails with `503 Service Unavailable` if any of the
+    /// [readiness checks](Shop::check_readiness) fail, including while the shop is
+    /// draining on shutdown.
+    pub async fn ready(&self) -> impl IntoResponse {
+        let response =
+            message::HealthResponse::new(&self.start_time, self.shop().check_readiness().await);
+
+        if !response.healthy {
+            crate::warn!(
+                target: LOG_TARGET,
+                "The shop is not ready: {checks:?}",
+                checks = response.checks,
+            );
+        }
+
+        (
+            if response.healthy {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            },
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(response),
+        )
+    }
+
     /// `POST` Handler for incoming requests.
+    ///
+    /// The `principal` is the authenticated identity of the client, if authentication
+    /// is enabled.
     pub async fn request(
         &self,
         Query(params): Query<Q>,
         Json(payload): Json<I>,
+        principal: Option<message::Principal>,
     ) -> impl IntoResponse {
-        let timeout = params.get_timeout();
+        self.request_order(
+            message::CombinedInput::new(params, Some(payload)).with_principal(principal),
+        )
+        .await
+    }
 
-        self.create_and_retrieve_order(message::CombinedInput::new(params, Some(payload)), timeout)
-            .await
+    /// An internal method to place an order, and wait for its result.
+    async fn request_order(&self, input: message::CombinedInput<Q, I>) -> impl IntoResponse {
+        let timeout = input.query.get_timeout();
+
+        self.create_and_retrieve_order(input, timeout).await
     }
 
     /// `POST` Handler for asynchronous requests.
@@ -107,71 +300,898 @@ where
         &self,
         Query(params): Query<Q>,
         Json(payload): Json<I>,
+        principal: Option<message::Principal>,
     ) -> impl IntoResponse {
-        self.create_order(message::CombinedInput {
-            query: params,
-            input: Some(payload),
-        })
+        self.async_request_order(
+            message::CombinedInput::new(params, Some(payload)).with_principal(principal),
+        )
         .await
-        .map(|(ticket, _)| message::TicketResponse {
-            ticket,
-            metadata: message::ResponseMetadata::new(&self.start_time),
-        })
+    }
+
+    /// An internal method to place an order, and return its ticket immediately.
+    async fn async_request_order(&self, input: message::CombinedInput<Q, I>) -> impl IntoResponse {
+        self.create_order(input)
+            .await
+            .map(|(ticket, _)| message::TicketResponse {
+                ticket: self.issue_ticket(ticket),
+                metadata: message::ResponseMetadata::new(&self.start_time),
+            })
     }
 
     /// A `GET` request to fetch results from a previously processed request.
+    ///
+    /// If authentication is enabled, only the `principal` that created the ticket, or
+    /// an administrator, can fetch its results.
     pub async fn async_retrieve(
         &self,
         Query(params): Query<message::TicketQuery>,
+        principal: Option<message::Principal>,
     ) -> impl IntoResponse {
         let timeout = params.get_timeout();
 
-        self.retrieve_order_with_timeout(params.ticket, timeout)
+        self.retrieve_order_with_timeout(params.ticket, timeout, principal.as_ref())
             .await
     }
 
-    /// An internal method to create a new ticket on the AWS SQS queue,
-    /// then return the [`Order`] instance to await the result.
-    pub async fn create_order(
+    /// A `GET` request to check the state of a ticket, without waiting for or fetching
+    /// its result.
+    ///
+    /// If authentication is enabled, only the `principal` that created the ticket, or
+    /// an administrator, can check its state.
+    pub async fn ticket_status(
         &self,
-        input: message::CombinedInput<Q, I>,
-    ) -> Result<(message::Ticket, Arc<OrderSegment>), CoffeeShopError> {
+        Query(params): Query<message::TicketQuery>,
+        principal: Option<message::Principal>,
+    ) -> Result<message::TicketStatusResponse, CoffeeShopError> {
+        let shop = self.shop();
+
+        let issued_ticket = params.ticket;
+        let ticket = self.redeem_ticket(&issued_ticket)?;
+
+        let order = match shop.get_order(&ticket).await {
+            Some(order) => order,
+            None => shop
+                .recover_order(&ticket)
+                .await
+                .map_err(|err| Self::conceal_ticket(err, &issued_ticket))?,
+        };
+
+        Self::authorize_order(&issued_ticket, order.value(), principal.as_ref())?;
+
+        let state = match order.value().result() {
+            None => message::TicketState::Pending,
+            Some((_, true)) => message::TicketState::Succeeded,
+            Some((_, false)) => message::TicketState::Failed,
+        };
+
+        Ok(message::TicketStatusResponse {
+            metadata: message::ResponseMetadata::new(&self.start_time),
+            ticket: issued_ticket,
+            state,
+        })
+    }
+
+    /// `POST` Handler for batch requests.
+    ///
+    /// Each item is validated and enqueued independently; the errors are reported
+    /// per item. If the shared query is synchronous, the results of all items are
+    /// awaited under the timeout of the shared query, or 60 seconds if it does not
+    /// set one; otherwise only the tickets are returned.
+    ///
+    /// All the items are traced as part of the same `batch_request` span, which is a
+    /// child of the `trace_context` of the caller, if any.
+    pub async fn batch_request(
+        &self,
+        shared_query: Option<Q>,
+        items: Vec<message::BatchRequestItem<Q, I>>,
+        principal: Option<message::Principal>,
+        trace_context: Option<TraceContext>,
+    ) -> Result<message::BatchResponse<O>, CoffeeShopError> {
+        if items.len() > MAX_BATCH_SIZE {
+            return Err(CoffeeShopError::InvalidPayload {
+                kind: "BatchSizeExceeded",
+                message: format!(
+                    "A batch can contain at most {MAX_BATCH_SIZE} items, found {count}.",
+                    count = items.len(),
+                ),
+            });
+        }
+
+        let wait = shared_query.as_ref().is_some_and(|query| !query.is_async());
+        let inputs_count = items.len();
+        let timeout = shared_query
+            .as_ref()
+            .and_then(|query| query.get_timeout())
+            .unwrap_or(DEFAULT_BATCH_RETRIEVE_TIMEOUT);
+
+        let context = TraceContext::child_of(trace_context.as_ref());
+
+        let inputs = items
+            .into_iter()
+            .map(|item| {
+                item.into_combined_input(shared_query.as_ref())
+                    .map(|input| {
+                        input
+                            .with_principal(principal.clone())
+                            .with_trace_context(Some(context))
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let orders = self
+            .create_orders(inputs)
+            .instrument(context_span!(
+                "batch_request",
+                context,
+                count = inputs_count
+            ))
+            .await
+            .into_iter()
+            .map(|order| order.map(|(ticket, order)| (self.issue_ticket(ticket), order)));
+
+        let results = if wait {
+            let shop = self.shop();
+            let deadline = tokio::time::Instant::now() + timeout;
+
+            futures::future::join_all(orders.map(|order| {
+                let shop = &shop;
+
+                async move {
+                    let (ticket, order) = match order {
+                        Ok(order) => order,
+                        Err(err) => {
+                            return message::BatchItemResponse::new_error(
+                                None,
+                                err.as_error_schema(),
+                            )
+                        }
+                    };
+
+                    let result = tokio::time::timeout_at(
+                        deadline,
+                        order.value().wait_and_fetch_when_complete::<O, _>(shop),
+                    )
+                    .await
+                    .unwrap_or(Err(CoffeeShopError::RetrieveTimeout(timeout)));
+
+                    match result {
+                        Ok(Ok(output)) => message::BatchItemResponse::new_output(ticket, output),
+                        Ok(Err(schema)) => {
+                            message::BatchItemResponse::new_error(Some(ticket), schema)
+                        }
+                        Err(err) => message::BatchItemResponse::new_error(
+                            Some(ticket),
+                            err.as_error_schema(),
+                        ),
+                    }
+                }
+            }))
+            .await
+        } else {
+            orders
+                .map(|order| match order {
+                    Ok((ticket, _)) => message::BatchItemResponse::new_ticket(ticket),
+                    Err(err) => message::BatchItemResponse::new_error(None, err.as_error_schema()),
+                })
+                .collect()
+        };
+
+        Ok(message::BatchResponse::new(
+            &self.start_time,
+            results,
+            if wait {
+                StatusCode::OK
+            } else {
+                StatusCode::ACCEPTED
+            },
+        ))
+    }
+
+    /// `POST` Handler for retrieving the results of multiple tickets.
+    ///
+    /// Duplicated tickets are only reported once. Tickets that are still pending at
+    /// the end of the wait are returned without an output or an error. If the query
+    /// does not set a timeout, the wait is limited to 60 seconds.
+    pub async fn batch_retrieve(
+        &self,
+        query: message::BatchTicketQuery,
+        principal: Option<message::Principal>,
+    ) -> Result<message::BatchResponse<O>, CoffeeShopError> {
+        if query.tickets.len() > MAX_BATCH_SIZE {
+            return Err(CoffeeShopError::InvalidPayload {
+                kind: "BatchSizeExceeded",
+                message: format!(
+                    "A batch can contain at most {MAX_BATCH_SIZE} tickets, found {count}.",
+                    count = query.tickets.len(),
+                ),
+            });
+        }
+
+        let shop = self.shop();
+        let timeout = query
+            .get_timeout()
+            .unwrap_or(DEFAULT_BATCH_RETRIEVE_TIMEOUT);
+
+        let mut seen = hashbrown::HashSet::with_capacity(query.tickets.len());
+        let tickets = query
+            .tickets
+            .into_iter()
+            .filter(|ticket| seen.insert(ticket.clone()))
+            .collect::<Vec<_>>();
+
+        // Any invalid ticket tokens are rejected without looking up DynamoDB.
+        let redeemed = tickets
+            .iter()
+            .map(|ticket| self.redeem_ticket(ticket))
+            .collect::<Vec<_>>();
+        let redeemed_tickets = redeemed
+            .iter()
+            .filter_map(|ticket| ticket.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+
+        let mut recovered = shop.recover_orders(&redeemed_tickets).await?.into_iter();
+
+        let orders = tickets
+            .iter()
+            .zip(redeemed)
+            .map(|(issued_ticket, ticket)| {
+                ticket.and_then(|ticket| {
+                    recovered
+                        .next()
+                        .expect("Exactly one order should be recovered for each ticket.")
+                        .and_then(|order| {
+                            Self::authorize_order(issued_ticket, order.value(), principal.as_ref())
+                                .map(|_| (ticket, order))
+                        })
+                        .map_err(|err| Self::conceal_ticket(err, issued_ticket))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if query.mode != message::BatchRetrieveMode::AvailableNow {
+            let mut pending = orders
+                .iter()
+                .filter_map(|order| order.as_ref().ok())
+                .map(|(_, order)| order.value().wait_until_complete())
+                .collect::<futures::stream::FuturesUnordered<_>>();
+
+            let wait_for = match query.mode {
+                message::BatchRetrieveMode::Any => pending.len().min(1),
+                _ => pending.len(),
+            };
+
+            let wait = async {
+                for _ in 0..wait_for {
+                    pending.next().await;
+                }
+            };
+
+            if tokio::time::timeout(timeout, wait).await.is_err() {
+                crate::info!(
+                    target: LOG_TARGET,
+                    "Timeout of {timeout:?} reached while waiting for a batch of {count} tickets; returning what is available.",
+                    count = tickets.len(),
+                );
+            }
+        }
+
+        let fulfilled_tickets = orders
+            .iter()
+            .filter_map(|order| {
+                order
+                    .as_ref()
+                    .ok()
+                    .filter(|(_, order)| order.value().is_fulfilled())
+                    .map(|(ticket, _)| ticket)
+            })
+            .collect::<Vec<_>>();
+
+        let mut outputs: hashbrown::HashMap<message::Ticket, message::ProcessResultExport<O>> =
+            helpers::dynamodb::get_process_results_by_tickets(&shop, fulfilled_tickets.into_iter())
+                .await?
+                .into_iter()
+                .collect();
+
+        let results = tickets
+            .into_iter()
+            .zip(orders)
+            .map(|(issued_ticket, order)| match order {
+                Err(err) => message::BatchItemResponse::new_error(
+                    Some(issued_ticket),
+                    err.as_error_schema(),
+                ),
+                Ok((ticket, _)) => match outputs.remove(&ticket) {
+                    Some(Ok(output)) => {
+                        message::BatchItemResponse::new_output(issued_ticket, output)
+                    }
+                    Some(Err(schema)) => {
+                        message::BatchItemResponse::new_error(Some(issued_ticket), schema)
+                    }
+                    None => message::BatchItemResponse::new_pending(issued_ticket),
+                },
+            })
+            .collect();
+
+        Ok(message::BatchResponse::new(
+            &self.start_time,
+            results,
+            StatusCode::OK,
+        ))
+    }
+
+    /// An internal method to reserve capacity for `count` tickets, without exceeding
+    /// [`Config::max_tickets`](crate::cli::Config::max_tickets) tickets in flight.
+    ///
+    /// The reservations are held by the [`Order`]s once the tickets are created, and
+    /// released when they are fulfilled; or released when dropped if the tickets could
+    /// not be created.
+    ///
+    /// If `count` tickets cannot all be accepted, the reservations for the tickets that
+    /// can be accepted are returned in the [`Err`] variant, alongside the
+    /// [`CoffeeShopError::TooManyTickets`] to report for the rest.
+    async fn reserve_capacity(
+        &self,
+        shop: &Shop<Q, I, O, F>,
+        count: usize,
+    ) -> Result<
+        Vec<helpers::capacity::TicketReservation>,
+        (Vec<helpers::capacity::TicketReservation>, CoffeeShopError),
+    > {
+        let max_tickets = shop.config.max_tickets;
+        let reservations = self.capacity.reserve_up_to(count, max_tickets);
+
+        if reservations.len() == count {
+            return Ok(reservations);
+        }
+
+        let available = reservations.len();
+        let outstanding = self.capacity.in_flight().saturating_sub(available);
+
+        // Estimate how long it would take for the baristas to work through the
+        // excess tickets, assuming the recent processing times are representative.
+        let excess = outstanding + count - max_tickets;
+        let serving = shop.baristas.iter().filter(|barista| barista.is_serving());
+        let slots = serving.count() * usize::from(shop.config.barista_concurrency);
+        let rounds = excess.div_ceil(slots.max(1)) as u32;
+        let retry_after = shop
+            .average_processing_time()
+            .await
+            .unwrap_or(DEFAULT_RETRY_AFTER)
+            .saturating_mul(rounds)
+            .clamp(DEFAULT_RETRY_AFTER, MAX_RETRY_AFTER);
+
+        crate::warn!(
+            target: LOG_TARGET,
+            "Rejecting {rejected} tickets with {outstanding} outstanding out of {max_tickets}; retry after {retry_after:?}.",
+            rejected = count - available,
+        );
+
+        Err((
+            reservations,
+            CoffeeShopError::TooManyTickets {
+                outstanding,
+                max_tickets,
+                retry_after,
+            },
+        ))
+    }
+
+    /// An internal method to identify the client of a request for rate limiting,
+    /// according to [`Config::rate_limit_key`](crate::cli::Config::rate_limit_key).
+    ///
+    /// If the configured key is not present in the request, the client IP address is
+    /// used instead.
+    fn rate_limit_key(
+        &self,
+        shop: &Shop<Q, I, O, F>,
+        headers: &HeaderMap,
+        peer: Option<SocketAddr>,
+        query: Option<&Q>,
+    ) -> String {
+        let header_value = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let key = match shop.config.rate_limit_key {
+            helpers::rate_limit::RateLimitKey::ClientIp => None,
+            helpers::rate_limit::RateLimitKey::ForwardedFor => header_value("x-forwarded-for")
+                .and_then(|value| value.split(',').next())
+                .map(|addr| format!("ip:{}", addr.trim())),
+            helpers::rate_limit::RateLimitKey::ApiKey => {
+                header_value(&shop.config.rate_limit_header).map(|key| format!("api_key:{key}"))
+            }
+            helpers::rate_limit::RateLimitKey::Query => query
+                .and_then(QueryType::rate_limit_key)
+                .map(|key| format!("query:{key}")),
+        };
+
+        key.unwrap_or_else(|| {
+            format!(
+                "ip:{}",
+                peer.map_or_else(|| "unknown".to_owned(), |peer| peer.ip().to_string())
+            )
+        })
+    }
+
+    /// Check the rate limit of the client of a request, taking `cost` tokens from
+    /// its bucket.
+    ///
+    /// Returns [`None`] if rate limiting is not enabled; otherwise the
+    /// [`RateLimitDecision`](helpers::rate_limit::RateLimitDecision) to report in the
+    /// response headers. Limited clients get a [`CoffeeShopError::RateLimited`], while
+    /// requests costing more than the bucket can hold get a
+    /// [`CoffeeShopError::RateLimitCostExceeded`].
+    pub async fn check_rate_limit(
+        &self,
+        headers: &HeaderMap,
+        peer: Option<SocketAddr>,
+        query: Option<&Q>,
+        cost: u32,
+    ) -> Result<Option<helpers::rate_limit::RateLimitDecision>, CoffeeShopError> {
         let shop = self.shop();
 
+        let Some(settings) = shop.config.rate_limit_settings() else {
+            return Ok(None);
+        };
+
+        let key = self.rate_limit_key(&shop, headers, peer, query);
+        let decision = self
+            .rate_limiter
+            .acquire(&*shop, &settings, &key, cost)
+            .await;
+
+        if decision.allowed {
+            Ok(Some(decision))
+        } else if let Some(retry_after) = decision.retry_after {
+            crate::warn!(
+                target: LOG_TARGET,
+                "Rate limited a client; retry after {retry_after:?}.",
+            );
+
+            Err(CoffeeShopError::RateLimited(decision))
+        } else {
+            crate::warn!(
+                target: LOG_TARGET,
+                "Rejected a request costing {cost} tokens, above the rate limit of {limit}.",
+                limit = decision.limit,
+            );
+
+            Err(CoffeeShopError::RateLimitCostExceeded {
+                cost,
+                limit: decision.limit,
+            })
+        }
+    }
+
+    /// Stop accepting new orders, as the shop is closing.
+    pub fn close(&self) {
+        if !self.closing.swap(true, Ordering::Relaxed) {
+            crate::warn!(
+                target: LOG_TARGET,
+                "The shop is closing; rejecting any new orders."
+            );
+        }
+    }
+
+    /// Check if the shop is closing.
+    pub fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::Relaxed)
+    }
+
+    /// An internal method to reject new orders once the shop is closing.
+    fn check_open(&self) -> Result<(), CoffeeShopError> {
+        if self.is_closing() {
+            Err(CoffeeShopError::ShopClosing)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the latest sample of the AWS SQS queue depth, if any.
+    pub fn queue_depth(&self) -> Option<usize> {
+        *self
+            .queue_depth
+            .read()
+            .expect("The queue depth lock is poisoned; this should not be possible.")
+    }
+
+    /// Sample the AWS SQS queue depth, and cache it for the admission control.
+    pub async fn sample_queue_depth(&self) -> Result<usize, CoffeeShopError> {
+        let shop = self.shop();
+        let depth = helpers::sqs::get_ticket_count(&*shop).await?;
+        shop.health.record(Dependency::Sqs);
+
+        *self
+            .queue_depth
+            .write()
+            .expect("The queue depth lock is poisoned; this should not be possible.") = Some(depth);
+
+        crate::trace!(target: LOG_TARGET, "Sampled queue depth: {depth}.");
+
+        Ok(depth)
+    }
+
+    /// Periodically sample the AWS SQS queue depth.
+    ///
+    /// This function returns immediately if
+    /// [`Config::max_queue_depth`](crate::cli::Config::max_queue_depth) is not set.
+    /// Otherwise, it will loop until the `shutdown_signal` is triggered. Failed samples
+    /// are logged, and the previous sample is kept.
+    pub async fn periodically_sample_queue_depth(
+        &self,
+        shutdown_signal: Arc<Notify>,
+    ) -> Result<(), CoffeeShopError> {
+        let interval = {
+            let shop = self.shop();
+
+            if shop.config.max_queue_depth.is_none() {
+                return Ok(());
+            }
+
+            shop.config.queue_depth_interval()
+        };
+
+        tokio::select! {
+            _ = async {
+                loop {
+                    if let Err(err) = self.sample_queue_depth().await {
+                        crate::warn!(
+                            target: LOG_TARGET,
+                            "Failed to sample the queue depth, keeping the previous sample: {}",
+                            err
+                        );
+                    }
+                    tokio::time::sleep(interval).await;
+                }
+            } => Ok(()),
+            _ = shutdown_signal.notified() => {
+                crate::warn!(target: LOG_TARGET, "A 3rd party had requested shutdown; stop sampling the queue depth.");
+                Ok(())
+            },
+        }
+    }
+
+    /// An internal method to shed new requests if the AWS SQS queue depth had exceeded
+    /// [`Config::max_queue_depth`](crate::cli::Config::max_queue_depth).
+    ///
+    /// Asynchronous requests are still accepted if
+    /// [`Config::queue_depth_accepts_async`](crate::cli::Config::queue_depth_accepts_async)
+    /// is set.
+    fn check_queue_depth(
+        &self,
+        shop: &Shop<Q, I, O, F>,
+        is_async: bool,
+    ) -> Result<(), CoffeeShopError> {
+        let Some(max_queue_depth) = shop.config.max_queue_depth else {
+            return Ok(());
+        };
+
+        if is_async && shop.config.queue_depth_accepts_async {
+            return Ok(());
+        }
+
+        match self.queue_depth() {
+            Some(depth) if depth > max_queue_depth => Err(CoffeeShopError::QueueBacklogExceeded {
+                depth,
+                max_queue_depth,
+                retry_after: shop.config.queue_depth_interval(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// An internal method to rate limit an order, then place it synchronously or
+    /// asynchronously depending on its query.
+    ///
+    /// All errors are pre-converted to responses, so that the typing is consistent.
+    async fn handle_order(
+        &self,
+        headers: &HeaderMap,
+        peer: Option<SocketAddr>,
+        input: message::CombinedInput<Q, I>,
+    ) -> axum::response::Response {
+        let rate_limit = match self
+            .check_rate_limit(headers, peer, Some(&input.query), 1)
+            .await
+        {
+            Ok(rate_limit) => rate_limit,
+            Err(err) => return err.into_response(),
+        };
+
+        let input = input.with_trace_context(TraceContext::from_headers(headers));
+
+        let mut response = if input.query.is_async() {
+            crate::info!(target: LOG_TARGET, "Received an asynchronous request.");
+            self.async_request_order(input).await.into_response()
+        } else {
+            crate::info!(target: LOG_TARGET, "Received a blocking request.");
+            self.request_order(input).await.into_response()
+        };
+
+        if let Some(rate_limit) = rate_limit {
+            rate_limit.apply_headers(&mut response);
+        }
+
+        response
+    }
+
+    /// An internal method to validate an input using the [`Machine`] before it
+    /// is sent to the AWS SQS queue.
+    async fn validate_order(
+        &self,
+        shop: &Shop<Q, I, O, F>,
+        input: &message::CombinedInput<Q, I>,
+    ) -> Result<(), CoffeeShopError> {
         // Validate the query prior to creating the ticket, to avoid unnecessary
         // processing of invalid requests.
-        shop.coffee_machine.validate(&input.query, input.input.as_ref()).await
+        shop.coffee_machine.validate(&input.query, input.input.as_ref(), input.principal.as_ref()).await
         .inspect_err(
             |err| crate::warn!(target: LOG_TARGET, "Validation failed, not pushing to SQS: {:#?}", err)
         )
-        .map_err(CoffeeShopError::ErrorSchema)?;
+        .map_err(CoffeeShopError::ErrorSchema)
+    }
+
+    /// An internal method to check if the `principal` can access an [`Order`].
+    ///
+    /// If authentication is disabled, there is no `principal`, and all orders are
+    /// accessible. Orders owned by a different principal are reported as
+    /// [`CoffeeShopError::TicketNotFound`], so as not to disclose that the ticket exists.
+    fn authorize_order(
+        ticket: &message::Ticket,
+        order: &Order,
+        principal: Option<&message::Principal>,
+    ) -> Result<(), CoffeeShopError> {
+        match principal {
+            Some(principal) if !principal.can_access(order.owner()) => {
+                Err(CoffeeShopError::TicketNotFound(ticket.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// An internal method to replace the [`Ticket`](message::Ticket) in an error with
+    /// the one issued to the client, so that the raw AWS SQS message ID is not exposed
+    /// if [ticket tokens](Self::ticket_signer) are enabled.
+    fn conceal_ticket(err: CoffeeShopError, issued_ticket: &message::Ticket) -> CoffeeShopError {
+        match err {
+            CoffeeShopError::TicketNotFound(_) => {
+                CoffeeShopError::TicketNotFound(issued_ticket.clone())
+            }
+            CoffeeShopError::ResultNotFound(_) => {
+                CoffeeShopError::ResultNotFound(issued_ticket.clone())
+            }
+            err => err,
+        }
+    }
+
+    /// An internal method to spawn the [`Order`] for a ticket that had just been
+    /// put onto the AWS SQS queue.
+    async fn place_order(
+        &self,
+        shop: &Shop<Q, I, O, F>,
+        ticket: message::Ticket,
+        is_async: bool,
+        owner: Option<String>,
+        reservation: helpers::capacity::TicketReservation,
+    ) -> (message::Ticket, Arc<OrderSegment>) {
+        // Asynchronous tickets are likely to be retrieved from a different shop;
+        // record the ticket so that other shops know it exists.
+        if is_async {
+            helpers::dynamodb::put_pending_ticket(shop, &ticket, owner.as_deref())
+                .await
+                .unwrap_or_else(|err| {
+                    crate::error!(
+                        target: LOG_TARGET,
+                        "Failed to record ticket {ticket} as pending, ignoring. It may not be retrievable from other shops: {err}",
+                    )
+                });
+        }
+
+        (
+            ticket.clone(),
+            shop.spawn_order(ticket, owner, Some(reservation)).await,
+        )
+    }
+
+    /// An internal method to create a new ticket on the AWS SQS queue,
+    /// then return the [`Order`] instance to await the result.
+    pub async fn create_order(
+        &self,
+        input: message::CombinedInput<Q, I>,
+    ) -> Result<(message::Ticket, Arc<OrderSegment>), CoffeeShopError> {
+        let shop = self.shop();
+
+        let is_async = input.query.is_async();
+
+        // The ticket is part of the trace of the request, or starts a new one.
+        let context = TraceContext::child_of(input.trace_context.as_ref());
+        let span = context_span!(
+            "create_order",
+            context,
+            is_async,
+            ticket = tracing::field::Empty
+        );
+
+        async move {
+            self.check_open()?;
+            self.check_queue_depth(&shop, is_async)?;
+            let reservation = self
+                .reserve_capacity(&shop, 1)
+                .await
+                .map_err(|(_, err)| err)?
+                .pop()
+                .expect("Exactly one reservation should be made for one ticket.");
+            self.validate_order(&shop, &input).await?;
+
+            self.request_count.fetch_add(1, Ordering::Relaxed);
+
+            let owner = input.principal.as_ref().map(message::Principal::owner);
+            let ticket = helpers::sqs::put_ticket(&shop, input.with_trace_context(Some(context)))
+                .instrument(tracing::info_span!("put_ticket"))
+                .await?;
+            shop.health.record(Dependency::Sqs);
+            tracing::Span::current().record("ticket", ticket.as_str());
+
+            Ok(self
+                .place_order(&shop, ticket, is_async, owner, reservation)
+                .await)
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// An internal method to create multiple tickets on the AWS SQS queue in batches,
+    /// then return the [`Order`] instances to await the results.
+    ///
+    /// The results are in the same order as the inputs; each input is validated
+    /// and enqueued independently.
+    pub async fn create_orders(
+        &self,
+        inputs: Vec<Result<message::CombinedInput<Q, I>, CoffeeShopError>>,
+    ) -> Vec<Result<(message::Ticket, Arc<OrderSegment>), CoffeeShopError>> {
+        let shop = self.shop();
+
+        let inputs = inputs
+            .into_iter()
+            .map(|input| {
+                input.and_then(|input| {
+                    self.check_open()?;
+                    self.check_queue_depth(&shop, input.query.is_async())
+                        .map(|_| input)
+                })
+            })
+            .collect::<Vec<_>>();
 
-        self.request_count.fetch_add(1, Ordering::Relaxed);
+        let validations = futures::future::join_all(inputs.iter().map(|input| async {
+            match input {
+                Ok(input) => self.validate_order(&shop, input).await,
+                // This will be reported below.
+                Err(_) => Ok(()),
+            }
+        }))
+        .await;
+
+        // Only the valid inputs count towards the capacity; the excess inputs
+        // at the end of the batch are rejected.
+        let valid_count = inputs
+            .iter()
+            .zip(validations.iter())
+            .filter(|(input, validation)| input.is_ok() && validation.is_ok())
+            .count();
+        let (mut reservations, capacity_error) =
+            match self.reserve_capacity(&shop, valid_count).await {
+                Ok(reservations) => (reservations, None),
+                Err((reservations, err)) => (reservations, Some(err.as_error_schema())),
+            };
 
-        let ticket = helpers::sqs::put_ticket(&shop, input).await?;
+        let mut results = Vec::with_capacity(inputs.len());
+        let mut valid_indices = vec![];
+        let mut valid_inputs = vec![];
 
-        Ok((ticket.clone(), shop.spawn_order(ticket).await))
+        for (index, (input, validation)) in inputs.into_iter().zip(validations).enumerate() {
+            match input.and_then(|input| validation.map(|_| input)) {
+                Ok(input) => match reservations.pop() {
+                    Some(reservation) => {
+                        valid_indices.push((
+                            index,
+                            input.query.is_async(),
+                            input.principal.as_ref().map(message::Principal::owner),
+                            reservation,
+                        ));
+                        valid_inputs.push(input);
+                        results.push(None);
+                    }
+                    None => results.push(Some(Err(CoffeeShopError::ErrorSchema(
+                        capacity_error
+                            .clone()
+                            .expect("Capacity can only be exhausted if there is an error."),
+                    )))),
+                },
+                Err(err) => results.push(Some(Err(err))),
+            }
+        }
+
+        self.request_count
+            .fetch_add(valid_inputs.len(), Ordering::Relaxed);
+
+        let tickets = helpers::sqs::put_tickets(&shop, valid_inputs).await;
+
+        let orders = futures::future::join_all(valid_indices.into_iter().zip(tickets).map(
+            |((index, is_async, owner, reservation), ticket)| {
+                let shop = &shop;
+
+                async move {
+                    match ticket {
+                        Ok(ticket) => (
+                            index,
+                            Ok(self
+                                .place_order(shop, ticket, is_async, owner, reservation)
+                                .await),
+                        ),
+                        Err(err) => (index, Err(err)),
+                    }
+                }
+            },
+        ))
+        .await;
+
+        for (index, order) in orders {
+            results[index] = Some(order);
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("All items should have been given a result by now."))
+            .collect()
     }
 
     /// An internal method to retrieve the result of a ticket from the
     /// AWS SQS queue.
-    pub async fn retrieve_order(&self, ticket: String) -> axum::response::Response {
+    ///
+    /// If the ticket was not created by this shop, the order is recovered from
+    /// DynamoDB using [`Shop::recover_order`].
+    ///
+    /// The `ticket` is the one [issued](Self::issue_ticket) to the client.
+    pub async fn retrieve_order(
+        &self,
+        ticket: String,
+        principal: Option<&message::Principal>,
+    ) -> axum::response::Response {
         let start_time = self.start_time;
 
         let shop = self.shop();
 
-        let order = shop
-            .orders
-            .get(&ticket)
-            .await
-            .ok_or_else(|| CoffeeShopError::TicketNotFound(ticket.clone()));
+        let issued_ticket = ticket;
+        let ticket = match self.redeem_ticket(&issued_ticket) {
+            Ok(ticket) => ticket,
+            Err(err) => {
+                crate::warn!(target: LOG_TARGET, "Rejected a ticket token: {err}");
+
+                return err.into_response();
+            }
+        };
+
+        let order = match shop.get_order(&ticket).await {
+            Some(order) => order,
+            None => match shop.recover_order(&ticket).await {
+                Ok(order) => order,
+                Err(err) => return Self::conceal_ticket(err, &issued_ticket).into_response(),
+            },
+        };
+
+        if let Err(err) = Self::authorize_order(&issued_ticket, order.value(), principal) {
+            crate::warn!(
+                target: LOG_TARGET,
+                "Refused access to order {ticket} owned by a different principal.",
+            );
 
-        if let Err(err) = order {
             return err.into_response();
         }
 
-        let order = order.unwrap();
-
         crate::info!(
             target: LOG_TARGET,
             "Waiting for order {} to complete...",
@@ -186,9 +1206,11 @@ where
             .map(|result| {
                 result.map(|output| {
                     // Use the `OutputResponse` to create a response.
-                    message::OutputResponse::new(ticket, &output, &start_time).into_response()
+                    message::OutputResponse::new(issued_ticket.clone(), &output, &start_time)
+                        .into_response()
                 })
             })
+            .map_err(|err| Self::conceal_ticket(err, &issued_ticket))
             // Convert any remaining errors into responses.
             .into_response()
     }
@@ -199,6 +1221,7 @@ where
         &self,
         ticket: String,
         timeout: Option<tokio::time::Duration>,
+        principal: Option<&message::Principal>,
     ) -> axum::response::Response {
         let ticket_for_log = ticket.clone();
 
@@ -213,12 +1236,12 @@ where
                     );
                     Err::<(), _>(CoffeeShopError::RetrieveTimeout(timeout)).into_response()
                 }
-                result = self.retrieve_order(ticket) => {
+                result = self.retrieve_order(ticket, principal) => {
                     result
                 }
             }
         } else {
-            self.retrieve_order(ticket).await
+            self.retrieve_order(ticket, principal).await
         }
     }
 
@@ -232,10 +1255,17 @@ where
         input: message::CombinedInput<Q, I>,
         timeout: Option<tokio::time::Duration>,
     ) -> axum::response::Response {
+        let principal = input.principal.clone();
+
         match self.create_order(input).await {
             Ok((ticket, _order)) => {
                 tokio::task::yield_now().await;
-                self.retrieve_order_with_timeout(ticket, timeout).await
+                self.retrieve_order_with_timeout(
+                    self.issue_ticket(ticket),
+                    timeout,
+                    principal.as_ref(),
+                )
+                .await
             }
             Err(err) => err.into_response(),
         }
@@ -262,22 +1292,27 @@ where
         shutdown_signal: Arc<Notify>,
         max_execution_time: Option<tokio::time::Duration>,
     ) -> Result<(), CoffeeShopError> {
-        let mut app = axum::Router::new()
-            .route(
-                "/status",
-                axum::routing::get({
-                    let arc_self = Arc::clone(self);
+        let status_route = axum::routing::get({
+            let arc_self = Arc::clone(self);
 
-                    || async move { arc_self.status().await }
-                }),
-            )
+            |principal: Option<Extension<message::Principal>>| async move {
+                arc_self
+                    .status(principal.map(|Extension(principal)| principal).as_ref())
+                    .await
+            }
+        });
+
+        let mut app = axum::Router::new()
             .route(
                 "/request",
                 axum::routing::post({
                     let arc_self = Arc::clone(self);
 
                     // Add Error handling to the request handler.
-                    |query_result: Result<Query<Q>, QueryRejection>,
+                    |headers: HeaderMap,
+                     connect_info: Option<ConnectInfo<SocketAddr>>,
+                     principal: Option<Extension<message::Principal>>,
+                     query_result: Result<Query<Q>, QueryRejection>,
                      json_result: Result<Json<I>, JsonRejection>| async move {
                         match (query_result, json_result) {
                             (Err(query_rejection), _) => {
@@ -302,27 +1337,172 @@ where
 
                                 err.into_response()
                             }
-                            (Ok(Query(params)), Ok(json)) => {
-                                // Check if the request is synchronous or asynchronous,
-                                // and call the appropriate method.
-                                // Pre-convert all errors to responses, so that the typing
-                                // is consistent.
-                                if params.is_async() {
-                                    crate::info!(
-                                        target: LOG_TARGET,
-                                        "Received an asynchronous request.",
-                                    );
-                                    arc_self
-                                        .async_request(Query(params), json)
-                                        .await
-                                        .into_response()
-                                } else {
-                                    crate::info!(
+                            (Ok(Query(params)), Ok(Json(payload))) => {
+                                arc_self
+                                    .handle_order(
+                                        &headers,
+                                        connect_info.map(|ConnectInfo(peer)| peer),
+                                        message::CombinedInput::new(params, Some(payload))
+                                            .with_principal(
+                                                principal.map(|Extension(principal)| principal),
+                                            ),
+                                    )
+                                    .await
+                            }
+                        }
+                    }
+                }),
+            )
+            .route(
+                "/tasks/:task/request",
+                axum::routing::post({
+                    let arc_self = Arc::clone(self);
+
+                    |Path(task): Path<String>,
+                     headers: HeaderMap,
+                     connect_info: Option<ConnectInfo<SocketAddr>>,
+                     principal: Option<Extension<message::Principal>>,
+                     uri: Uri,
+                     body: axum::body::Bytes| async move {
+                        let (params, payload) =
+                            match arc_self.shop().coffee_machine.decode_task(&task, &uri, &body) {
+                                Ok(decoded) => decoded,
+                                Err(err) => {
+                                    crate::warn!(
                                         target: LOG_TARGET,
-                                        "Received a blocking request.",
+                                        "Rejected a request for task {task:?}: {err:#?}",
                                     );
-                                    arc_self.request(Query(params), json).await.into_response()
+
+                                    return err.into_response();
                                 }
+                            };
+
+                        arc_self
+                            .handle_order(
+                                &headers,
+                                connect_info.map(|ConnectInfo(peer)| peer),
+                                message::CombinedInput::new(params, payload)
+                                    .with_principal(principal.map(|Extension(principal)| principal)),
+                            )
+                            .await
+                    }
+                }),
+            )
+            .route(
+                "/request/batch",
+                axum::routing::post({
+                    let arc_self = Arc::clone(self);
+
+                    |headers: HeaderMap,
+                     connect_info: Option<ConnectInfo<SocketAddr>>,
+                     principal: Option<Extension<message::Principal>>,
+                     uri: Uri,
+                     json_result: Result<
+                        Json<Vec<message::BatchRequestItem<Q, I>>>,
+                        JsonRejection,
+                    >| async move {
+                        // The shared query is optional, as long as every item has its own query.
+                        let query_result = match uri.query() {
+                            Some(query) if !query.is_empty() => {
+                                Query::<Q>::try_from_uri(&uri).map(|Query(params)| Some(params))
+                            }
+                            _ => Ok(None),
+                        };
+
+                        match (query_result, json_result) {
+                            (Err(query_rejection), _) => {
+                                let err = query_rejection.into_coffeeshop_error();
+
+                                crate::warn!(
+                                    target: LOG_TARGET,
+                                    "Query rejection for /request/batch: {:#?}",
+                                    err
+                                );
+
+                                err.into_response()
+                            }
+                            (_, Err(json_rejection)) => {
+                                let err = json_rejection.into_coffeeshop_error();
+
+                                crate::warn!(
+                                    target: LOG_TARGET,
+                                    "JSON rejection for /request/batch: {:#?}",
+                                    err
+                                );
+
+                                err.into_response()
+                            }
+                            (Ok(shared_query), Ok(Json(items))) => {
+                                crate::info!(
+                                    target: LOG_TARGET,
+                                    "Received a batch request of {count} items.",
+                                    count = items.len(),
+                                );
+
+                                // Each item costs a token; the client is identified by the
+                                // shared query if any, or the query of the first item.
+                                let rate_limit = match arc_self
+                                    .check_rate_limit(
+                                        &headers,
+                                        connect_info.map(|ConnectInfo(peer)| peer),
+                                        shared_query.as_ref().or_else(|| {
+                                            items.first().and_then(|item| item.query.as_ref())
+                                        }),
+                                        u32::try_from(items.len()).unwrap_or(u32::MAX).max(1),
+                                    )
+                                    .await
+                                {
+                                    Ok(rate_limit) => rate_limit,
+                                    Err(err) => return err.into_response(),
+                                };
+
+                                let mut response = arc_self
+                                    .batch_request(
+                                        shared_query,
+                                        items,
+                                        principal.map(|Extension(principal)| principal),
+                                        TraceContext::from_headers(&headers),
+                                    )
+                                    .await
+                                    .into_response();
+
+                                if let Some(rate_limit) = rate_limit {
+                                    rate_limit.apply_headers(&mut response);
+                                }
+
+                                response
+                            }
+                        }
+                    }
+                }),
+            )
+            .route(
+                "/retrieve/batch",
+                axum::routing::post({
+                    let arc_self = Arc::clone(self);
+
+                    |principal: Option<Extension<message::Principal>>,
+                     json_result: Result<Json<message::BatchTicketQuery>, JsonRejection>| async move {
+                        match json_result {
+                            Err(rejection) => {
+                                let err = rejection.into_coffeeshop_error();
+
+                                crate::warn!(
+                                    target: LOG_TARGET,
+                                    "JSON rejection for /retrieve/batch: {:#?}",
+                                    err
+                                );
+
+                                err.into_response()
+                            }
+                            Ok(Json(query)) => {
+                                arc_self
+                                    .batch_retrieve(
+                                        query,
+                                        principal.map(|Extension(principal)| principal),
+                                    )
+                                    .await
+                                    .into_response()
                             }
                         }
                     }
@@ -333,7 +1513,8 @@ where
                 axum::routing::get({
                     let arc_self = Arc::clone(self);
 
-                    |query_result: Result<Query<message::TicketQuery>, QueryRejection>| async move {
+                    |principal: Option<Extension<message::Principal>>,
+                     query_result: Result<Query<message::TicketQuery>, QueryRejection>| async move {
                         match query_result {
                             Err(rejection) => {
                                 let err = rejection.into_coffeeshop_error();
@@ -346,15 +1527,138 @@ where
 
                                 err.into_response()
                             }
-                            Ok(query) => arc_self.async_retrieve(query).await.into_response(),
+                            Ok(query) => arc_self
+                                .async_retrieve(query, principal.map(|Extension(principal)| principal))
+                                .await
+                                .into_response(),
+                        }
+                    }
+                }),
+            )
+            .route(
+                "/ticket/status",
+                axum::routing::get({
+                    let arc_self = Arc::clone(self);
+
+                    |principal: Option<Extension<message::Principal>>,
+                     query_result: Result<Query<message::TicketQuery>, QueryRejection>| async move {
+                        match query_result {
+                            Err(rejection) => {
+                                let err = rejection.into_coffeeshop_error();
+
+                                crate::warn!(
+                                    target: LOG_TARGET,
+                                    "Query rejection for /ticket/status: {:#?}",
+                                    err
+                                );
+
+                                err.into_response()
+                            }
+                            Ok(query) => arc_self
+                                .ticket_status(query, principal.map(|Extension(principal)| principal))
+                                .await
+                                .into_response(),
                         }
                     }
                 }),
             );
 
+        let metrics_route = axum::routing::get({
+            let arc_self = Arc::clone(self);
+
+            || async move { arc_self.metrics().await }
+        });
+
+        let public_status = self.shop().config.public_status;
+        let public_metrics = self.shop().config.public_metrics;
+        if !public_status {
+            app = app.route("/status", status_route.clone());
+        }
+        if !public_metrics {
+            app = app.route("/metrics", metrics_route.clone());
+        }
+
         // Add additional routes to the app.
         app = additional_routes.fold(app, |app, (path, handler)| app.route(path, handler));
 
+        // Authenticate all the routes so far, if enabled.
+        if let Some(authenticator) = helpers::auth::Authenticator::from_config(&self.shop().config)?
+        {
+            let authenticator = Arc::new(authenticator);
+
+            app = app.route_layer(axum::middleware::from_fn(
+                move |mut request: axum::extract::Request, next: axum::middleware::Next| {
+                    let authenticator = Arc::clone(&authenticator);
+
+                    async move {
+                        match authenticator.authenticate(request.headers()) {
+                            Ok(principal) => {
+                                request.extensions_mut().insert(principal);
+                                next.run(request).await
+                            }
+                            Err(err) => {
+                                crate::warn!(
+                                    target: LOG_TARGET,
+                                    "Rejected an unauthenticated request for {}: {}",
+                                    request.uri(),
+                                    err
+                                );
+
+                                err.into_response()
+                            }
+                        }
+                    }
+                },
+            ));
+        }
+
+        if public_status {
+            app = app.route("/status", status_route);
+        }
+        if public_metrics {
+            app = app.route("/metrics", metrics_route);
+        }
+
+        // The health probes are never authenticated, as load balancers cannot.
+        app = app
+            .route(
+                "/health/live",
+                axum::routing::get({
+                    let arc_self = Arc::clone(self);
+
+                    || async move { arc_self.live().await }
+                }),
+            )
+            .route(
+                "/health/ready",
+                axum::routing::get({
+                    let arc_self = Arc::clone(self);
+
+                    || async move { arc_self.ready().await }
+                }),
+            );
+
+        // Record the metrics of all the routes so far, by their matched paths.
+        app = app.route_layer(axum::middleware::from_fn(
+            |request: axum::extract::Request, next: axum::middleware::Next| async move {
+                let route = request
+                    .extensions()
+                    .get::<axum::extract::MatchedPath>()
+                    .map(|path| path.as_str().to_owned())
+                    .unwrap_or_default();
+                let started = tokio::time::Instant::now();
+
+                let response = next.run(request).await;
+                helpers::metrics::metrics().record_request(
+                    &route,
+                    response.status(),
+                    started.elapsed(),
+                );
+
+                response
+            },
+        ));
+
         // 404 Fallback.
         app = app.fallback(|uri| async {
             crate::warn!(
@@ -376,6 +1680,15 @@ where
             CoffeeShopError::InvalidMethod
         });
 
+        // The requests are served in their own tasks, outside of the log context of
+        // the shop; carry it over to every request.
+        let log_context = LogContext::current();
+        app = app.layer(axum::middleware::from_fn(
+            move |request: axum::extract::Request, next: axum::middleware::Next| {
+                log_context.clone().scope(next.run(request))
+            },
+        ));
+
         // Add the trace and timeout layers to the app.
         if let Some(max_execution_time) = max_execution_time {
             app = app.layer((
@@ -394,8 +1707,12 @@ where
                 )
             })?;
 
-        let server = axum::serve(listener, app)
-            .with_graceful_shutdown(async move { shutdown_signal.notified().await });
+        // The peer address is needed for rate limiting by client IP.
+        let server = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { shutdown_signal.notified().await });
 
         let result = tokio::try_join!(server, async {
             crate::info!(